@@ -466,6 +466,97 @@ exports.resetToOriginalConfig = () => {
   writeJSON(userSettingsPath, existingSettings);
 };
 
+// ============================================
+// 服务商预设
+// ============================================
+
+// 模型映射对应的环境变量
+const PRESET_MODEL_ENV = {
+  default: 'ANTHROPIC_MODEL',
+  opus: 'ANTHROPIC_DEFAULT_OPUS_MODEL',
+  sonnet: 'ANTHROPIC_DEFAULT_SONNET_MODEL',
+  haiku: 'ANTHROPIC_DEFAULT_HAIKU_MODEL',
+  smallFast: 'ANTHROPIC_SMALL_FAST_MODEL'
+};
+
+/**
+ * 读取用户预设文件，每个文件包含一个预设或预设数组
+ */
+function readUserPresets() {
+  const presetsDir = path.join(getAppConfigDir(), 'providers');
+  if (!fs.existsSync(presetsDir)) {
+    return [];
+  }
+
+  const presets = [];
+  const files = fs.readdirSync(presetsDir).filter((file) => file.endsWith('.json')).sort();
+  for (const file of files) {
+    const filePath = path.join(presetsDir, file);
+    try {
+      const content = readJSON(filePath);
+      for (const preset of Array.isArray(content) ? content : [content]) {
+        if (!preset.id || !/^https?:\/\//.test(preset.baseUrl || '')) {
+          throw new Error(`preset '${preset.id}' has no id or an invalid base URL`);
+        }
+        presets.push({ ...preset, source: 'user' });
+      }
+    } catch (error) {
+      console.warn(`Skipping provider presets in ${filePath}:`, error.message);
+    }
+  }
+  return presets;
+}
+
+/**
+ * 内置预设加上用户预设，同 id 的用户预设替换内置预设
+ */
+exports.listProviderPresets = () => {
+  const presets = require('./providers.json').map((preset) => ({
+    models: {},
+    secrets: [],
+    ...preset,
+    source: 'builtin'
+  }));
+
+  for (const preset of readUserPresets()) {
+    const index = presets.findIndex((existing) => existing.id === preset.id);
+    const normalized = { models: {}, secrets: [], ...preset };
+    if (index >= 0) {
+      presets[index] = normalized;
+    } else {
+      presets.push(normalized);
+    }
+  }
+  return presets;
+};
+
+/**
+ * 根据预设创建配置，secrets 为预设中每个密钥环境变量的值
+ */
+exports.createConfigFromPreset = (presetId, title, secrets) => {
+  const preset = exports.listProviderPresets().find((p) => p.id === presetId);
+  if (!preset) {
+    throw new Error(`Provider preset '${presetId}' not found`);
+  }
+
+  const env = { ...(preset.env || {}), ANTHROPIC_BASE_URL: preset.baseUrl };
+  for (const [key, envName] of Object.entries(PRESET_MODEL_ENV)) {
+    if (preset.models[key]) {
+      env[envName] = preset.models[key];
+    }
+  }
+  for (const field of preset.secrets) {
+    const value = ((secrets || {})[field.env] || '').trim();
+    if (!value) {
+      throw new Error(`${field.label} is required for ${preset.name}`);
+    }
+    env[field.env] = value;
+  }
+
+  const storeTitle = (title || '').trim() || preset.name;
+  return exports.createConfig(generateId(6), storeTitle, { env });
+};
+
 // ============================================
 // MCP 服务器管理 (Phase 2.3)
 // ============================================
@@ -713,19 +804,24 @@ exports.readProjectUsageFiles = () => {
   const homeDir = getHomeDir();
   const projectsDir = path.join(homeDir, '.claude/projects');
 
+  const allRecords = [];
+  const failures = [];
+  let skippedLines = 0;
+
   if (!fs.existsSync(projectsDir)) {
-    return [];
+    return { records: allRecords, skipped_lines: skippedLines, failures };
   }
 
-  const allRecords = [];
   const jsonlFiles = findJsonlFiles(projectsDir);
 
   for (const filePath of jsonlFiles) {
     try {
       const content = fs.readFileSync(filePath, 'utf-8');
       const lines = content.split('\n');
+      // Claude Code may still be writing the last line, it is not an error
+      lines.pop();
 
-      for (const line of lines) {
+      for (const [lineIndex, line] of lines.entries()) {
         if (!line.trim()) continue;
 
         try {
@@ -745,11 +841,8 @@ exports.readProjectUsageFiles = () => {
           const usageObj = jsonValue.usage || (jsonValue.message && jsonValue.message.usage);
 
           if (usageObj) {
-            usage = {
-              input_tokens: usageObj.input_tokens,
-              cache_read_input_tokens: usageObj.cache_read_input_tokens,
-              output_tokens: usageObj.output_tokens
-            };
+            // Keep every field, including ones added by newer Claude Code versions
+            usage = { ...usageObj };
           }
 
           // Only include records with valid data
@@ -762,12 +855,16 @@ exports.readProjectUsageFiles = () => {
                 uuid,
                 timestamp,
                 model: model || null,
-                usage
+                usage,
+                project_path: jsonValue.cwd || path.basename(path.dirname(filePath)),
+                session_id: jsonValue.sessionId || path.basename(filePath, '.jsonl')
               });
             }
           }
         } catch (e) {
-          // Skip invalid JSON lines
+          // Skip invalid JSON lines, reporting where they are
+          skippedLines++;
+          failures.push({ path: filePath, line: lineIndex + 1, message: e.message });
         }
       }
     } catch (e) {
@@ -775,7 +872,133 @@ exports.readProjectUsageFiles = () => {
     }
   }
 
-  return allRecords;
+  return { records: allRecords, skipped_lines: skippedLines, failures };
+};
+
+const pad2 = (n) => String(n).padStart(2, '0');
+
+// 本地时间的日期，YYYY-MM-DD
+function localDate(date) {
+  return `${date.getFullYear()}-${pad2(date.getMonth() + 1)}-${pad2(date.getDate())}`;
+}
+
+// 与 Tauri 的 usage_query 相同：RFC 3339 时间或本地日期
+function parseUsageBound(value) {
+  const match = /^(\d{4})-(\d{2})-(\d{2})$/.exec(value);
+  const date = match ? new Date(Number(match[1]), Number(match[2]) - 1, Number(match[3])) : new Date(value);
+  if (Number.isNaN(date.getTime())) {
+    throw new Error(`Invalid time '${value}', use a date like 2025-01-31 or an RFC 3339 timestamp`);
+  }
+  return date;
+}
+
+// 时间段的起点，周从周一开始
+function usagePeriod(dimension, date) {
+  switch (dimension) {
+    case 'hour':
+      return `${localDate(date)}T${pad2(date.getHours())}:00`;
+    case 'week': {
+      const monday = new Date(date.getFullYear(), date.getMonth(), date.getDate() - ((date.getDay() + 6) % 7));
+      return localDate(monday);
+    }
+    case 'month':
+      return `${date.getFullYear()}-${pad2(date.getMonth() + 1)}`;
+    default:
+      return localDate(date);
+  }
+}
+
+function emptyUsageTotals() {
+  return {
+    requests: 0,
+    input_tokens: 0,
+    output_tokens: 0,
+    cache_creation_input_tokens: 0,
+    cache_read_input_tokens: 0,
+    web_search_requests: 0,
+    cost_usd: 0,
+    unpriced_requests: 0
+  };
+}
+
+function addUsage(totals, record) {
+  const usage = record.usage || {};
+  totals.requests += 1;
+  totals.input_tokens += usage.input_tokens || 0;
+  totals.output_tokens += usage.output_tokens || 0;
+  totals.cache_creation_input_tokens += usage.cache_creation_input_tokens || 0;
+  totals.cache_read_input_tokens += usage.cache_read_input_tokens || 0;
+  totals.web_search_requests += (usage.server_tool_use && usage.server_tool_use.web_search_requests) || 0;
+  // 没有价格表，所有请求都不计费用
+  totals.unpriced_requests += 1;
+}
+
+/**
+ * 按时间段、模型、项目和会话汇总使用量，与 Tauri 的 query_usage 一致
+ */
+exports.queryUsage = (query = {}) => {
+  const scan = exports.readProjectUsageFiles();
+  const groupBy = query.group_by || [];
+  const timeDimensions = groupBy.filter((dimension) => ['hour', 'day', 'week', 'month'].includes(dimension));
+  if (timeDimensions.length > 1) {
+    throw new Error('Usage can only be grouped by one time period at a time');
+  }
+  const timeDimension = timeDimensions[0];
+  const from = query.from ? parseUsageBound(query.from) : null;
+  const to = query.to ? parseUsageBound(query.to) : null;
+  const needsTime = Boolean(from || to || timeDimension);
+  const matches = (filter, value) => !filter || filter.length === 0 || (value != null && filter.includes(value));
+  const grouped = (dimension, value) => (groupBy.includes(dimension) ? value || 'unknown' : undefined);
+
+  const total = emptyUsageTotals();
+  const groups = new Map();
+
+  for (const record of scan.records) {
+    if (!matches(query.models, record.model) || !matches(query.projects, record.project_path) || !matches(query.sessions, record.session_id)) {
+      continue;
+    }
+
+    const date = new Date(record.timestamp);
+    const hasTime = !Number.isNaN(date.getTime());
+    if (needsTime && (!hasTime || (from && date < from) || (to && date >= to))) {
+      continue;
+    }
+
+    const group = {
+      period: timeDimension && hasTime ? usagePeriod(timeDimension, date) : undefined,
+      model: grouped('model', record.model),
+      project_path: grouped('project', record.project_path),
+      session_id: grouped('session', record.session_id)
+    };
+    const key = JSON.stringify([group.period, group.model, group.project_path, group.session_id]);
+    if (!groups.has(key)) {
+      groups.set(key, { ...group, totals: emptyUsageTotals() });
+    }
+
+    addUsage(total, record);
+    addUsage(groups.get(key).totals, record);
+  }
+
+  const sortKey = (group) => [group.period, group.model, group.project_path, group.session_id].map((value) => value || '').join('\u0000');
+  return {
+    total,
+    groups: [...groups.values()].sort((a, b) => (sortKey(a) < sortKey(b) ? -1 : sortKey(a) > sortKey(b) ? 1 : 0)),
+    skipped_lines: scan.skipped_lines,
+    failures: scan.failures
+  };
+};
+
+/**
+ * 使用量费用，没有价格表，所以只列出未定价的模型
+ */
+exports.getUsageCosts = () => {
+  const models = new Set();
+  for (const record of exports.readProjectUsageFiles().records) {
+    if (record.model) {
+      models.add(record.model);
+    }
+  }
+  return { total_usd: 0, by_day: {}, by_model: {}, by_project: {}, unpriced_models: [...models].sort() };
 };
 
 // ============================================
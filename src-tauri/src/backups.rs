@@ -0,0 +1,387 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::AppError;
+use crate::paths;
+use crate::persistence::write_atomic;
+
+const BACKUPS_DIR: &str = "backups";
+
+// Older snapshots are deleted beyond this
+const MAX_BACKUPS: usize = 20;
+
+/// Reason of the snapshot taken before the app first touched the Claude
+/// config. It is what a restore needs most, so retention never deletes it.
+pub const INITIAL_REASON: &str = "initial";
+
+// What gets snapshotted inside the Claude config directory. Session
+// transcripts (projects/) are left out, they are large and not configuration.
+const CLAUDE_DIR_ENTRIES: &[&str] = &["settings.json", "CLAUDE.md", "commands", "agents"];
+
+// Archive prefixes, mapped back to their locations on restore
+const CLAUDE_DIR_PREFIX: &str = "claude";
+const CLAUDE_JSON_ENTRY: &str = "claude.json";
+const MANIFEST_ENTRY: &str = "manifest.json";
+
+const ZSTD_LEVEL: i32 = 19;
+
+// Archive path and contents of each file in a backup
+type ArchiveFiles = Vec<(String, Vec<u8>)>;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct BackupEntry {
+    // Path inside the archive, e.g. `claude/commands/review.md`
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupManifest {
+    pub id: String,
+    pub created_at: String,
+    pub reason: String,
+    // Where the files came from, for reference
+    pub claude_dir: String,
+    pub claude_json_path: String,
+    pub entries: Vec<BackupEntry>,
+    // Checksum of the archive itself, kept next to it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_sha256: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupVerification {
+    pub id: String,
+    pub ok: bool,
+    pub archive_ok: bool,
+    // Entries whose contents don't match the manifest
+    pub corrupted: Vec<String>,
+    // Entries listed in the manifest but absent from the archive
+    pub missing: Vec<String>,
+}
+
+fn backups_dir() -> Result<PathBuf, AppError> {
+    Ok(paths::app_config_dir()?.join(BACKUPS_DIR))
+}
+
+fn archive_path(id: &str) -> Result<PathBuf, AppError> {
+    validate_id(id)?;
+    Ok(backups_dir()?.join(format!("{}.tar.zst", id)))
+}
+
+fn manifest_path(id: &str) -> Result<PathBuf, AppError> {
+    validate_id(id)?;
+    Ok(backups_dir()?.join(format!("{}.json", id)))
+}
+
+fn validate_id(id: &str) -> Result<(), AppError> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid backup id '{}'", id).into());
+    }
+    Ok(())
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Snapshot the Claude configuration into a new archive and apply retention.
+pub fn create(reason: &str) -> Result<BackupManifest, AppError> {
+    let claude_dir = paths::claude_dir()?;
+    let claude_json_path = paths::claude_json_path()?;
+
+    let mut files: ArchiveFiles = Vec::new();
+    for entry in CLAUDE_DIR_ENTRIES {
+        collect(&claude_dir.join(entry), &format!("{}/{}", CLAUDE_DIR_PREFIX, entry), &mut files)?;
+    }
+    collect(&claude_json_path, CLAUDE_JSON_ENTRY, &mut files)?;
+
+    if files.is_empty() {
+        return Err(AppError::not_found("There are no Claude configuration files to back up")
+            .with_path(&claude_dir));
+    }
+
+    let now = chrono::Utc::now();
+    let id = format!("{}-{}", now.format("%Y%m%d-%H%M%S"), nanoid::nanoid!(4));
+    let mut manifest = BackupManifest {
+        id: id.clone(),
+        created_at: now.to_rfc3339(),
+        reason: reason.to_string(),
+        claude_dir: claude_dir.display().to_string(),
+        claude_json_path: claude_json_path.display().to_string(),
+        entries: files
+            .iter()
+            .map(|(path, contents)| BackupEntry {
+                path: path.clone(),
+                size: contents.len() as u64,
+                sha256: sha256_hex(contents),
+            })
+            .collect(),
+        archive_sha256: None,
+    };
+
+    let archive = build_archive(&manifest, &files)?;
+    manifest.archive_sha256 = Some(sha256_hex(&archive));
+
+    let archive_file = archive_path(&id)?;
+    write_atomic(&archive_file, &archive)
+        .map_err(|e| AppError::io("Failed to write backup archive", &archive_file, e))?;
+    write_manifest(&manifest)?;
+
+    if let Err(e) = apply_retention() {
+        eprintln!("Warning: Failed to remove old backups: {}", e);
+    }
+
+    Ok(manifest)
+}
+
+// Read a file, or every file below a directory, into `files`
+fn collect(source: &Path, archive_path: &str, files: &mut ArchiveFiles) -> Result<(), AppError> {
+    let Ok(metadata) = fs::metadata(source) else {
+        return Ok(());
+    };
+
+    if metadata.is_file() {
+        let contents = fs::read(source).map_err(|e| AppError::io("Failed to read file for backup", source, e))?;
+        files.push((archive_path.to_string(), contents));
+    } else if metadata.is_dir() {
+        let mut entries: Vec<_> = fs::read_dir(source)
+            .map_err(|e| AppError::io("Failed to read directory for backup", source, e))?
+            .filter_map(Result::ok)
+            .collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            // Symlinked directories could loop back on themselves
+            if entry.file_type().map(|t| t.is_symlink()).unwrap_or(false) && entry.path().is_dir() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            collect(&entry.path(), &format!("{}/{}", archive_path, name), files)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn build_archive(manifest: &BackupManifest, files: &[(String, Vec<u8>)]) -> Result<Vec<u8>, AppError> {
+    let encoder = zstd::Encoder::new(Vec::new(), ZSTD_LEVEL)
+        .map_err(|e| format!("Failed to start compression: {}", e))?;
+    let mut builder = tar::Builder::new(encoder);
+
+    let manifest_json = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize backup manifest: {}", e))?;
+    append(&mut builder, MANIFEST_ENTRY, &manifest_json)?;
+    for (path, contents) in files {
+        append(&mut builder, path, contents)?;
+    }
+
+    let encoder = builder
+        .into_inner()
+        .map_err(|e| format!("Failed to finish backup archive: {}", e))?;
+    Ok(encoder
+        .finish()
+        .map_err(|e| format!("Failed to finish compression: {}", e))?)
+}
+
+fn append<W: std::io::Write>(builder: &mut tar::Builder<W>, path: &str, contents: &[u8]) -> Result<(), AppError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o600);
+    header.set_mtime(chrono::Utc::now().timestamp() as u64);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, path, contents)
+        .map_err(|e| format!("Failed to add {} to backup archive: {}", path, e).into())
+}
+
+fn write_manifest(manifest: &BackupManifest) -> Result<(), AppError> {
+    let path = manifest_path(&manifest.id)?;
+    let json_content = serde_json::to_string_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize backup manifest: {}", e))?;
+    write_atomic(&path, json_content).map_err(|e| AppError::io("Failed to write backup manifest", &path, e))
+}
+
+fn read_manifest(id: &str) -> Result<BackupManifest, AppError> {
+    let path = manifest_path(id)?;
+    let content = fs::read_to_string(&path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => AppError::not_found(format!("Backup '{}' not found", id)).with_path(&path),
+        _ => AppError::io("Failed to read backup manifest", &path, e),
+    })?;
+    serde_json::from_str(&content).map_err(|e| AppError::parse("Failed to parse backup manifest", &path, e))
+}
+
+/// All backups, newest first.
+pub fn list() -> Result<Vec<BackupManifest>, AppError> {
+    let dir = backups_dir()?;
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut manifests = Vec::new();
+    for entry in fs::read_dir(&dir).map_err(|e| AppError::io("Failed to read backups directory", &dir, e))? {
+        let path = entry.map_err(|e| AppError::io("Failed to read backups directory", &dir, e))?.path();
+        let Some(id) = path.file_name().and_then(|name| name.to_str()).and_then(|name| name.strip_suffix(".json")) else {
+            continue;
+        };
+
+        match read_manifest(id) {
+            Ok(manifest) => manifests.push(manifest),
+            Err(e) => eprintln!("Warning: Skipping backup {}: {}", id, e),
+        }
+    }
+
+    manifests.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(manifests)
+}
+
+// Every file in the archive, by path
+fn read_archive(id: &str) -> Result<(Vec<u8>, ArchiveFiles), AppError> {
+    let path = archive_path(id)?;
+    let archive = fs::read(&path).map_err(|e| AppError::io("Failed to read backup archive", &path, e))?;
+
+    let decoder = zstd::Decoder::new(archive.as_slice())
+        .map_err(|e| AppError::io("Failed to decompress backup archive", &path, e))?;
+    let mut tar = tar::Archive::new(decoder);
+    let mut files = Vec::new();
+    for entry in tar.entries().map_err(|e| AppError::io("Failed to read backup archive", &path, e))? {
+        let mut entry = entry.map_err(|e| AppError::io("Failed to read backup archive", &path, e))?;
+        let entry_path = entry
+            .path()
+            .map_err(|e| AppError::io("Failed to read backup archive", &path, e))?
+            .to_string_lossy()
+            .to_string();
+        let mut contents = Vec::new();
+        entry
+            .read_to_end(&mut contents)
+            .map_err(|e| AppError::io("Failed to read backup archive", &path, e))?;
+        files.push((entry_path, contents));
+    }
+
+    Ok((archive, files))
+}
+
+/// Check the archive and every file in it against the manifest checksums.
+pub fn verify(id: &str) -> Result<BackupVerification, AppError> {
+    let manifest = read_manifest(id)?;
+    let (archive, files) = read_archive(id)?;
+
+    let archive_ok = manifest
+        .archive_sha256
+        .as_ref()
+        .map(|expected| *expected == sha256_hex(&archive))
+        .unwrap_or(true);
+
+    let mut corrupted = Vec::new();
+    let mut missing = Vec::new();
+    for entry in &manifest.entries {
+        match files.iter().find(|(path, _)| *path == entry.path) {
+            Some((_, contents)) if sha256_hex(contents) == entry.sha256 => {}
+            Some(_) => corrupted.push(entry.path.clone()),
+            None => missing.push(entry.path.clone()),
+        }
+    }
+
+    Ok(BackupVerification {
+        id: id.to_string(),
+        ok: archive_ok && corrupted.is_empty() && missing.is_empty(),
+        archive_ok,
+        corrupted,
+        missing,
+    })
+}
+
+/// Restore a backup, or only the entries under the given paths
+/// (e.g. `claude/commands` or `claude.json`). Files that were added since
+/// the backup are left in place. The current state is backed up first.
+pub fn restore(id: &str, only: Option<&[String]>) -> Result<Vec<String>, AppError> {
+    let manifest = read_manifest(id)?;
+    let (_, files) = read_archive(id)?;
+
+    let selected: Vec<&BackupEntry> = manifest
+        .entries
+        .iter()
+        .filter(|entry| match only {
+            Some(prefixes) => prefixes.iter().any(|prefix| {
+                let prefix = prefix.trim_end_matches('/');
+                entry.path == prefix || entry.path.starts_with(&format!("{}/", prefix))
+            }),
+            None => true,
+        })
+        .collect();
+    if selected.is_empty() {
+        return Err(AppError::not_found("Nothing in this backup matches the selection"));
+    }
+
+    // Refuse to restore anything that doesn't match its checksum
+    let mut restorable = Vec::new();
+    for entry in &selected {
+        let (_, contents) = files
+            .iter()
+            .find(|(path, _)| *path == entry.path)
+            .ok_or_else(|| format!("Backup is missing {}", entry.path))?;
+        if sha256_hex(contents) != entry.sha256 {
+            return Err(AppError::conflict(format!(
+                "{} in backup {} is corrupted, nothing was restored",
+                entry.path, id
+            )));
+        }
+        restorable.push((destination(&entry.path)?, contents));
+    }
+
+    if let Err(e) = create("pre-restore") {
+        // Nothing to back up is fine, anything else means we'd lose data
+        if e.kind != crate::error::ErrorKind::NotFound {
+            return Err(e.context("Failed to back up current configuration before restoring"));
+        }
+    }
+
+    let mut restored = Vec::new();
+    for (destination, contents) in restorable {
+        write_atomic(&destination, contents)
+            .map_err(|e| AppError::io("Failed to restore file", &destination, e))?;
+        restored.push(destination.display().to_string());
+    }
+
+    Ok(restored)
+}
+
+// Where an archive path is restored to, refusing anything that escapes its root
+fn destination(archive_path: &str) -> Result<PathBuf, AppError> {
+    if archive_path == CLAUDE_JSON_ENTRY {
+        return Ok(paths::claude_json_path()?);
+    }
+
+    let relative = archive_path
+        .strip_prefix(&format!("{}/", CLAUDE_DIR_PREFIX))
+        .map(Path::new)
+        .filter(|relative| relative.components().all(|c| matches!(c, Component::Normal(_))))
+        .ok_or_else(|| format!("Refusing to restore unexpected path '{}'", archive_path))?;
+
+    Ok(paths::claude_dir()?.join(relative))
+}
+
+pub fn delete(id: &str) -> Result<(), AppError> {
+    // Make sure it exists so a typo doesn't silently succeed
+    read_manifest(id)?;
+
+    for path in [archive_path(id)?, manifest_path(id)?] {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| AppError::io("Failed to delete backup", &path, e))?;
+        }
+    }
+    Ok(())
+}
+
+fn apply_retention() -> Result<(), AppError> {
+    let prunable = list()?.into_iter().filter(|manifest| manifest.reason != INITIAL_REASON);
+    for manifest in prunable.skip(MAX_BACKUPS) {
+        delete(&manifest.id)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,517 @@
+// Headless command line interface over the same config store as the app,
+// for SSH sessions and scripts (e.g. `ccmate use work` from dotfiles).
+
+use cc_config_lib::bundle::{ConflictStrategy, SecretMode};
+use cc_config_lib::commands::{self, ConfigDiffDocuments, ConfigStore};
+use cc_config_lib::connection::ConnectionStatus;
+use cc_config_lib::error::AppError;
+use cc_config_lib::json_diff;
+use cc_config_lib::usage_query::{UsageDimension, UsageQuery, UsageTotals};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::Value;
+use similar::TextDiff;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "ccmate", version, about = "Manage Claude Code configurations from the terminal")]
+struct Cli {
+    /// Print machine-readable JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Passphrase of a passphrase-protected secret vault
+    #[arg(long, global = true, env = "CCMATE_VAULT_PASSPHRASE", hide_env_values = true)]
+    vault_passphrase: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List config stores
+    List,
+    /// Activate a config store
+    Use {
+        /// Store id or title
+        store: String,
+    },
+    /// Show a store's settings, or the active one
+    Show {
+        /// Store id or title
+        store: Option<String>,
+    },
+    /// Compare two stores, or a store with the live settings.json
+    Diff {
+        /// Store id or title
+        from: String,
+        /// Store id or title, defaults to the live settings.json
+        to: Option<String>,
+    },
+    /// Check that a store's base URL, token and model work
+    Test {
+        /// Store id or title, defaults to the active one
+        store: Option<String>,
+    },
+    /// Manage global MCP servers
+    Mcp {
+        #[command(subcommand)]
+        command: McpCommand,
+    },
+    /// Manage the notification hooks CC Mate installs into Claude Code
+    Hooks {
+        #[command(subcommand)]
+        command: HooksCommand,
+    },
+    /// Token usage totals, per model unless grouped otherwise
+    Usage {
+        /// Start of the range, a date (2025-01-31) or RFC 3339 timestamp
+        #[arg(long)]
+        from: Option<String>,
+        /// End of the range (exclusive)
+        #[arg(long)]
+        to: Option<String>,
+        /// Group by (repeatable)
+        #[arg(long = "by", value_enum, default_values_t = [By::Model])]
+        by: Vec<By>,
+        /// Only this model (repeatable)
+        #[arg(long = "model")]
+        models: Vec<String>,
+        /// Only this project path (repeatable)
+        #[arg(long = "project")]
+        projects: Vec<String>,
+    },
+    /// Write config stores to a bundle file, or stdout
+    Export {
+        file: Option<String>,
+        /// Store id or title to export, with the stores it extends (repeatable, defaults to all)
+        #[arg(long = "store")]
+        stores: Vec<String>,
+        /// What to do with API tokens
+        #[arg(long, value_enum, default_value_t = Secrets::Redact)]
+        secrets: Secrets,
+        /// Passphrase for `--secrets encrypt`
+        #[arg(long, env = "CCMATE_BUNDLE_PASSPHRASE", hide_env_values = true)]
+        passphrase: Option<String>,
+    },
+    /// Add config stores from a bundle file
+    Import {
+        file: String,
+        /// Passphrase of a bundle with encrypted secrets
+        #[arg(long, env = "CCMATE_BUNDLE_PASSPHRASE", hide_env_values = true)]
+        passphrase: Option<String>,
+        /// How to handle stores whose id or title already exists
+        #[arg(long, value_enum, default_value_t = OnConflict::Rename)]
+        on_conflict: OnConflict,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Secrets {
+    Redact,
+    Include,
+    Encrypt,
+}
+
+impl From<Secrets> for SecretMode {
+    fn from(secrets: Secrets) -> SecretMode {
+        match secrets {
+            Secrets::Redact => SecretMode::Redact,
+            Secrets::Include => SecretMode::Include,
+            Secrets::Encrypt => SecretMode::Encrypt,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OnConflict {
+    Rename,
+    Overwrite,
+    Skip,
+}
+
+impl From<OnConflict> for ConflictStrategy {
+    fn from(on_conflict: OnConflict) -> ConflictStrategy {
+        match on_conflict {
+            OnConflict::Rename => ConflictStrategy::Rename,
+            OnConflict::Overwrite => ConflictStrategy::Overwrite,
+            OnConflict::Skip => ConflictStrategy::Skip,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum By {
+    Hour,
+    Day,
+    Week,
+    Month,
+    Model,
+    Project,
+    Session,
+}
+
+impl From<By> for UsageDimension {
+    fn from(by: By) -> UsageDimension {
+        match by {
+            By::Hour => UsageDimension::Hour,
+            By::Day => UsageDimension::Day,
+            By::Week => UsageDimension::Week,
+            By::Month => UsageDimension::Month,
+            By::Model => UsageDimension::Model,
+            By::Project => UsageDimension::Project,
+            By::Session => UsageDimension::Session,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum McpCommand {
+    /// List global MCP servers
+    List,
+    /// Add or replace a global MCP server
+    Add {
+        name: String,
+        /// Full server config as JSON, e.g. '{"type":"http","url":"..."}'
+        #[arg(long, conflicts_with_all = ["command", "url"])]
+        json: Option<String>,
+        /// Command starting a stdio server
+        #[arg(long, conflicts_with = "url")]
+        command: Option<String>,
+        /// Argument passed to the command (repeatable)
+        #[arg(long = "arg", requires = "command")]
+        args: Vec<String>,
+        /// KEY=VALUE environment variable for the server (repeatable)
+        #[arg(long = "env")]
+        env: Vec<String>,
+        /// URL of an HTTP server
+        #[arg(long)]
+        url: Option<String>,
+    },
+    /// Remove a global MCP server
+    Rm { name: String },
+}
+
+#[derive(Subcommand)]
+enum HooksCommand {
+    Install,
+    Remove,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let json = cli.json;
+
+    match tauri::async_runtime::block_on(run(cli)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if json {
+                eprintln!("{}", serde_json::to_string_pretty(&e).unwrap_or_else(|_| e.message.clone()));
+            } else {
+                eprintln!("error: {}", e);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), AppError> {
+    let json = cli.json;
+
+    // Nothing can ask for the passphrase later, so the vault is unlocked up front
+    if let Some(passphrase) = &cli.vault_passphrase {
+        let status = commands::get_vault_status().await?;
+        if status.passphrase_protected && !status.unlocked {
+            commands::unlock_vault(passphrase.clone()).await?;
+        }
+    }
+
+    match cli.command {
+        Command::List => {
+            let stores = commands::get_stores().await?;
+            if json {
+                return print_json(&stores);
+            }
+            for store in &stores {
+                let marker = if store.using { "*" } else { " " };
+                let scope = match &store.project {
+                    Some(project) => format!("  ({})", project.path),
+                    None => String::new(),
+                };
+                println!("{} {:<8} {}{}", marker, store.id, store.title, scope);
+            }
+            Ok(())
+        }
+        Command::Use { store } => {
+            let store = find_store(&store).await?;
+            commands::set_using_config(store.id.clone()).await?;
+            if json {
+                return print_json(&commands::get_store(store.id).await?);
+            }
+            println!("Switched to \"{}\"", store.title);
+            Ok(())
+        }
+        Command::Show { store } => {
+            let store = match store {
+                Some(store) => find_store(&store).await?,
+                None => commands::get_current_store()
+                    .await?
+                    .ok_or_else(|| AppError::not_found("No config store is in use"))?,
+            };
+            if json {
+                return print_json(&store);
+            }
+            println!("{} ({})", store.title, store.id);
+            print_json(&store.settings)
+        }
+        Command::Diff { from, to } => {
+            let from = find_store(&from).await?;
+            let to = match to {
+                Some(to) => Some(find_store(&to).await?.id),
+                None => None,
+            };
+
+            let documents = commands::diff_config_documents(from.id, to).await?;
+            if json {
+                return print_json(&json_diff::diff(&documents.from, &documents.to));
+            }
+            print_unified_diff(&documents)
+        }
+        Command::Test { store } => {
+            let store = match store {
+                Some(store) => find_store(&store).await?,
+                None => commands::get_current_store()
+                    .await?
+                    .ok_or_else(|| AppError::not_found("No config store is in use"))?,
+            };
+
+            let report = commands::test_config_connection(store.id).await?;
+            if json {
+                print_json(&report)?;
+            } else {
+                let status = serde_json::to_value(report.status)
+                    .ok()
+                    .and_then(|status| status.as_str().map(str::to_string))
+                    .unwrap_or_default();
+                let http_status = report.http_status.map(|code| format!(" HTTP {}", code)).unwrap_or_default();
+                let latency = report.latency_ms.map(|ms| format!(" in {} ms", ms)).unwrap_or_default();
+                println!("{}{}{} ({} via {})", status, http_status, latency, report.model, report.base_url);
+                if let Some(message) = &report.message {
+                    println!("{}", message);
+                }
+            }
+
+            if report.status == ConnectionStatus::Ok {
+                Ok(())
+            } else {
+                Err(AppError::network(format!("Connection check for \"{}\" failed", store.title)))
+            }
+        }
+        Command::Mcp { command } => run_mcp(command, json).await,
+        Command::Hooks { command } => {
+            match command {
+                HooksCommand::Install => commands::add_claude_code_hook().await?,
+                HooksCommand::Remove => commands::remove_claude_code_hook().await?,
+            }
+            if json {
+                return print_json(&serde_json::json!({ "ok": true }));
+            }
+            Ok(())
+        }
+        Command::Usage { from, to, by, models, projects } => {
+            let query = UsageQuery {
+                from,
+                to,
+                group_by: by.iter().map(|by| UsageDimension::from(*by)).collect(),
+                models,
+                projects,
+                sessions: vec![],
+            };
+            let result = commands::query_usage(query).await?;
+            if json {
+                return print_json(&result);
+            }
+
+            println!(
+                "{:<40} {:>14} {:>14} {:>14} {:>14} {:>10}",
+                "GROUP", "INPUT", "OUTPUT", "CACHE WRITE", "CACHE READ", "COST"
+            );
+            for group in &result.groups {
+                let label = [&group.period, &group.model, &group.project_path, &group.session_id]
+                    .into_iter()
+                    .flatten()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join("  ");
+                print_usage_row(if label.is_empty() { "all" } else { &label }, &group.totals);
+            }
+            if result.groups.len() > 1 {
+                print_usage_row("total", &result.total);
+            }
+            if result.total.unpriced_requests > 0 {
+                eprintln!(
+                    "note: {} requests use models without a price and aren't in COST",
+                    result.total.unpriced_requests
+                );
+            }
+            Ok(())
+        }
+        Command::Export { file, stores, secrets, passphrase } => {
+            let store_ids = if stores.is_empty() {
+                None
+            } else {
+                let mut ids = Vec::new();
+                for store in &stores {
+                    ids.push(find_store(store).await?.id);
+                }
+                Some(ids)
+            };
+
+            let content = commands::export_profiles(store_ids, Some(secrets.into()), passphrase).await?;
+            match file {
+                Some(file) => commands::write_bundle_file(Path::new(&file), &content)?,
+                None => println!("{}", content),
+            }
+            Ok(())
+        }
+        Command::Import { file, passphrase, on_conflict } => {
+            let content = std::fs::read_to_string(&file)
+                .map_err(|e| AppError::io("Failed to read import file", &file, e))?;
+            let report = commands::import_profiles(content, passphrase, Some(on_conflict.into()), None).await?;
+
+            if json {
+                return print_json(&report);
+            }
+            for profile in &report.imported {
+                let verb = if profile.overwritten { "Overwrote" } else { "Imported" };
+                println!("{} \"{}\" ({})", verb, profile.title, profile.id);
+                if !profile.redacted.is_empty() {
+                    println!("  needs a value for: {}", profile.redacted.join(", "));
+                }
+            }
+            for profile in &report.skipped {
+                eprintln!("Skipped \"{}\": {}", profile.title, profile.reason);
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn run_mcp(command: McpCommand, json: bool) -> Result<(), AppError> {
+    match command {
+        McpCommand::List => {
+            let servers = commands::get_global_mcp_servers().await?;
+            let servers: BTreeMap<_, _> = servers.into_iter().map(|(name, server)| (name, server.config)).collect();
+            if json {
+                return print_json(&servers);
+            }
+            for (name, config) in &servers {
+                let target = config
+                    .get("url")
+                    .or_else(|| config.get("command"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("");
+                println!("{:<24} {}", name, target);
+            }
+            Ok(())
+        }
+        McpCommand::Add { name, json: config_json, command, args, env, url } => {
+            let mut config = match config_json {
+                Some(config_json) => serde_json::from_str(&config_json)
+                    .map_err(|e| AppError::parse("Failed to parse --json", "--json", e))?,
+                None => match (command, url) {
+                    (Some(command), _) => serde_json::json!({ "type": "stdio", "command": command, "args": args }),
+                    (None, Some(url)) => serde_json::json!({ "type": "http", "url": url }),
+                    (None, None) => return Err("Pass --json, --command or --url".into()),
+                },
+            };
+
+            if !env.is_empty() {
+                let mut vars = serde_json::Map::new();
+                for pair in env {
+                    let (key, value) = pair
+                        .split_once('=')
+                        .ok_or_else(|| format!("Invalid --env '{}', expected KEY=VALUE", pair))?;
+                    vars.insert(key.to_string(), Value::String(value.to_string()));
+                }
+                config["env"] = Value::Object(vars);
+            }
+
+            commands::update_global_mcp_server(name.clone(), config).await?;
+            if !json {
+                println!("Saved MCP server \"{}\"", name);
+            }
+            Ok(())
+        }
+        McpCommand::Rm { name } => {
+            commands::delete_global_mcp_server(name.clone()).await?;
+            if !json {
+                println!("Removed MCP server \"{}\"", name);
+            }
+            Ok(())
+        }
+    }
+}
+
+// Match by id first, then by title (case-insensitively as a fallback)
+async fn find_store(query: &str) -> Result<ConfigStore, AppError> {
+    let stores = commands::get_stores().await?;
+
+    if let Some(store) = stores.iter().find(|store| store.id == query || store.title == query) {
+        return Ok(store.clone());
+    }
+
+    let matches: Vec<&ConfigStore> = stores
+        .iter()
+        .filter(|store| store.title.eq_ignore_ascii_case(query))
+        .collect();
+    match matches.as_slice() {
+        [store] => Ok((*store).clone()),
+        [] => Err(AppError::not_found(format!(
+            "No config store with id or title '{}' (see `ccmate list`)",
+            query
+        ))),
+        _ => Err(AppError::conflict(format!(
+            "'{}' matches several config stores, use its id instead",
+            query
+        ))),
+    }
+}
+
+fn print_unified_diff(documents: &ConfigDiffDocuments) -> Result<(), AppError> {
+    let pretty = |value: &Value| {
+        serde_json::to_string_pretty(value)
+            .map(|content| content + "\n")
+            .map_err(|e| format!("Failed to serialize settings: {}", e))
+    };
+    let (from, to) = (pretty(&documents.from)?, pretty(&documents.to)?);
+
+    let diff = TextDiff::from_lines(&from, &to);
+    print!(
+        "{}",
+        diff.unified_diff()
+            .context_radius(3)
+            .header(&documents.from_label, &documents.to_label)
+    );
+    Ok(())
+}
+
+fn print_usage_row(label: &str, totals: &UsageTotals) {
+    println!(
+        "{:<40} {:>14} {:>14} {:>14} {:>14} {:>10}",
+        label,
+        totals.input_tokens,
+        totals.output_tokens,
+        totals.cache_creation_input_tokens,
+        totals.cache_read_input_tokens,
+        format!("${:.2}", totals.cost_usd)
+    );
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), AppError> {
+    let content = serde_json::to_string_pretty(value).map_err(|e| format!("Failed to serialize output: {}", e))?;
+    println!("{}", content);
+    Ok(())
+}
@@ -0,0 +1,521 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+use crate::commands::ConfigStore;
+use crate::error::{AppError, ErrorKind};
+use crate::merge::{escape_pointer_token, ArrayStrategy};
+use crate::secrets;
+
+/// Written into every bundle so other JSON files are rejected early.
+pub const BUNDLE_FORMAT: &str = "ccmate-profiles";
+
+// Bump when the layout changes in a way older versions can't read
+pub const BUNDLE_VERSION: u32 = 1;
+
+// Stands in for a secret in an encrypted bundle: `{"$encrypted": "<base64>"}`
+const ENCRYPTED_KEY: &str = "$encrypted";
+
+// Encrypted with the bundle key so a wrong passphrase is reported as such
+const PASSPHRASE_CHECK: &[u8] = b"ccmate-bundle";
+
+/// What happens to API tokens and other vault secrets on export.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretMode {
+    /// Replaced with empty strings, to be filled in after import
+    #[default]
+    Redact,
+    /// Written in plaintext
+    Include,
+    /// Encrypted with a passphrase the importer has to know
+    Encrypt,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Bundle {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: u64,
+    pub secrets: SecretMode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<BundleEncryption>,
+    pub profiles: Vec<BundleProfile>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct BundleEncryption {
+    pub salt: String,
+    pub check: String,
+}
+
+/// A profile as it travels between machines. Machine specific state (`using`,
+/// owned keys, project binding) is left out.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleProfile {
+    pub id: String,
+    pub title: String,
+    pub settings: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub array_strategy: Option<ArrayStrategy>,
+    // JSON pointers of secrets that were redacted and need to be filled in
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redacted: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    /// Import under a new id and/or title
+    #[default]
+    Rename,
+    /// Replace the existing profile's settings
+    Overwrite,
+    Skip,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictKind {
+    /// A profile with the same id exists
+    Id,
+    /// A profile with another id but the same title exists
+    Title,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfilePreview {
+    pub id: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<ConflictKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub existing_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redacted: Vec<String>,
+}
+
+/// What an import would do, for the UI to ask how conflicts should be handled.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BundlePreview {
+    pub version: u32,
+    pub exported_at: u64,
+    pub secrets: SecretMode,
+    pub profiles: Vec<ProfilePreview>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportAction {
+    Create,
+    Overwrite,
+    Skip(String),
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedProfile {
+    pub id: String,
+    pub title: String,
+    // Id the profile had in the bundle
+    pub source_id: String,
+    pub overwritten: bool,
+    // Secrets that were redacted on export and still need a value
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redacted: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedProfile {
+    pub id: String,
+    pub title: String,
+    pub reason: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct ImportReport {
+    pub imported: Vec<ImportedProfile>,
+    pub skipped: Vec<SkippedProfile>,
+}
+
+/// One profile of the bundle with its final id, title and parents on this machine.
+#[derive(Debug, Clone)]
+pub struct ImportStep {
+    pub source_id: String,
+    pub profile: BundleProfile,
+    pub action: ImportAction,
+}
+
+/// Build a bundle from `stores`. `store_ids` selects profiles (all when None);
+/// the profiles they extend are always included so the bundle is self-contained.
+pub fn export(
+    stores: &[ConfigStore],
+    store_ids: Option<&[String]>,
+    mode: SecretMode,
+    passphrase: Option<&str>,
+) -> Result<Bundle, AppError> {
+    let mut selected: HashSet<&str> = match store_ids {
+        Some(ids) => {
+            for id in ids {
+                if !stores.iter().any(|store| &store.id == id) {
+                    return Err(AppError::not_found(format!("Store '{}' not found", id)));
+                }
+            }
+            ids.iter().map(String::as_str).collect()
+        }
+        None => stores.iter().map(|store| store.id.as_str()).collect(),
+    };
+
+    let mut pending: Vec<&str> = selected.iter().copied().collect();
+    while let Some(id) = pending.pop() {
+        let Some(store) = stores.iter().find(|store| store.id == id) else {
+            continue;
+        };
+        for parent in store.extends.iter().flatten() {
+            if selected.insert(parent.as_str()) {
+                pending.push(parent.as_str());
+            }
+        }
+    }
+
+    let (key, encryption) = match mode {
+        SecretMode::Encrypt => {
+            let passphrase = passphrase
+                .filter(|passphrase| !passphrase.is_empty())
+                .ok_or_else(|| AppError::new(ErrorKind::Validation, "A passphrase is required to encrypt secrets"))?;
+            let mut salt = [0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            let key = secrets::derive_passphrase_key(passphrase, &salt)?;
+            let encryption = BundleEncryption {
+                salt: BASE64.encode(salt),
+                check: secrets::encrypt(&key, "check", PASSPHRASE_CHECK)?,
+            };
+            (Some(key), Some(encryption))
+        }
+        _ => (None, None),
+    };
+
+    // Keep the order of stores.json
+    let mut profiles = Vec::new();
+    for store in stores.iter().filter(|store| selected.contains(store.id.as_str())) {
+        let mut redacted = Vec::new();
+        let settings = export_secrets(&store.settings, "", &store.id, mode, key.as_ref(), &mut redacted)?;
+        profiles.push(BundleProfile {
+            id: store.id.clone(),
+            title: store.title.clone(),
+            settings,
+            extends: store.extends.clone().filter(|parents| !parents.is_empty()),
+            array_strategy: store.array_strategy,
+            redacted,
+        });
+    }
+
+    Ok(Bundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        exported_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| format!("Failed to get timestamp: {}", e))?
+            .as_secs(),
+        secrets: mode,
+        encryption,
+        profiles,
+    })
+}
+
+// Tokens that were never moved to the vault are secrets too
+fn is_plaintext_secret(pointer: &str, value: &Value) -> bool {
+    value.as_str().is_some_and(|s| !s.is_empty())
+        && secrets::SECRET_ENV_KEYS
+            .iter()
+            .any(|key| pointer == format!("/env/{}", key))
+}
+
+fn export_secrets(
+    value: &Value,
+    pointer: &str,
+    store_id: &str,
+    mode: SecretMode,
+    key: Option<&[u8; 32]>,
+    redacted: &mut Vec<String>,
+) -> Result<Value, AppError> {
+    let plaintext = match secrets::secret_ref_name(value) {
+        // Another profile's secret is never carried along
+        Some(name) if !secrets::is_owned(name, &[store_id.to_string()]) => {
+            redacted.push(pointer.to_string());
+            return Ok(Value::String(String::new()));
+        }
+        Some(name) => Some(
+            secrets::get_secret(name)?
+                .ok_or_else(|| AppError::not_found(format!("Secret '{}' not found in vault", name)))?,
+        ),
+        None if is_plaintext_secret(pointer, value) => value.as_str().map(str::to_string),
+        None => None,
+    };
+
+    if let Some(plaintext) = plaintext {
+        return match (mode, key) {
+            (SecretMode::Include, _) => Ok(Value::String(plaintext)),
+            (SecretMode::Encrypt, Some(key)) => {
+                let sealed = secrets::encrypt(key, &format!("{}{}", store_id, pointer), plaintext.as_bytes())?;
+                Ok(serde_json::json!({ ENCRYPTED_KEY: sealed }))
+            }
+            _ => {
+                redacted.push(pointer.to_string());
+                Ok(Value::String(String::new()))
+            }
+        };
+    }
+
+    match value {
+        Value::Object(obj) => {
+            let mut exported = serde_json::Map::new();
+            for (child_key, child) in obj {
+                let child_pointer = format!("{}/{}", pointer, escape_pointer_token(child_key));
+                exported.insert(
+                    child_key.clone(),
+                    export_secrets(child, &child_pointer, store_id, mode, key, redacted)?,
+                );
+            }
+            Ok(Value::Object(exported))
+        }
+        _ => Ok(value.clone()),
+    }
+}
+
+/// Read a bundle. Vault references can't be resolved on another machine and
+/// must not pick up a local secret, so they come in redacted. `source` names
+/// the file in errors.
+pub fn parse(content: &str, source: &str) -> Result<Bundle, AppError> {
+    let value: Value = serde_json::from_str(content).map_err(|e| AppError::parse("Failed to parse bundle", source, e))?;
+
+    if value.get("format").and_then(Value::as_str) != Some(BUNDLE_FORMAT) {
+        return Err(AppError::new(ErrorKind::Validation, "Not a CC Mate profile bundle").with_path(source));
+    }
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > BUNDLE_VERSION as u64 {
+        return Err(AppError::new(
+            ErrorKind::Validation,
+            format!(
+                "This bundle was made by a newer version of CC Mate (format version {}), please update",
+                version
+            ),
+        ));
+    }
+
+    let mut bundle: Bundle =
+        serde_json::from_value(value).map_err(|e| AppError::parse("Failed to parse bundle", source, e))?;
+    for profile in &mut bundle.profiles {
+        profile.settings = redact_refs(&profile.settings, "", &mut profile.redacted);
+    }
+    Ok(bundle)
+}
+
+fn redact_refs(value: &Value, pointer: &str, redacted: &mut Vec<String>) -> Value {
+    if secrets::secret_ref_name(value).is_some() {
+        redacted.push(pointer.to_string());
+        return Value::String(String::new());
+    }
+
+    match value {
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .map(|(key, child)| {
+                    let child_pointer = format!("{}/{}", pointer, escape_pointer_token(key));
+                    (key.clone(), redact_refs(child, &child_pointer, redacted))
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(index, child)| redact_refs(child, &format!("{}/{}", pointer, index), redacted))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// The bundle's profiles with encrypted secrets decrypted.
+pub fn open(bundle: &Bundle, passphrase: Option<&str>) -> Result<Vec<BundleProfile>, AppError> {
+    if bundle.secrets != SecretMode::Encrypt {
+        return Ok(bundle.profiles.clone());
+    }
+
+    let encryption = bundle
+        .encryption
+        .as_ref()
+        .ok_or_else(|| AppError::new(ErrorKind::Validation, "Bundle is missing its encryption parameters"))?;
+    let passphrase = passphrase
+        .filter(|passphrase| !passphrase.is_empty())
+        .ok_or_else(|| AppError::new(ErrorKind::PermissionDenied, "This bundle is encrypted, enter its passphrase"))?;
+
+    let salt = BASE64
+        .decode(&encryption.salt)
+        .map_err(|e| format!("Failed to decode bundle salt: {}", e))?;
+    let key = secrets::derive_passphrase_key(passphrase, &salt)?;
+    if secrets::decrypt(&key, "check", &encryption.check).is_err() {
+        return Err(AppError::new(ErrorKind::PermissionDenied, "Wrong passphrase for this bundle"));
+    }
+
+    bundle
+        .profiles
+        .iter()
+        .map(|profile| {
+            let mut profile = profile.clone();
+            profile.settings = decrypt_secrets(&profile.settings, "", &profile.id, &key)?;
+            Ok(profile)
+        })
+        .collect()
+}
+
+fn decrypt_secrets(value: &Value, pointer: &str, profile_id: &str, key: &[u8; 32]) -> Result<Value, AppError> {
+    match value {
+        Value::Object(obj) => {
+            if let (1, Some(Value::String(sealed))) = (obj.len(), obj.get(ENCRYPTED_KEY)) {
+                let plaintext = secrets::decrypt(key, &format!("{}{}", profile_id, pointer), sealed)?;
+                let plaintext = String::from_utf8(plaintext)
+                    .map_err(|e| format!("Secret at {} is not valid UTF-8: {}", pointer, e))?;
+                return Ok(Value::String(plaintext));
+            }
+
+            let mut decrypted = serde_json::Map::new();
+            for (child_key, child) in obj {
+                let child_pointer = format!("{}/{}", pointer, escape_pointer_token(child_key));
+                decrypted.insert(child_key.clone(), decrypt_secrets(child, &child_pointer, profile_id, key)?);
+            }
+            Ok(Value::Object(decrypted))
+        }
+        _ => Ok(value.clone()),
+    }
+}
+
+fn find_conflict<'a>(profile: &BundleProfile, existing: &'a [ConfigStore]) -> Option<(ConflictKind, &'a ConfigStore)> {
+    if let Some(store) = existing.iter().find(|store| store.id == profile.id) {
+        return Some((ConflictKind::Id, store));
+    }
+    existing
+        .iter()
+        .find(|store| store.title == profile.title)
+        .map(|store| (ConflictKind::Title, store))
+}
+
+pub fn preview(bundle: &Bundle, existing: &[ConfigStore]) -> BundlePreview {
+    let profiles = bundle
+        .profiles
+        .iter()
+        .map(|profile| {
+            let conflict = find_conflict(profile, existing);
+            ProfilePreview {
+                id: profile.id.clone(),
+                title: profile.title.clone(),
+                conflict: conflict.map(|(kind, _)| kind),
+                existing_id: conflict.map(|(_, store)| store.id.clone()),
+                redacted: profile.redacted.clone(),
+            }
+        })
+        .collect();
+
+    BundlePreview {
+        version: bundle.version,
+        exported_at: bundle.exported_at,
+        secrets: bundle.secrets,
+        profiles,
+    }
+}
+
+/// Decide what happens to each profile: `resolutions` picks a strategy per
+/// bundle profile id, `default` applies to the other conflicts. Parents are
+/// remapped to the ids the profiles end up with.
+pub fn plan(
+    profiles: &[BundleProfile],
+    existing: &[ConfigStore],
+    default: ConflictStrategy,
+    resolutions: &HashMap<String, ConflictStrategy>,
+) -> Vec<ImportStep> {
+    let mut taken_ids: HashSet<String> = existing.iter().map(|store| store.id.clone()).collect();
+    let mut taken_titles: HashSet<String> = existing.iter().map(|store| store.title.clone()).collect();
+    let mut id_map: HashMap<String, String> = HashMap::new();
+
+    let mut steps: Vec<ImportStep> = profiles
+        .iter()
+        .map(|profile| {
+            let mut imported = profile.clone();
+            let strategy = resolutions.get(&profile.id).copied().unwrap_or(default);
+
+            let action = match (find_conflict(profile, existing), strategy) {
+                // Also covers clashes with profiles earlier in the same bundle
+                (None, _) | (Some(_), ConflictStrategy::Rename) => {
+                    if taken_ids.contains(&imported.id) {
+                        imported.id = unique_id(&taken_ids);
+                    }
+                    if taken_titles.contains(&imported.title) {
+                        imported.title = unique_title(&imported.title, &taken_titles);
+                    }
+                    ImportAction::Create
+                }
+                (Some((_, store)), ConflictStrategy::Overwrite) => {
+                    imported.id = store.id.clone();
+                    ImportAction::Overwrite
+                }
+                (Some((kind, store)), ConflictStrategy::Skip) => {
+                    // Children in the bundle then extend the profile already here
+                    imported.id = store.id.clone();
+                    let reason = match kind {
+                        ConflictKind::Id => format!("a profile with id {} already exists", store.id),
+                        ConflictKind::Title => format!("a profile titled \"{}\" already exists", store.title),
+                    };
+                    ImportAction::Skip(reason)
+                }
+            };
+
+            taken_ids.insert(imported.id.clone());
+            taken_titles.insert(imported.title.clone());
+            id_map.insert(profile.id.clone(), imported.id.clone());
+
+            ImportStep {
+                source_id: profile.id.clone(),
+                profile: imported,
+                action,
+            }
+        })
+        .collect();
+
+    for step in &mut steps {
+        if let Some(parents) = &mut step.profile.extends {
+            for parent in parents.iter_mut() {
+                if let Some(mapped) = id_map.get(parent) {
+                    *parent = mapped.clone();
+                }
+            }
+        }
+    }
+
+    steps
+}
+
+fn unique_id(taken: &HashSet<String>) -> String {
+    loop {
+        let id = nanoid::nanoid!(6);
+        if !taken.contains(&id) {
+            return id;
+        }
+    }
+}
+
+// "Work" -> "Work (2)", "Work (3)", ...
+fn unique_title(title: &str, taken: &HashSet<String>) -> String {
+    (2..)
+        .map(|n| format!("{} ({})", title, n))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap_or_else(|| title.to_string())
+}
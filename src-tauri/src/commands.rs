@@ -1,18 +1,36 @@
 use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use tauri_plugin_updater::UpdaterExt;
 use reqwest;
 use uuid::Uuid;
 use nanoid;
 
-// Application configuration directory
-const APP_CONFIG_DIR: &str = ".ccconfig";
-
-pub async fn initialize_app_config() -> Result<(), String> {
+use crate::backups::{self, BackupManifest, BackupVerification};
+use crate::bundle::{
+    self, BundlePreview, BundleProfile, ConflictStrategy, ImportAction, ImportReport, ImportedProfile, SecretMode, SkippedProfile,
+};
+use crate::connection::{self, ConnectionReport, ConnectionTarget};
+use crate::error::AppError;
+use crate::history::{self, Revision, RevisionTrigger};
+use crate::json_diff::{self, ChangeKind, DiffEntry};
+use crate::merge::{self, ArrayStrategy, OwnedKey};
+use crate::paths::{self, ClaudeHomes};
+use crate::persistence::{update_json_file, write_atomic, write_private};
+use crate::pricing::{self, ModelPrice, PriceTable, UsageCosts};
+use crate::profiles;
+use crate::providers::{self, ProviderPreset};
+use crate::secrets::{self, VaultBackend, VaultStatus};
+use crate::settings_schema::{self, Diagnostic};
+use crate::sync::{self, SyncConfig, SyncItem, SyncReport, SyncResolution};
+use crate::undo::{self, UndoEntry};
+use crate::usage_index::{self, UsageScan};
+use crate::usage_query::{self, UsageQuery, UsageQueryResult};
+
+pub async fn initialize_app_config() -> Result<(), AppError> {
     println!("initialize_app_config called");
 
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
+    let app_config_path = paths::app_config_dir()?;
 
     println!(
         "Checking if app config directory exists: {}",
@@ -23,7 +41,7 @@ pub async fn initialize_app_config() -> Result<(), String> {
     if !app_config_path.exists() {
         println!("App config directory does not exist, creating...");
         std::fs::create_dir_all(&app_config_path)
-            .map_err(|e| format!("Failed to create app config directory: {}", e))?;
+            .map_err(|e| AppError::io("Failed to create app config directory", &app_config_path, e))?;
         println!(
             "App config directory created: {}",
             app_config_path.display()
@@ -32,22 +50,20 @@ pub async fn initialize_app_config() -> Result<(), String> {
         println!("App config directory already exists");
     }
 
-    // Check if we need to backup Claude configs
-    let claude_dir = home_dir.join(".claude");
+    // Take a first snapshot before we ever touch the user's Claude files
+    let claude_dir = paths::claude_dir()?;
     println!(
         "Checking if Claude directory exists: {}",
         claude_dir.display()
     );
 
     if claude_dir.exists() {
-        // Check if we already have a backup
-        let backup_dir = app_config_path.join("claude_backup");
-        if backup_dir.exists() {
+        if !backups::list()?.is_empty() {
             println!("Claude backup already exists, skipping backup");
         } else {
             println!("Claude directory exists but no backup found, backing up...");
-            if let Err(e) = backup_claude_configs_internal(&app_config_path, &claude_dir) {
-                return Err(format!("Failed to backup Claude configs: {}", e));
+            if let Err(e) = backups::create(backups::INITIAL_REASON) {
+                return Err(e.context("Failed to backup Claude configs"));
             }
             println!("Claude configs backed up successfully");
         }
@@ -55,6 +71,62 @@ pub async fn initialize_app_config() -> Result<(), String> {
         println!("Claude directory does not exist, skipping backup");
     }
 
+    if let Err(e) = migrate_plaintext_secrets() {
+        eprintln!("Warning: Failed to move plaintext tokens to the vault: {}", e);
+    }
+
+    Ok(())
+}
+
+// Stores saved before the vault existed, and the Original Config store made from
+// settings.json, kept tokens in plaintext, in their settings or in what they replaced
+// in settings.json. Move them to the vault; once done there is nothing left to move.
+fn migrate_plaintext_secrets() -> Result<(), AppError> {
+    let stores_file = paths::stores_file()?;
+    if !stores_file.exists() {
+        return Ok(());
+    }
+
+    let content = std::fs::read_to_string(&stores_file)
+        .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
+    let mut stores_data: StoresData = serde_json::from_str(&content)
+        .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?;
+
+    let mut migrated = false;
+    for store in &mut stores_data.configs {
+        let mut settings = store.settings.clone();
+        let owned_keys = secrets::extract_secrets(&store.id, &mut settings)
+            .and_then(|()| {
+                let Some(owned) = store.owned_keys.clone() else {
+                    return Ok(None);
+                };
+                // The values it applied still hold the tokens that just moved
+                let (_, used_secrets) = secrets::resolve_refs(&settings, std::slice::from_ref(&store.id))?;
+                conceal_ownership(&store.id, owned, &used_secrets).map(Some)
+            });
+        let owned_keys = match owned_keys {
+            Ok(owned_keys) => owned_keys,
+            Err(e) => {
+                eprintln!("Warning: Failed to move the tokens of \"{}\" to the vault: {}", store.title, e);
+                continue;
+            }
+        };
+
+        if settings != store.settings || owned_keys != store.owned_keys {
+            store.settings = settings;
+            store.owned_keys = owned_keys;
+            migrated = true;
+        }
+    }
+    if !migrated {
+        return Ok(());
+    }
+
+    let json_content = serde_json::to_string_pretty(&stores_data)
+        .map_err(|e| format!("Failed to serialize stores: {}", e))?;
+    write_atomic(&stores_file, json_content)
+        .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))?;
+    println!("Moved plaintext tokens to the vault");
     Ok(())
 }
 
@@ -73,6 +145,25 @@ pub struct ConfigStore {
     pub created_at: u64,
     pub settings: Value,
     pub using: bool,
+    // IDs of the stores this one inherits settings from, in merge order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<Vec<String>>,
+    #[serde(rename = "arrayStrategy", default, skip_serializing_if = "Option::is_none")]
+    pub array_strategy: Option<ArrayStrategy>,
+    // Pins the store to a project, so activating it writes that project's settings instead of the user's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<ProjectBinding>,
+    // Keys this store wrote into settings.json while it is in use
+    #[serde(rename = "ownedKeys", default, skip_serializing_if = "Option::is_none")]
+    pub owned_keys: Option<Vec<OwnedKey>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ProjectBinding {
+    pub path: String,
+    // Write .claude/settings.local.json (kept out of git) instead of .claude/settings.json
+    #[serde(default)]
+    pub local: bool,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
@@ -95,11 +186,9 @@ pub struct NotificationSettings {
 }
 
 #[tauri::command]
-pub async fn read_config_file(config_type: String) -> Result<ConfigFile, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-
+pub async fn read_config_file(config_type: String) -> Result<ConfigFile, AppError> {
     let path = match config_type.as_str() {
-        "user" => home_dir.join(".claude/settings.json"),
+        "user" => paths::user_settings_path()?,
         "enterprise_macos" => {
             PathBuf::from("/Library/Application Support/ClaudeCode/managed-settings.json")
         }
@@ -108,17 +197,17 @@ pub async fn read_config_file(config_type: String) -> Result<ConfigFile, String>
         "mcp_macos" => PathBuf::from("/Library/Application Support/ClaudeCode/managed-mcp.json"),
         "mcp_linux" => PathBuf::from("/etc/claude-code/managed-mcp.json"),
         "mcp_windows" => PathBuf::from("C:\\ProgramData\\ClaudeCode\\managed-mcp.json"),
-        _ => return Err("Invalid configuration type".to_string()),
+        _ => return Err("Invalid configuration type".into()),
     };
 
     let path_str = path.to_string_lossy().to_string();
 
     if path.exists() {
         let content =
-            std::fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+            std::fs::read_to_string(&path).map_err(|e| AppError::io("Failed to read file", &path, e))?;
 
         let json_content: Value =
-            serde_json::from_str(&content).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+            serde_json::from_str(&content).map_err(|e| AppError::parse("Failed to parse JSON", &path, e))?;
 
         Ok(ConfigFile {
             path: path_str,
@@ -135,29 +224,34 @@ pub async fn read_config_file(config_type: String) -> Result<ConfigFile, String>
 }
 
 #[tauri::command]
-pub async fn write_config_file(config_type: String, content: Value) -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-
+pub async fn write_config_file(config_type: String, content: Value) -> Result<(), AppError> {
     let path = match config_type.as_str() {
-        "user" => home_dir.join(".claude/settings.json"),
-        _ => return Err("Cannot write to enterprise configuration files".to_string()),
+        "user" => paths::user_settings_path()?,
+        _ => return Err("Cannot write to enterprise configuration files".into()),
     };
 
+    settings_schema::ensure_valid(&content)?;
+
     let json_content = serde_json::to_string_pretty(&content)
         .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
 
-    std::fs::write(&path, json_content).map_err(|e| format!("Failed to write file: {}", e))?;
+    write_atomic(&path, json_content).map_err(|e| AppError::io("Failed to write file", &path, e))?;
 
     Ok(())
 }
 
+/// Diagnostics for a settings document, so editors can show them before saving.
+#[tauri::command]
+pub async fn validate_settings(settings: Value) -> Result<Vec<Diagnostic>, AppError> {
+    Ok(settings_schema::validate(&settings))
+}
+
 #[tauri::command]
-pub async fn list_config_files() -> Result<Vec<String>, String> {
+pub async fn list_config_files() -> Result<Vec<String>, AppError> {
     let mut configs = vec![];
 
     // User settings
-    if let Some(home) = dirs::home_dir() {
-        let user_settings = home.join(".claude/settings.json");
+    if let Ok(user_settings) = paths::user_settings_path() {
         if user_settings.exists() {
             configs.push("user".to_string());
         }
@@ -201,85 +295,175 @@ pub async fn list_config_files() -> Result<Vec<String>, String> {
 }
 
 #[tauri::command]
-pub async fn check_app_config_exists() -> Result<bool, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
+pub async fn check_app_config_exists() -> Result<bool, AppError> {
+    let app_config_path = paths::app_config_dir()?;
     Ok(app_config_path.exists())
 }
 
 #[tauri::command]
-pub async fn create_app_config_dir() -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
+pub async fn create_app_config_dir() -> Result<(), AppError> {
+    let app_config_path = paths::app_config_dir()?;
 
     std::fs::create_dir_all(&app_config_path)
-        .map_err(|e| format!("Failed to create app config directory: {}", e))?;
+        .map_err(|e| AppError::io("Failed to create app config directory", &app_config_path, e))?;
 
     Ok(())
 }
 
-fn backup_claude_configs_internal(
-    app_config_path: &std::path::Path,
-    claude_dir: &std::path::Path,
-) -> Result<(), String> {
-    // Create backup directory
-    let backup_dir = app_config_path.join("claude_backup");
+#[tauri::command]
+pub async fn backup_claude_configs() -> Result<BackupManifest, AppError> {
+    backups::create("manual")
+}
 
-    std::fs::create_dir_all(&backup_dir)
-        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+#[tauri::command]
+pub async fn list_backups() -> Result<Vec<BackupManifest>, AppError> {
+    backups::list()
+}
 
-    // Copy all files from .claude directory to backup
-    for entry in std::fs::read_dir(claude_dir)
-        .map_err(|e| format!("Failed to read Claude directory: {}", e))?
-    {
-        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-        let source_path = entry.path();
-        let file_name = source_path.file_name().ok_or("Invalid file name")?;
-        let dest_path = backup_dir.join(file_name);
-
-        if source_path.is_file() {
-            std::fs::copy(&source_path, &dest_path)
-                .map_err(|e| format!("Failed to copy file {}: {}", source_path.display(), e))?;
-        }
-    }
+#[tauri::command]
+pub async fn verify_backup(backup_id: String) -> Result<BackupVerification, AppError> {
+    backups::verify(&backup_id)
+}
 
-    Ok(())
+/// Restore a backup; `entries` limits it to archive paths such as `claude/commands`.
+#[tauri::command]
+pub async fn restore_backup(backup_id: String, entries: Option<Vec<String>>) -> Result<Vec<String>, AppError> {
+    backups::restore(&backup_id, entries.as_deref())
 }
 
 #[tauri::command]
-pub async fn backup_claude_configs() -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let claude_dir = home_dir.join(".claude");
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
+pub async fn delete_backup(backup_id: String) -> Result<(), AppError> {
+    backups::delete(&backup_id)
+}
+
+// Store management functions
 
-    if !claude_dir.exists() {
-        return Err("Claude configuration directory does not exist".to_string());
+// Keys the store applied to settings.json, as recorded in stores.json with secrets
+// concealed; stores activated before ownership was recorded are assumed to own every
+// top-level key they hold
+fn recorded_ownership(store: &ConfigStore) -> Vec<OwnedKey> {
+    store
+        .owned_keys
+        .clone()
+        .unwrap_or_else(|| merge::legacy_ownership(&store.settings))
+}
+
+// The keys the store applied with the plaintext values settings.json has. Fails while
+// the vault is locked, the concealed secrets couldn't be matched against the file.
+fn applied_ownership(stores: &[ConfigStore], store: &ConfigStore) -> Result<Vec<OwnedKey>, AppError> {
+    let owners = profiles::lineage(stores, &store.id);
+    let reveal = |value: Value| -> Result<Value, AppError> { Ok(secrets::resolve_refs(&value, &owners)?.0) };
+    recorded_ownership(store)
+        .into_iter()
+        .map(|key| {
+            Ok(OwnedKey {
+                path: key.path,
+                value: reveal(key.value)?,
+                previous: key.previous.map(reveal).transpose()?,
+            })
+        })
+        .collect()
+}
+
+// Effective settings of a store with secret references resolved, ready to be written to
+// settings.json, plus the secrets that went into them
+fn materialize_settings(stores: &[ConfigStore], store_id: &str) -> Result<(Value, Vec<(String, String)>), AppError> {
+    let effective_settings = profiles::resolve_settings(stores, store_id)?;
+    secrets::resolve_refs(&effective_settings, &profiles::lineage(stores, store_id))
+}
+
+// Keep secret values out of the ownership record saved in stores.json
+fn conceal_ownership(
+    store_id: &str,
+    owned: Vec<OwnedKey>,
+    used_secrets: &[(String, String)],
+) -> Result<Vec<OwnedKey>, AppError> {
+    owned
+        .into_iter()
+        .map(|key| {
+            // Tokens the store replaced aren't its own, they get a vault entry of their own
+            let previous = match key.previous {
+                Some(previous) => {
+                    let mut previous = secrets::conceal(&previous, used_secrets);
+                    secrets::extract_previous_secrets(store_id, &key.path, &mut previous)?;
+                    Some(previous)
+                }
+                None => None,
+            };
+            Ok(OwnedKey {
+                path: key.path,
+                value: secrets::conceal(&key.value, used_secrets),
+                previous,
+            })
+        })
+        .collect()
+}
+
+// The settings file a store is written to when activated
+fn store_settings_path(claude_dir: &std::path::Path, store: &ConfigStore) -> PathBuf {
+    match &store.project {
+        Some(project) => {
+            let file_name = if project.local { "settings.local.json" } else { "settings.json" };
+            PathBuf::from(&project.path).join(".claude").join(file_name)
+        }
+        None => claude_dir.join("settings.json"),
     }
+}
 
-    // Ensure app config directory exists
-    std::fs::create_dir_all(&app_config_path)
-        .map_err(|e| format!("Failed to create app config directory: {}", e))?;
+/// Settings files of the profiles pinned to a project, whose `.claude` directory exists.
+pub fn project_settings_paths() -> Result<Vec<PathBuf>, AppError> {
+    let stores_file = paths::stores_file()?;
+    if !stores_file.exists() {
+        return Ok(vec![]);
+    }
+
+    let content = std::fs::read_to_string(&stores_file)
+        .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
+
+    let stores_data: StoresData = serde_json::from_str(&content)
+        .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?;
 
-    backup_claude_configs_internal(&app_config_path, &claude_dir)
+    let claude_dir = paths::claude_dir()?;
+    let mut settings_paths: Vec<PathBuf> = stores_data
+        .configs
+        .iter()
+        .filter(|store| store.project.is_some())
+        .map(|store| store_settings_path(&claude_dir, store))
+        .filter(|path| path.parent().is_some_and(std::path::Path::is_dir))
+        .collect();
+    settings_paths.sort();
+    settings_paths.dedup();
+    Ok(settings_paths)
 }
 
-// Store management functions
+// Take back what the previous profile applied to a settings file and merge the new
+// settings in its place, returning the keys they now own
+fn apply_settings_to_file(
+    settings_path: &std::path::Path,
+    previous_owned: &[OwnedKey],
+    settings: &Value,
+    arrays: ArrayStrategy,
+) -> Result<Vec<OwnedKey>, AppError> {
+    update_json_file(settings_path, |existing_settings| {
+        merge::revert_profile(existing_settings, previous_owned);
+        Ok(merge::apply_profile(existing_settings, settings, arrays))
+    })
+    .map_err(|e| e.context("Failed to update settings"))
+}
 
 #[tauri::command]
-pub async fn get_stores() -> Result<Vec<ConfigStore>, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
-    let stores_file = app_config_path.join("stores.json");
+pub async fn get_stores() -> Result<Vec<ConfigStore>, AppError> {
+    let stores_file = paths::stores_file()?;
 
     if !stores_file.exists() {
         return Ok(vec![]);
     }
 
     let content = std::fs::read_to_string(&stores_file)
-        .map_err(|e| format!("Failed to read stores file: {}", e))?;
+        .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
 
     let mut stores_data: StoresData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse stores file: {}", e))?;
+        .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?;
 
     // Add default notification settings if they don't exist
     if stores_data.notification.is_none() {
@@ -292,10 +476,10 @@ pub async fn get_stores() -> Result<Vec<ConfigStore>, String> {
         let json_content = serde_json::to_string_pretty(&stores_data)
             .map_err(|e| format!("Failed to serialize stores: {}", e))?;
 
-        std::fs::write(&stores_file, json_content)
-            .map_err(|e| format!("Failed to write stores file: {}", e))?;
+        write_atomic(&stores_file, json_content)
+            .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))?;
 
-        println!("Added default notification settings to existing stores.json");
+        eprintln!("Added default notification settings to existing stores.json");
     }
 
     let mut stores_vec = stores_data.configs;
@@ -310,22 +494,23 @@ pub async fn create_config(
     id: String,
     title: String,
     settings: Value,
-) -> Result<ConfigStore, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
-    let stores_file = app_config_path.join("stores.json");
+    extends: Option<Vec<String>>,
+    array_strategy: Option<ArrayStrategy>,
+) -> Result<ConfigStore, AppError> {
+    let app_config_path = paths::app_config_dir()?;
+    let stores_file = paths::stores_file()?;
 
     // Ensure app config directory exists
     std::fs::create_dir_all(&app_config_path)
-        .map_err(|e| format!("Failed to create app config directory: {}", e))?;
+        .map_err(|e| AppError::io("Failed to create app config directory", &app_config_path, e))?;
 
     // Read existing stores
     let mut stores_data = if stores_file.exists() {
         let content = std::fs::read_to_string(&stores_file)
-            .map_err(|e| format!("Failed to read stores file: {}", e))?;
+            .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
 
         serde_json::from_str::<StoresData>(&content)
-            .map_err(|e| format!("Failed to parse stores file: {}", e))?
+            .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?
     } else {
         StoresData {
             configs: vec![],
@@ -337,23 +522,39 @@ pub async fn create_config(
         }
     };
 
+    settings_schema::ensure_valid(&settings)?;
+
+    // Make sure the parent profiles exist before anything is written
+    let extends = extends.filter(|parents| !parents.is_empty());
+    if let Some(parents) = &extends {
+        profiles::validate_extends(&stores_data.configs, &id, parents)?;
+    }
+
+    // Tokens go to the secret vault, the profile only keeps a reference
+    let mut settings = settings;
+    secrets::extract_secrets(&id, &mut settings)?;
+
     // Determine if this should be the active store (true if no other stores exist)
     let should_be_active = stores_data.configs.is_empty();
 
     // If this is the first config being created and there's an existing settings.json, create an Original Config store
     if should_be_active {
-        let claude_settings_path = home_dir.join(".claude/settings.json");
+        let claude_settings_path = paths::user_settings_path()?;
         if claude_settings_path.exists() {
             // Read existing settings
             let settings_content = std::fs::read_to_string(&claude_settings_path)
-                .map_err(|e| format!("Failed to read existing Claude settings: {}", e))?;
+                .map_err(|e| AppError::io("Failed to read existing Claude settings", &claude_settings_path, e))?;
+
+            let mut settings_json: Value = serde_json::from_str(&settings_content)
+                .map_err(|e| AppError::parse("Failed to parse existing Claude settings", &claude_settings_path, e))?;
 
-            let settings_json: Value = serde_json::from_str(&settings_content)
-                .map_err(|e| format!("Failed to parse existing Claude settings: {}", e))?;
+            // Its tokens go to the vault like any other profile's
+            let original_id = nanoid::nanoid!(6); // Generate a 6-character ID
+            secrets::extract_secrets(&original_id, &mut settings_json)?;
 
             // Create an Original Config store with existing settings
             let original_store = ConfigStore {
-                id: nanoid::nanoid!(6), // Generate a 6-character ID
+                id: original_id,
                 title: "Original Config".to_string(),
                 created_at: std::time::SystemTime::now()
                     .duration_since(std::time::UNIX_EPOCH)
@@ -361,57 +562,32 @@ pub async fn create_config(
                     .as_secs(),
                 settings: settings_json,
                 using: false, // Original Config should not be active by default
+                extends: None,
+                array_strategy: None,
+                project: None,
+                owned_keys: None,
             };
 
             // Add the Original Config store to the collection
             stores_data.configs.push(original_store);
-            println!("Created Original Config store from existing settings.json");
+            eprintln!("Created Original Config store from existing settings.json");
         }
     }
 
-    // If this is the first store (and therefore active), write its settings to the user's actual settings.json with partial update
-    if should_be_active {
-        let user_settings_path = home_dir.join(".claude/settings.json");
-
-        // Create .claude directory if it doesn't exist
-        if let Some(parent) = user_settings_path.parent() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
-        }
-
-        // Read existing settings if file exists, otherwise start with empty object
-        let mut existing_settings = if user_settings_path.exists() {
-            let content = std::fs::read_to_string(&user_settings_path)
-                .map_err(|e| format!("Failed to read existing settings: {}", e))?;
-            serde_json::from_str(&content)
-                .map_err(|e| format!("Failed to parse existing settings: {}", e))?
-        } else {
-            serde_json::Value::Object(serde_json::Map::new())
-        };
-
-        // Merge the new settings into existing settings (partial update)
-        if let Some(settings_obj) = settings.as_object() {
-            if let Some(existing_obj) = existing_settings.as_object_mut() {
-                // Update only the keys present in the stored settings
-                for (key, value) in settings_obj {
-                    existing_obj.insert(key.clone(), value.clone());
-                }
-            } else {
-                // If existing settings is not an object, replace it entirely
-                existing_settings = settings.clone();
-            }
-        } else {
-            // If stored settings is not an object, replace existing entirely
-            existing_settings = settings.clone();
-        }
-
-        // Write the merged settings back to file
-        let json_content = serde_json::to_string_pretty(&existing_settings)
-            .map_err(|e| format!("Failed to serialize merged settings: {}", e))?;
-
-        std::fs::write(&user_settings_path, json_content)
-            .map_err(|e| format!("Failed to write user settings: {}", e))?;
-    }
+    // If this is the first store (and therefore active), merge its settings into the user's actual settings.json
+    let owned_keys = if should_be_active {
+        let user_settings_path = paths::user_settings_path()?;
+        let (resolved_settings, used_secrets) = secrets::resolve_refs(&settings, std::slice::from_ref(&id))?;
+        let owned_keys = apply_settings_to_file(
+            &user_settings_path,
+            &[],
+            &resolved_settings,
+            array_strategy.unwrap_or_default(),
+        )?;
+        Some(conceal_ownership(&id, owned_keys, &used_secrets)?)
+    } else {
+        None
+    };
 
     // Create new store
     let new_store = ConfigStore {
@@ -423,6 +599,10 @@ pub async fn create_config(
             .as_secs(),
         settings,
         using: should_be_active,
+        extends,
+        array_strategy,
+        project: None,
+        owned_keys,
     };
 
     // Add store to collection
@@ -432,8 +612,12 @@ pub async fn create_config(
     let json_content = serde_json::to_string_pretty(&stores_data)
         .map_err(|e| format!("Failed to serialize stores: {}", e))?;
 
-    std::fs::write(&stores_file, json_content)
-        .map_err(|e| format!("Failed to write stores file: {}", e))?;
+    write_atomic(&stores_file, json_content)
+        .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))?;
+
+    if let Err(e) = history::record(&new_store, RevisionTrigger::Created) {
+        eprintln!("Warning: Failed to record revision: {}", e);
+    }
 
     // Automatically unlock CC extension when creating new config
     if let Err(e) = unlock_cc_ext().await {
@@ -444,170 +628,467 @@ pub async fn create_config(
 }
 
 #[tauri::command]
-pub async fn delete_config(store_id: String) -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
-    let stores_file = app_config_path.join("stores.json");
+pub async fn list_provider_presets() -> Result<Vec<ProviderPreset>, AppError> {
+    providers::load()
+}
+
+/// Create a profile for an Anthropic-compatible provider from its preset.
+/// `secrets` maps each of the preset's secret env variables to its value.
+#[tauri::command]
+pub async fn create_config_from_preset(
+    preset_id: String,
+    title: Option<String>,
+    secrets: HashMap<String, String>,
+) -> Result<ConfigStore, AppError> {
+    let preset = providers::find(&preset_id)?;
+    let settings = preset.settings(&secrets)?;
+    let title = title
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| preset.name.clone());
+
+    create_config(nanoid::nanoid!(6), title, settings, None, None).await
+}
+
+/// Send a one token request with the profile's credentials and model to its
+/// `ANTHROPIC_BASE_URL`. The token only ever goes where the profile sends it.
+#[tauri::command]
+pub async fn test_config_connection(store_id: String) -> Result<ConnectionReport, AppError> {
+    let stores = get_stores().await?;
+    let (settings, _) = materialize_settings(&stores, &store_id)?;
+
+    let target = ConnectionTarget::from_settings(&settings);
+    Ok(connection::check(&target, connection::DEFAULT_TIMEOUT).await)
+}
+
+#[tauri::command]
+pub async fn delete_config(store_id: String) -> Result<(), AppError> {
+    let stores_file = paths::stores_file()?;
 
     if !stores_file.exists() {
-        return Err("Stores file does not exist".to_string());
+        return Err(AppError::not_found("Stores file does not exist").with_path(&stores_file));
     }
 
     // Read existing stores
     let content = std::fs::read_to_string(&stores_file)
-        .map_err(|e| format!("Failed to read stores file: {}", e))?;
+        .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
 
     let mut stores_data: StoresData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse stores file: {}", e))?;
-
-    // Find and remove store by ID
-    let original_len = stores_data.configs.len();
-    stores_data.configs.retain(|store| store.id != store_id);
+        .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?;
 
-    if stores_data.configs.len() == original_len {
-        return Err("Store not found".to_string());
+    // Profiles that inherit from this one would silently lose settings
+    let children = profiles::direct_children(&stores_data.configs, &store_id);
+    if !children.is_empty() {
+        let titles: Vec<&str> = children.iter().map(|store| store.title.as_str()).collect();
+        return Err(AppError::conflict(format!("Store is extended by: {}", titles.join(", "))));
     }
 
+    // Find and remove store by ID
+    let index = stores_data
+        .configs
+        .iter()
+        .position(|store| store.id == store_id)
+        .ok_or_else(|| AppError::not_found("Store not found"))?;
+    let removed_store = stores_data.configs.remove(index);
+    let snapshot = undo::Snapshot::new(format!("Delete config \"{}\"", removed_store.title))
+        .store(removed_store.clone(), index);
+
     // Write back to file
     let json_content = serde_json::to_string_pretty(&stores_data)
         .map_err(|e| format!("Failed to serialize stores: {}", e))?;
 
-    std::fs::write(&stores_file, json_content)
-        .map_err(|e| format!("Failed to write stores file: {}", e))?;
+    write_atomic(&stores_file, json_content)
+        .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))?;
 
+    // Its history (and the secrets it refers to) stays so the store can be restored
+    if let Err(e) = history::record(&removed_store, RevisionTrigger::Deleted) {
+        eprintln!("Warning: Failed to record revision: {}", e);
+    }
+
+    record_undo(snapshot);
     Ok(())
 }
 
 #[tauri::command]
-pub async fn set_using_config(store_id: String) -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
-    let stores_file = app_config_path.join("stores.json");
+pub async fn set_using_config(store_id: String) -> Result<(), AppError> {
+    let claude_dir = paths::claude_dir()?;
+    let stores_file = paths::stores_file()?;
 
     if !stores_file.exists() {
-        return Err("Stores file does not exist".to_string());
+        return Err(AppError::not_found("Stores file does not exist").with_path(&stores_file));
     }
 
     // Read existing stores
     let content = std::fs::read_to_string(&stores_file)
-        .map_err(|e| format!("Failed to read stores file: {}", e))?;
+        .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
 
     let mut stores_data: StoresData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse stores file: {}", e))?;
+        .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?;
 
     // Find the store and check if it exists
-    let store_found = stores_data.configs.iter().any(|store| store.id == store_id);
-    if !store_found {
-        return Err("Store not found".to_string());
+    let selected = stores_data
+        .configs
+        .iter()
+        .find(|store| store.id == store_id)
+        .ok_or_else(|| AppError::not_found("Store not found"))?;
+
+    // Profiles pinned to a project write that project's settings instead of the user's
+    if let Some(project) = &selected.project {
+        if !std::path::Path::new(&project.path).is_dir() {
+            return Err(AppError::not_found(format!("Project directory '{}' does not exist", project.path)).with_path(&project.path));
+        }
     }
+    let settings_path = store_settings_path(&claude_dir, selected);
 
-    // Set all stores to not using, then set the selected one to using
-    let mut selected_store_settings: Option<Value> = None;
+    // Whatever the profile active for the same settings file applied has to be taken back before switching
+    let previous_owned: Vec<OwnedKey> = stores_data
+        .configs
+        .iter()
+        .filter(|store| store.using && store_settings_path(&claude_dir, store) == settings_path)
+        .map(|store| applied_ownership(&stores_data.configs, store))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+
+    // Write the selected store's settings to its settings.json, merged with what's there
+    let (effective_settings, used_secrets) = materialize_settings(&stores_data.configs, &selected.id)?;
+    let owned_keys = apply_settings_to_file(
+        &settings_path,
+        &previous_owned,
+        &effective_settings,
+        selected.array_strategy.unwrap_or_default(),
+    )?;
+    let owned_keys = conceal_ownership(&selected.id, owned_keys, &used_secrets)?;
+
+    // Set the stores sharing that settings file to not using, then set the selected one to using
     for store in &mut stores_data.configs {
         if store.id == store_id {
             store.using = true;
-            selected_store_settings = Some(store.settings.clone());
-        } else {
+            store.owned_keys = Some(owned_keys.clone());
+        } else if store_settings_path(&claude_dir, store) == settings_path {
             store.using = false;
+            store.owned_keys = None;
         }
     }
 
-    // Write the selected store's settings to the user's actual settings.json with partial update
-    if let Some(settings) = selected_store_settings {
-        let user_settings_path = home_dir.join(".claude/settings.json");
+    // Write back to stores file
+    let json_content = serde_json::to_string_pretty(&stores_data)
+        .map_err(|e| format!("Failed to serialize stores: {}", e))?;
 
-        // Create .claude directory if it doesn't exist
-        if let Some(parent) = user_settings_path.parent() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+    write_atomic(&stores_file, json_content)
+        .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))?;
+
+    Ok(())
+}
+
+/// Where the live settings file no longer matches what an active profile applied.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileDrift {
+    pub store_id: String,
+    pub title: String,
+    pub settings_path: String,
+    // `old` is the profile's value, `new` the one in the file; secrets are masked
+    pub entries: Vec<DiffEntry>,
+}
+
+fn read_settings_or_empty(path: &std::path::Path) -> Result<Value, AppError> {
+    if !path.exists() {
+        return Ok(Value::Object(serde_json::Map::new()));
+    }
+
+    let content = std::fs::read_to_string(path).map_err(|e| AppError::io("Failed to read settings", path, e))?;
+    serde_json::from_str(&content).map_err(|e| AppError::parse("Failed to parse settings", path, e))
+}
+
+// Differences under each key the store owns, tagged with the index of that key
+fn owned_key_drift(owned: &[OwnedKey], live: &Value) -> Vec<(usize, DiffEntry)> {
+    let mut drift = Vec::new();
+
+    for (index, key) in owned.iter().enumerate() {
+        let live_value = if key.path.is_empty() {
+            Some(live)
+        } else {
+            live.pointer(&key.path)
+        };
+
+        match live_value {
+            Some(live_value) => drift.extend(json_diff::diff(&key.value, live_value).into_iter().map(|entry| {
+                let entry = DiffEntry {
+                    path: format!("{}{}", key.path, entry.path),
+                    ..entry
+                };
+                (index, entry)
+            })),
+            None => drift.push((
+                index,
+                DiffEntry {
+                    path: key.path.clone(),
+                    kind: ChangeKind::Removed,
+                    old: Some(key.value.clone()),
+                    new: None,
+                },
+            )),
         }
+    }
 
-        // Read existing settings if file exists, otherwise start with empty object
-        let mut existing_settings = if user_settings_path.exists() {
-            let content = std::fs::read_to_string(&user_settings_path)
-                .map_err(|e| format!("Failed to read existing settings: {}", e))?;
-            serde_json::from_str(&content)
-                .map_err(|e| format!("Failed to parse existing settings: {}", e))?
+    drift
+}
+
+/// Compare every active profile with its live settings file. Only profiles
+/// that drifted are returned.
+#[tauri::command]
+pub async fn check_profile_drift() -> Result<Vec<ProfileDrift>, AppError> {
+    let claude_dir = paths::claude_dir()?;
+    let stores = get_stores().await?;
+
+    let unlocked = secrets::status()?.unlocked;
+
+    let mut drifted = Vec::new();
+    for store in stores.iter().filter(|store| store.using) {
+        let settings_path = store_settings_path(&claude_dir, store);
+        let live = read_settings_or_empty(&settings_path)?;
+        let drift = if unlocked {
+            owned_key_drift(&applied_ownership(&stores, store)?, &live)
         } else {
-            serde_json::Value::Object(serde_json::Map::new())
+            // Without the vault a token can't be compared, only the other keys are checked
+            owned_key_drift(&recorded_ownership(store), &live)
+                .into_iter()
+                .filter(|(_, entry)| entry.old.as_ref().and_then(secrets::secret_ref_name).is_none())
+                .collect()
         };
+        if drift.is_empty() {
+            continue;
+        }
 
-        // Merge the new settings into existing settings (partial update)
-        if let Some(settings_obj) = settings.as_object() {
-            if let Some(existing_obj) = existing_settings.as_object_mut() {
-                // Update only the keys present in the stored settings
-                for (key, value) in settings_obj {
-                    existing_obj.insert(key.clone(), value.clone());
-                }
+        // Show vault secrets as references rather than plaintext
+        let used_secrets = store
+            .owned_keys
+            .iter()
+            .flatten()
+            .filter_map(|key| secrets::resolve_refs(&key.value, &profiles::lineage(&stores, &store.id)).ok())
+            .flat_map(|(_, used)| used)
+            .collect::<Vec<_>>();
+        let mask = |value: Option<Value>| value.map(|value| secrets::conceal(&value, &used_secrets));
+
+        drifted.push(ProfileDrift {
+            store_id: store.id.clone(),
+            title: store.title.clone(),
+            settings_path: settings_path.display().to_string(),
+            entries: drift
+                .into_iter()
+                .map(|(_, entry)| DiffEntry {
+                    old: mask(entry.old),
+                    new: mask(entry.new),
+                    ..entry
+                })
+                .collect(),
+        });
+    }
+
+    Ok(drifted)
+}
+
+/// Write what an active profile applied back over the drifted keys.
+#[tauri::command]
+pub async fn reapply_profile(store_id: String) -> Result<(), AppError> {
+    let claude_dir = paths::claude_dir()?;
+    let stores = get_stores().await?;
+    let store = stores
+        .iter()
+        .find(|store| store.id == store_id)
+        .ok_or_else(|| AppError::not_found(format!("Store with id '{}' not found", store_id)))?;
+    if !store.using {
+        return Err(AppError::conflict(format!("\"{}\" is not in use", store.title)));
+    }
+
+    let owned = applied_ownership(&stores, store)?;
+    update_json_file(&store_settings_path(&claude_dir, store), |live| {
+        for key in &owned {
+            if key.path.is_empty() {
+                *live = key.value.clone();
             } else {
-                // If existing settings is not an object, replace it entirely
-                existing_settings = settings.clone();
+                merge::set_pointer(live, &merge::parse_pointer(&key.path), Some(key.value.clone()));
             }
-        } else {
-            // If stored settings is not an object, replace existing entirely
-            existing_settings = settings.clone();
         }
+        Ok(())
+    })
+    .map_err(|e| e.context("Failed to reapply profile"))
+}
 
-        // Write the merged settings back to file
-        let json_content = serde_json::to_string_pretty(&existing_settings)
-            .map_err(|e| format!("Failed to serialize merged settings: {}", e))?;
+/// Take the live values of drifted keys into an active profile. The settings
+/// file is left as it is; the profile and what it owns are updated to match.
+#[tauri::command]
+pub async fn adopt_profile_drift(store_id: String) -> Result<ConfigStore, AppError> {
+    let claude_dir = paths::claude_dir()?;
+    let stores_file = paths::stores_file()?;
+
+    let content = std::fs::read_to_string(&stores_file)
+        .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
+    let mut stores_data: StoresData = serde_json::from_str(&content)
+        .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?;
 
-        std::fs::write(&user_settings_path, json_content)
-            .map_err(|e| format!("Failed to write user settings: {}", e))?;
+    let store_index = stores_data
+        .configs
+        .iter()
+        .position(|store| store.id == store_id)
+        .ok_or_else(|| AppError::not_found(format!("Store with id '{}' not found", store_id)))?;
+    let store = &stores_data.configs[store_index];
+    if !store.using {
+        return Err(AppError::conflict(format!("\"{}\" is not in use", store.title)));
+    }
+
+    let live = read_settings_or_empty(&store_settings_path(&claude_dir, store))?;
+    let mut owned = applied_ownership(&stores_data.configs, store)?;
+    let drift = owned_key_drift(&owned, &live);
+    if drift.is_empty() {
+        return Ok(store.clone());
+    }
+
+    let mut settings = store.settings.clone();
+    for (_, entry) in &drift {
+        merge::set_pointer(&mut settings, &merge::parse_pointer(&entry.path), entry.new.clone());
+    }
+
+    // A key that comes from a parent profile can't be removed (or un-appended) here,
+    // the parent would keep supplying it
+    let mut adopted = stores_data.configs.clone();
+    adopted[store_index].settings = settings.clone();
+    let effective = profiles::resolve_settings(&adopted, &store_id)?;
+    let owners = profiles::lineage(&adopted, &store_id);
+    for (_, entry) in &drift {
+        let resolved = if entry.path.is_empty() { Some(&effective) } else { effective.pointer(&entry.path) };
+        // Vault references compare by the tokens they stand for, like the live file has them
+        let resolved = resolved
+            .map(|value| secrets::resolve_refs(value, &owners).map(|(v, _)| v))
+            .transpose()?;
+        if resolved.as_ref() != entry.new.as_ref() {
+            let parent = inherited_from(&stores_data.configs, store, &entry.path);
+            return Err(AppError::conflict(format!(
+                "'{}' is inherited from \"{}\", change it in that profile or reapply this one",
+                entry.path, parent
+            )));
+        }
     }
+    settings_schema::ensure_valid(&settings)?;
+    secrets::extract_secrets(&store_id, &mut settings)?;
+
+    // The keys now own what is in the file, so switching away still restores their previous values
+    let drifted_keys: HashSet<usize> = drift.iter().map(|(index, _)| *index).collect();
+    for index in &drifted_keys {
+        let key = &mut owned[*index];
+        let live_value = if key.path.is_empty() {
+            Some(&live)
+        } else {
+            live.pointer(&key.path)
+        };
+        if let Some(live_value) = live_value {
+            key.value = live_value.clone();
+        }
+    }
+    let owned: Vec<OwnedKey> = owned
+        .into_iter()
+        .enumerate()
+        .filter(|(index, key)| {
+            !drifted_keys.contains(index) || key.path.is_empty() || live.pointer(&key.path).is_some()
+        })
+        .map(|(_, key)| key)
+        .collect();
+
+    stores_data.configs[store_index].settings = settings;
+    let (_, used_secrets) = materialize_settings(&stores_data.configs, &store_id)?;
+    stores_data.configs[store_index].owned_keys = Some(conceal_ownership(&store_id, owned, &used_secrets)?);
 
-    // Write back to stores file
     let json_content = serde_json::to_string_pretty(&stores_data)
         .map_err(|e| format!("Failed to serialize stores: {}", e))?;
+    write_atomic(&stores_file, json_content)
+        .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))?;
 
-    std::fs::write(&stores_file, json_content)
-        .map_err(|e| format!("Failed to write stores file: {}", e))?;
+    let updated_store = stores_data.configs[store_index].clone();
+    if let Err(e) = history::record(&updated_store, RevisionTrigger::Updated) {
+        eprintln!("Warning: Failed to record revision: {}", e);
+    }
 
-    Ok(())
+    Ok(updated_store)
+}
+
+// Title of the first parent of `store` that supplies the value at `pointer`
+fn inherited_from(stores: &[ConfigStore], store: &ConfigStore, pointer: &str) -> String {
+    store
+        .extends
+        .iter()
+        .flatten()
+        .find(|parent_id| {
+            profiles::resolve_settings(stores, parent_id)
+                .is_ok_and(|parent_settings| parent_settings.pointer(pointer).is_some())
+        })
+        .and_then(|parent_id| stores.iter().find(|parent| &parent.id == parent_id))
+        .map(|parent| parent.title.clone())
+        .unwrap_or_else(|| "a parent profile".to_string())
 }
 
 #[tauri::command]
-pub async fn reset_to_original_config() -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
-    let stores_file = app_config_path.join("stores.json");
+pub async fn reset_to_original_config() -> Result<(), AppError> {
+    let stores_file = paths::stores_file()?;
+    let user_settings_path = paths::user_settings_path()?;
+    let snapshot = undo::Snapshot::new("Reset to original config")
+        .file(&stores_file)?
+        .file(&user_settings_path)?;
 
     // Set all stores to not using
     if stores_file.exists() {
         let content = std::fs::read_to_string(&stores_file)
-            .map_err(|e| format!("Failed to read stores file: {}", e))?;
+            .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
 
         let mut stores_data: StoresData = serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse stores file: {}", e))?;
+            .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?;
+
+        // Take back what the active profile applied, then set all user-level stores to not using.
+        // Profiles pinned to a project are left alone.
+        let previous_owned: Vec<OwnedKey> = stores_data
+            .configs
+            .iter()
+            .filter(|store| store.using && store.project.is_none())
+            .map(|store| applied_ownership(&stores_data.configs, store))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        if !previous_owned.is_empty() {
+            update_json_file(&user_settings_path, |existing_settings| {
+                merge::revert_profile(existing_settings, &previous_owned);
+                Ok(())
+            })?;
+        }
 
-        // Set all stores to not using
-        for store in &mut stores_data.configs {
+        for store in stores_data.configs.iter_mut().filter(|store| store.project.is_none()) {
             store.using = false;
+            store.owned_keys = None;
         }
 
         // Write back to stores file
         let json_content = serde_json::to_string_pretty(&stores_data)
             .map_err(|e| format!("Failed to serialize stores: {}", e))?;
 
-        std::fs::write(&stores_file, json_content)
-            .map_err(|e| format!("Failed to write stores file: {}", e))?;
+        write_atomic(&stores_file, json_content)
+            .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))?;
     }
 
     // Clear env field in settings.json
-    let user_settings_path = home_dir.join(".claude/settings.json");
-
     // Create .claude directory if it doesn't exist
     if let Some(parent) = user_settings_path.parent() {
         std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+            .map_err(|e| AppError::io("Failed to create .claude directory", parent, e))?;
     }
 
     // Read existing settings if file exists, otherwise start with empty object
     let mut existing_settings = if user_settings_path.exists() {
         let content = std::fs::read_to_string(&user_settings_path)
-            .map_err(|e| format!("Failed to read existing settings: {}", e))?;
+            .map_err(|e| AppError::io("Failed to read existing settings", &user_settings_path, e))?;
         serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse existing settings: {}", e))?
+            .map_err(|e| AppError::parse("Failed to parse existing settings", &user_settings_path, e))?
     } else {
         serde_json::Value::Object(serde_json::Map::new())
     };
@@ -621,25 +1102,99 @@ pub async fn reset_to_original_config() -> Result<(), String> {
     let json_content = serde_json::to_string_pretty(&existing_settings)
         .map_err(|e| format!("Failed to serialize merged settings: {}", e))?;
 
-    std::fs::write(&user_settings_path, json_content)
-        .map_err(|e| format!("Failed to write user settings: {}", e))?;
+    write_atomic(&user_settings_path, json_content)
+        .map_err(|e| AppError::io("Failed to write user settings", &user_settings_path, e))?;
 
+    record_undo(snapshot);
     Ok(())
 }
 
 #[tauri::command]
-pub async fn get_current_store() -> Result<Option<ConfigStore>, String> {
+pub async fn get_current_store() -> Result<Option<ConfigStore>, AppError> {
     let stores = get_stores().await?;
-    Ok(stores.into_iter().find(|store| store.using))
+    Ok(stores.into_iter().find(|store| store.using && store.project.is_none()))
 }
 
 #[tauri::command]
-pub async fn get_store(store_id: String) -> Result<ConfigStore, String> {
+pub async fn get_project_store(project_path: String) -> Result<Option<ConfigStore>, AppError> {
+    let stores = get_stores().await?;
+    Ok(stores.into_iter().find(|store| {
+        store.using
+            && store
+                .project
+                .as_ref()
+                .map(|project| project.path == project_path)
+                .unwrap_or(false)
+    }))
+}
+
+/// Pins a store to one of Claude Code's projects, or back to the user settings when `project` is `None`.
+#[tauri::command]
+pub async fn set_config_project(store_id: String, project: Option<ProjectBinding>) -> Result<ConfigStore, AppError> {
+    let claude_dir = paths::claude_dir()?;
+    let stores_file = paths::stores_file()?;
+
+    if !stores_file.exists() {
+        return Err(AppError::not_found("Stores file does not exist").with_path(&stores_file));
+    }
+
+    // Only projects Claude Code knows about can be targeted
+    if let Some(project) = &project {
+        let projects = read_claude_projects().await?;
+        if !projects.iter().any(|p| p.path == project.path) {
+            return Err(AppError::not_found(format!("'{}' is not a known Claude Code project", project.path)));
+        }
+    }
+
+    // Read existing stores
+    let content = std::fs::read_to_string(&stores_file)
+        .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
+
+    let mut stores_data: StoresData = serde_json::from_str(&content)
+        .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?;
+
+    let store_index = stores_data
+        .configs
+        .iter()
+        .position(|store| store.id == store_id)
+        .ok_or_else(|| AppError::not_found(format!("Store with id '{}' not found", store_id)))?;
+    let previous_owned = applied_ownership(&stores_data.configs, &stores_data.configs[store_index])?;
+    let store = &mut stores_data.configs[store_index];
+
+    if store.project == project {
+        return Ok(store.clone());
+    }
+
+    // An active store is taken out of the settings file it currently targets
+    if store.using {
+        update_json_file(&store_settings_path(&claude_dir, store), |existing_settings| {
+            merge::revert_profile(existing_settings, &previous_owned);
+            Ok(())
+        })?;
+        store.using = false;
+        store.owned_keys = None;
+    }
+
+    store.project = project;
+    let updated_store = store.clone();
+
+    // Write back to stores file
+    let json_content = serde_json::to_string_pretty(&stores_data)
+        .map_err(|e| format!("Failed to serialize stores: {}", e))?;
+
+    write_atomic(&stores_file, json_content)
+        .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))?;
+
+    Ok(updated_store)
+}
+
+#[tauri::command]
+pub async fn get_store(store_id: String) -> Result<ConfigStore, AppError> {
     let stores = get_stores().await?;
     stores
         .into_iter()
         .find(|store| store.id == store_id)
-        .ok_or_else(|| format!("Store with id '{}' not found", store_id))
+        .ok_or_else(|| AppError::not_found(format!("Store with id '{}' not found", store_id)))
 }
 
 #[tauri::command]
@@ -647,28 +1202,40 @@ pub async fn update_config(
     store_id: String,
     title: String,
     settings: Value,
-) -> Result<ConfigStore, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
-    let stores_file = app_config_path.join("stores.json");
+    extends: Option<Vec<String>>,
+    array_strategy: Option<ArrayStrategy>,
+) -> Result<ConfigStore, AppError> {
+    update_store(store_id, title, settings, extends, array_strategy, RevisionTrigger::Updated).await
+}
+
+async fn update_store(
+    store_id: String,
+    title: String,
+    settings: Value,
+    extends: Option<Vec<String>>,
+    array_strategy: Option<ArrayStrategy>,
+    trigger: RevisionTrigger,
+) -> Result<ConfigStore, AppError> {
+    let claude_dir = paths::claude_dir()?;
+    let stores_file = paths::stores_file()?;
 
     if !stores_file.exists() {
-        return Err("Stores file does not exist".to_string());
+        return Err(AppError::not_found("Stores file does not exist").with_path(&stores_file));
     }
 
     // Read existing stores
     let content = std::fs::read_to_string(&stores_file)
-        .map_err(|e| format!("Failed to read stores file: {}", e))?;
+        .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
 
     let mut stores_data: StoresData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse stores file: {}", e))?;
+        .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?;
 
     // Find the store by ID
     let store_index = stores_data
         .configs
         .iter()
         .position(|store| store.id == store_id)
-        .ok_or_else(|| format!("Store with id '{}' not found", store_id))?;
+        .ok_or_else(|| AppError::not_found(format!("Store with id '{}' not found", store_id)))?;
 
     // // Check if new title conflicts with existing stores (excluding current one)
     // for existing_store in &stores_data.configs {
@@ -677,79 +1244,402 @@ pub async fn update_config(
     //     }
     // }
 
-    // Update the store
-    let store = &mut stores_data.configs[store_index];
-    store.title = title.clone();
-    store.settings = settings.clone();
+    settings_schema::ensure_valid(&settings)?;
+
+    if let Some(parents) = &extends {
+        profiles::validate_extends(&stores_data.configs, &store_id, parents)?;
+    }
+
+    // Remember what the stores in use applied before any of their settings change
+    let active_owned: Vec<(String, Vec<OwnedKey>)> = stores_data
+        .configs
+        .iter()
+        .filter(|store| store.using)
+        .map(|store| Ok((store.id.clone(), applied_ownership(&stores_data.configs, store)?)))
+        .collect::<Result<_, AppError>>()?;
+
+    // Tokens go to the secret vault, the profile only keeps a reference
+    let mut settings = settings;
+    secrets::extract_secrets(&store_id, &mut settings)?;
+
+    // Update the store
+    let store = &mut stores_data.configs[store_index];
+    let previous_secrets = secrets::owned_by_store(&store_id, &store.settings);
+    store.title = title.clone();
+    store.settings = settings.clone();
+    if let Some(parents) = extends {
+        store.extends = Some(parents).filter(|parents| !parents.is_empty());
+    }
+    if array_strategy.is_some() {
+        store.array_strategy = array_strategy;
+    }
+
+    // Refresh every settings file whose store in use is this one or inherits from it
+    for (active_id, previous_owned) in active_owned {
+        if active_id != store_id && !profiles::inherits_from(&stores_data.configs, &active_id, &store_id) {
+            continue;
+        }
+
+        let active_index = stores_data
+            .configs
+            .iter()
+            .position(|store| store.id == active_id)
+            .ok_or_else(|| AppError::not_found("Store not found"))?;
+        let active = &stores_data.configs[active_index];
+        let (effective_settings, used_secrets) = materialize_settings(&stores_data.configs, &active.id)?;
+
+        let owned_keys = apply_settings_to_file(
+            &store_settings_path(&claude_dir, active),
+            &previous_owned,
+            &effective_settings,
+            active.array_strategy.unwrap_or_default(),
+        )?;
+        stores_data.configs[active_index].owned_keys = Some(conceal_ownership(&active_id, owned_keys, &used_secrets)?);
+    }
+
+    // Write back to stores file
+    let json_content = serde_json::to_string_pretty(&stores_data)
+        .map_err(|e| format!("Failed to serialize stores: {}", e))?;
+
+    write_atomic(&stores_file, json_content)
+        .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))?;
+
+    let updated_store = stores_data.configs[store_index].clone();
+    if let Err(e) = history::record(&updated_store, trigger) {
+        eprintln!("Warning: Failed to record revision: {}", e);
+    }
+
+    // Drop vault entries neither the profile nor its history refer to anymore
+    let mut current_secrets = secrets::owned_by_store(&store_id, &settings);
+    for revision in history::load(&store_id).unwrap_or_default() {
+        current_secrets.extend(secrets::owned_by_store(&store_id, &revision.settings));
+    }
+    for name in previous_secrets.iter().filter(|name| !current_secrets.contains(name)) {
+        if let Err(e) = secrets::delete_secret(name) {
+            eprintln!("Warning: Failed to delete unused secret '{}': {}", name, e);
+        }
+    }
+
+    // Automatically unlock CC extension when updating config
+    if let Err(e) = unlock_cc_ext().await {
+        eprintln!("Warning: Failed to unlock CC extension: {}", e);
+    }
+
+    Ok(updated_store)
+}
+
+#[tauri::command]
+pub async fn list_config_revisions(store_id: String) -> Result<Vec<Revision>, AppError> {
+    let mut revisions = history::load(&store_id)?;
+    // Newest first
+    revisions.reverse();
+    Ok(revisions)
+}
+
+/// Diff two revisions of a store, or a revision against the store's current settings.
+#[tauri::command]
+pub async fn diff_config_revisions(
+    store_id: String,
+    from_revision: String,
+    to_revision: Option<String>,
+) -> Result<Vec<DiffEntry>, AppError> {
+    let from = history::find(&store_id, &from_revision)?;
+    let to_settings = match to_revision {
+        Some(revision_id) => history::find(&store_id, &revision_id)?.settings,
+        None => get_store(store_id).await?.settings,
+    };
+
+    Ok(json_diff::diff(&from.settings, &to_settings))
+}
+
+/// Two settings documents to compare, with secrets masked.
+#[derive(Debug, Clone)]
+pub struct ConfigDiffDocuments {
+    pub from_label: String,
+    pub from: Value,
+    pub to_label: String,
+    pub to: Value,
+}
+
+/// Effective settings of store `a` and of store `b`, or of the live settings
+/// file `a` is written to when `b` is None. Secrets are resolved before
+/// masking, so the same token stored under two names doesn't show up as a change.
+pub async fn diff_config_documents(store_a: String, store_b: Option<String>) -> Result<ConfigDiffDocuments, AppError> {
+    let stores = get_stores().await?;
+    let find = |id: &str| {
+        stores
+            .iter()
+            .find(|store| store.id == id)
+            .ok_or_else(|| AppError::not_found(format!("Store with id '{}' not found", id)))
+    };
+
+    // A locked vault still allows a diff, secrets then compare by reference
+    let effective = |store: &ConfigStore| -> Result<(Value, Vec<(String, String)>), AppError> {
+        let settings = profiles::resolve_settings(&stores, &store.id)?;
+        let owners = profiles::lineage(&stores, &store.id);
+        Ok(secrets::resolve_refs(&settings, &owners).unwrap_or((settings, vec![])))
+    };
+
+    let a = find(&store_a)?;
+    let (from, mut used_secrets) = effective(a)?;
+
+    let (to_label, to) = match store_b {
+        Some(store_b) => {
+            let b = find(&store_b)?;
+            let (to, used) = effective(b)?;
+            used_secrets.extend(used);
+            (b.title.clone(), to)
+        }
+        None => {
+            let settings_path = store_settings_path(&paths::claude_dir()?, a);
+            (settings_path.display().to_string(), read_settings_or_empty(&settings_path)?)
+        }
+    };
+
+    Ok(ConfigDiffDocuments {
+        from_label: a.title.clone(),
+        from: secrets::mask(&from, &used_secrets),
+        to_label,
+        to: secrets::mask(&to, &used_secrets),
+    })
+}
+
+/// JSON pointer level diff between two profiles, or a profile and its live settings file.
+#[tauri::command]
+pub async fn diff_configs(store_a: String, store_b: Option<String>) -> Result<Vec<DiffEntry>, AppError> {
+    let documents = diff_config_documents(store_a, store_b).await?;
+    Ok(json_diff::diff(&documents.from, &documents.to))
+}
+
+#[tauri::command]
+pub async fn restore_config_revision(store_id: String, revision_id: String) -> Result<ConfigStore, AppError> {
+    let revision = history::find(&store_id, &revision_id)?;
+    let exists = get_stores().await?.iter().any(|store| store.id == store_id);
+
+    if !exists {
+        // Bring a deleted store back under its old id
+        let store = create_config(
+            store_id,
+            revision.title,
+            revision.settings,
+            revision.extends,
+            revision.array_strategy,
+        )
+        .await?;
+        return Ok(store);
+    }
+
+    update_store(
+        store_id,
+        revision.title,
+        revision.settings,
+        Some(revision.extends.unwrap_or_default()),
+        revision.array_strategy,
+        RevisionTrigger::Restored,
+    )
+    .await
+}
+
+/// Delete the history and vault secrets of deleted stores, which are kept so
+/// a store can be restored. Stores whose deletion can still be undone are left
+/// alone. Returns the ids of the stores that were purged.
+#[tauri::command]
+pub async fn purge_deleted_configs() -> Result<Vec<String>, AppError> {
+    let stores = get_stores().await?;
+    let undoable: Vec<String> = undo::load()?
+        .into_iter()
+        .flat_map(|entry| entry.changes)
+        .filter_map(|change| match change {
+            undo::Change::Store { store, .. } => Some(store.id),
+            _ => None,
+        })
+        .collect();
 
-    // If this store is currently in use, also update the user's settings.json with partial update
-    if store.using {
-        let user_settings_path = home_dir.join(".claude/settings.json");
+    let secret_names = secrets::list_secrets()?;
+    let mut purged = vec![];
+    for store_id in history::store_ids()? {
+        if stores.iter().any(|store| store.id == store_id) || undoable.contains(&store_id) {
+            continue;
+        }
 
-        // Create .claude directory if it doesn't exist
-        if let Some(parent) = user_settings_path.parent() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+        let owners = [store_id.clone()];
+        for name in secret_names.iter().filter(|name| secrets::is_owned(name, &owners)) {
+            if let Err(e) = secrets::delete_secret(name) {
+                eprintln!("Warning: Failed to delete secret '{}': {}", name, e);
+            }
         }
 
-        // Read existing settings if file exists, otherwise start with empty object
-        let mut existing_settings = if user_settings_path.exists() {
-            let content = std::fs::read_to_string(&user_settings_path)
-                .map_err(|e| format!("Failed to read existing settings: {}", e))?;
-            serde_json::from_str(&content)
-                .map_err(|e| format!("Failed to parse existing settings: {}", e))?
-        } else {
-            serde_json::Value::Object(serde_json::Map::new())
-        };
+        history::remove(&store_id)?;
+        purged.push(store_id);
+    }
 
-        // Merge the new settings into existing settings (partial update)
-        if let Some(settings_obj) = settings.as_object() {
-            if let Some(existing_obj) = existing_settings.as_object_mut() {
-                // Update only the keys present in the stored settings
-                for (key, value) in settings_obj {
-                    existing_obj.insert(key.clone(), value.clone());
-                }
-            } else {
-                // If existing settings is not an object, replace it entirely
-                existing_settings = settings.clone();
+    Ok(purged)
+}
+
+/// Write profiles to a portable bundle (JSON text). All profiles are exported
+/// when `store_ids` is None; secrets are redacted unless asked otherwise.
+#[tauri::command]
+pub async fn export_profiles(
+    store_ids: Option<Vec<String>>,
+    secret_mode: Option<SecretMode>,
+    passphrase: Option<String>,
+) -> Result<String, AppError> {
+    let stores = get_stores().await?;
+    let bundle = bundle::export(
+        &stores,
+        store_ids.as_deref(),
+        secret_mode.unwrap_or_default(),
+        passphrase.as_deref(),
+    )?;
+
+    Ok(serde_json::to_string_pretty(&bundle).map_err(|e| format!("Failed to serialize bundle: {}", e))?)
+}
+
+/// Write an exported bundle to a file only the current user can read, since
+/// it may hold plaintext tokens.
+pub fn write_bundle_file(path: &std::path::Path, content: &str) -> Result<(), AppError> {
+    write_private(path, content).map_err(|e| AppError::io("Failed to write export file", path, e))
+}
+
+/// List a bundle's profiles and which of them clash with existing ones.
+#[tauri::command]
+pub async fn preview_profile_bundle(content: String) -> Result<BundlePreview, AppError> {
+    let bundle = bundle::parse(&content, "bundle")?;
+    let stores = get_stores().await?;
+    Ok(bundle::preview(&bundle, &stores))
+}
+
+/// A secret redacted on export would overwrite the profile's token with an
+/// empty string; the value or vault reference the profile already has is kept
+/// instead, and only the secrets still missing stay in `redacted`.
+fn keep_redacted_secrets(profile: &mut BundleProfile, current: &Value) {
+    let mut missing = Vec::new();
+    for pointer in std::mem::take(&mut profile.redacted) {
+        match current.pointer(&pointer) {
+            Some(value) if value.as_str() != Some("") => {
+                merge::set_pointer(&mut profile.settings, &merge::parse_pointer(&pointer), Some(value.clone()));
             }
-        } else {
-            // If stored settings is not an object, replace existing entirely
-            existing_settings = settings.clone();
+            _ => missing.push(pointer),
         }
+    }
+    profile.redacted = missing;
+}
 
-        // Write the merged settings back to file
-        let json_content = serde_json::to_string_pretty(&existing_settings)
-            .map_err(|e| format!("Failed to serialize merged settings: {}", e))?;
+/// Import a bundle. Conflicting profiles are handled by `conflict` (rename by
+/// default), or per bundle profile id through `resolutions`.
+#[tauri::command]
+pub async fn import_profiles(
+    content: String,
+    passphrase: Option<String>,
+    conflict: Option<ConflictStrategy>,
+    resolutions: Option<HashMap<String, ConflictStrategy>>,
+) -> Result<ImportReport, AppError> {
+    let bundle = bundle::parse(&content, "bundle")?;
+    let profiles = bundle::open(&bundle, passphrase.as_deref())?;
+    let existing = get_stores().await?;
+    let steps = bundle::plan(
+        &profiles,
+        &existing,
+        conflict.unwrap_or_default(),
+        &resolutions.unwrap_or_default(),
+    );
 
-        std::fs::write(&user_settings_path, json_content)
-            .map_err(|e| format!("Failed to write user settings: {}", e))?;
+    let mut report = ImportReport::default();
+    let existing_settings: HashMap<String, Value> =
+        existing.iter().map(|store| (store.id.clone(), store.settings.clone())).collect();
+    let mut available: HashSet<String> = existing.into_iter().map(|store| store.id).collect();
+    let mut pending = Vec::new();
+    for step in steps {
+        match &step.action {
+            ImportAction::Skip(reason) => report.skipped.push(SkippedProfile {
+                id: step.source_id.clone(),
+                title: step.profile.title.clone(),
+                reason: reason.clone(),
+            }),
+            _ => pending.push(step),
+        }
     }
 
-    // Write back to stores file
-    let json_content = serde_json::to_string_pretty(&stores_data)
-        .map_err(|e| format!("Failed to serialize stores: {}", e))?;
+    // Parents have to exist before the profiles extending them
+    while !pending.is_empty() {
+        let (ready, waiting): (Vec<_>, Vec<_>) = pending.into_iter().partition(|step| {
+            step.profile
+                .extends
+                .iter()
+                .flatten()
+                .all(|parent| available.contains(parent))
+        });
 
-    std::fs::write(&stores_file, json_content)
-        .map_err(|e| format!("Failed to write stores file: {}", e))?;
+        if ready.is_empty() {
+            report.skipped.extend(waiting.into_iter().map(|step| SkippedProfile {
+                id: step.source_id,
+                title: step.profile.title,
+                reason: "it extends a profile that is neither in the bundle nor on this machine".to_string(),
+            }));
+            break;
+        }
 
-    // Automatically unlock CC extension when updating config
-    if let Err(e) = unlock_cc_ext().await {
-        eprintln!("Warning: Failed to unlock CC extension: {}", e);
+        for step in ready {
+            let mut profile = step.profile;
+            let overwritten = step.action == ImportAction::Overwrite;
+            if overwritten {
+                if let Some(current) = existing_settings.get(&profile.id) {
+                    keep_redacted_secrets(&mut profile, current);
+                }
+            }
+            let result = if overwritten {
+                update_store(
+                    profile.id.clone(),
+                    profile.title.clone(),
+                    profile.settings,
+                    Some(profile.extends.unwrap_or_default()),
+                    profile.array_strategy,
+                    RevisionTrigger::Updated,
+                )
+                .await
+            } else {
+                create_config(
+                    profile.id.clone(),
+                    profile.title.clone(),
+                    profile.settings,
+                    profile.extends,
+                    profile.array_strategy,
+                )
+                .await
+            };
+
+            match result {
+                Ok(store) => {
+                    available.insert(store.id.clone());
+                    report.imported.push(ImportedProfile {
+                        id: store.id,
+                        title: store.title,
+                        source_id: step.source_id,
+                        overwritten,
+                        redacted: profile.redacted,
+                    });
+                }
+                Err(e) => report.skipped.push(SkippedProfile {
+                    id: step.source_id,
+                    title: profile.title,
+                    reason: e.message,
+                }),
+            }
+        }
+        pending = waiting;
     }
 
-    Ok(stores_data.configs[store_index].clone())
+    Ok(report)
 }
 
 #[tauri::command]
-pub async fn open_config_path() -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
+pub async fn open_config_path() -> Result<(), AppError> {
+    let app_config_path = paths::app_config_dir()?;
 
     // Ensure the directory exists
     if !app_config_path.exists() {
         std::fs::create_dir_all(&app_config_path)
-            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+            .map_err(|e| AppError::io("Failed to create config directory", &app_config_path, e))?;
     }
 
     // Open the directory in the system's file manager
@@ -780,22 +1670,180 @@ pub async fn open_config_path() -> Result<(), String> {
     Ok(())
 }
 
+// Secret vault functions
+
+#[tauri::command]
+pub async fn get_vault_status() -> Result<VaultStatus, AppError> {
+    secrets::status()
+}
+
+#[tauri::command]
+pub async fn list_secrets() -> Result<Vec<String>, AppError> {
+    secrets::list_secrets()
+}
+
+#[tauri::command]
+pub async fn set_secret(name: String, value: String) -> Result<Value, AppError> {
+    secrets::set_secret(&name, &value)?;
+    // Hand back the reference to put into a profile's settings. Profiles only
+    // resolve their own `<store id>-<env key>` secrets, see `secrets::resolve_refs`
+    Ok(secrets::secret_ref(&name))
+}
+
+#[tauri::command]
+pub async fn delete_secret(name: String) -> Result<(), AppError> {
+    secrets::delete_secret(&name)
+}
+
+#[tauri::command]
+pub async fn unlock_vault(passphrase: String) -> Result<(), AppError> {
+    secrets::unlock(&passphrase)
+}
+
+#[tauri::command]
+pub async fn set_vault_passphrase(passphrase: Option<String>) -> Result<(), AppError> {
+    secrets::set_passphrase(passphrase.as_deref())
+}
+
+#[tauri::command]
+pub async fn set_vault_backend(backend: VaultBackend) -> Result<(), AppError> {
+    secrets::set_backend(backend)
+}
+
 // MCP Server management functions
 
 #[tauri::command]
-pub async fn get_global_mcp_servers() -> Result<std::collections::HashMap<String, McpServer>, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let claude_json_path = home_dir.join(".claude.json");
+pub async fn get_sync_status() -> Result<SyncConfig, AppError> {
+    Ok(sync::load()?)
+}
+
+/// Use a shared directory or git checkout as the team source; `None` turns sync off.
+#[tauri::command]
+pub async fn set_sync_source(source: Option<String>, pull: bool) -> Result<SyncConfig, AppError> {
+    sync::set_source(source.as_deref(), pull)
+}
+
+#[tauri::command]
+pub async fn run_sync(app: tauri::AppHandle) -> Result<SyncReport, AppError> {
+    let report = sync::run().await?;
+
+    // Synced profiles show up in the tray
+    if let Err(e) = crate::tray::rebuild_tray_menu(app).await {
+        eprintln!("Warning: Failed to rebuild tray menu: {}", e);
+    }
+
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn resolve_sync_conflict(item: SyncItem, resolution: SyncResolution) -> Result<SyncConfig, AppError> {
+    sync::resolve(item, resolution).await
+}
+
+/// Revert the active user-level profile from the current home's settings.json
+/// and mark it as not in use. Profiles pinned to a project are left alone.
+fn release_user_profile() -> Result<(), AppError> {
+    let stores_file = paths::stores_file()?;
+    if !stores_file.exists() {
+        return Ok(());
+    }
+
+    let content = std::fs::read_to_string(&stores_file)
+        .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
+
+    let mut stores_data: StoresData = serde_json::from_str(&content)
+        .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?;
+
+    let active: Vec<&ConfigStore> = stores_data
+        .configs
+        .iter()
+        .filter(|store| store.using && store.project.is_none())
+        .collect();
+    if active.is_empty() {
+        return Ok(());
+    }
+    let previous_owned: Vec<OwnedKey> = active
+        .into_iter()
+        .map(|store| applied_ownership(&stores_data.configs, store))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let user_settings_path = paths::user_settings_path()?;
+    if !previous_owned.is_empty() && user_settings_path.exists() {
+        update_json_file(&user_settings_path, |existing_settings| {
+            merge::revert_profile(existing_settings, &previous_owned);
+            Ok(())
+        })?;
+    }
+
+    for store in stores_data.configs.iter_mut().filter(|store| store.project.is_none()) {
+        store.using = false;
+        store.owned_keys = None;
+    }
+
+    let json_content = serde_json::to_string_pretty(&stores_data)
+        .map_err(|e| format!("Failed to serialize stores: {}", e))?;
+
+    write_atomic(&stores_file, json_content)
+        .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))
+}
+
+#[tauri::command]
+pub async fn list_claude_homes() -> Result<ClaudeHomes, AppError> {
+    Ok(paths::load_homes()?)
+}
+
+#[tauri::command]
+pub async fn add_claude_home(name: String, path: String) -> Result<ClaudeHomes, AppError> {
+    Ok(paths::add_home(&name, &path)?)
+}
+
+#[tauri::command]
+pub async fn remove_claude_home(name: String) -> Result<ClaudeHomes, AppError> {
+    // Removing the active home switches back to the default one
+    if paths::load_homes()?.active.as_deref() == Some(name.as_str()) {
+        release_user_profile()?;
+    }
+    Ok(paths::remove_home(&name)?)
+}
+
+/// Point every Claude file access at another home; `None` goes back to the default one.
+#[tauri::command]
+pub async fn set_active_claude_home(app: tauri::AppHandle, name: Option<String>) -> Result<ClaudeHomes, AppError> {
+    // What the active profile owns describes the current home's settings.json, so it
+    // is taken back there instead of being reverted into the other home later
+    if paths::load_homes()?.active != name {
+        release_user_profile()?;
+    }
+    let homes = paths::set_active_home(name.as_deref())?;
+
+    // The watched files moved with the home
+    if let Err(e) = crate::watcher::start(&app) {
+        eprintln!("Warning: Failed to restart file watcher: {}", e);
+    }
+
+    if let Err(e) = crate::tray::rebuild_tray_menu(app).await {
+        eprintln!("Warning: Failed to rebuild tray menu: {}", e);
+    }
+
+    Ok(homes)
+}
+
+#[tauri::command]
+pub async fn get_global_mcp_servers() -> Result<std::collections::HashMap<String, McpServer>, AppError> {
+    let claude_json_path = paths::claude_json_path()?;
 
     if !claude_json_path.exists() {
         return Ok(std::collections::HashMap::new());
     }
 
     let content = std::fs::read_to_string(&claude_json_path)
-        .map_err(|e| format!("Failed to read .claude.json: {}", e))?;
+        .map_err(|e| AppError::io("Failed to read .claude.json", &claude_json_path, e))?;
 
     let json_value: Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse .claude.json: {}", e))?;
+        .map_err(|e| AppError::parse("Failed to parse .claude.json", &claude_json_path, e))?;
 
     let mcp_servers_obj = json_value.get("mcpServers")
         .and_then(|servers| servers.as_object())
@@ -814,7 +1862,7 @@ pub async fn get_global_mcp_servers() -> Result<std::collections::HashMap<String
 }
 
 #[tauri::command]
-pub async fn check_mcp_server_exists(server_name: String) -> Result<bool, String> {
+pub async fn check_mcp_server_exists(server_name: String) -> Result<bool, AppError> {
     let mcp_servers = get_global_mcp_servers().await?;
     Ok(mcp_servers.contains_key(&server_name))
 }
@@ -823,90 +1871,68 @@ pub async fn check_mcp_server_exists(server_name: String) -> Result<bool, String
 pub async fn update_global_mcp_server(
     server_name: String,
     server_config: Value,
-) -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let claude_json_path = home_dir.join(".claude.json");
-
-    // Read existing .claude.json or create new structure
-    let mut json_value = if claude_json_path.exists() {
-        let content = std::fs::read_to_string(&claude_json_path)
-            .map_err(|e| format!("Failed to read .claude.json: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse .claude.json: {}", e))?
-    } else {
-        Value::Object(serde_json::Map::new())
-    };
-
-    // Update mcpServers object
-    let mcp_servers = json_value
-        .as_object_mut()
-        .unwrap()
-        .entry("mcpServers".to_string())
-        .or_insert_with(|| Value::Object(serde_json::Map::new()))
-        .as_object_mut()
-        .unwrap();
-
-    // Update the specific server
-    mcp_servers.insert(server_name, server_config);
-
-    // Write back to file
-    let json_content = serde_json::to_string_pretty(&json_value)
-        .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
-
-    std::fs::write(&claude_json_path, json_content)
-        .map_err(|e| format!("Failed to write .claude.json: {}", e))?;
-
-    Ok(())
+) -> Result<(), AppError> {
+    let claude_json_path = paths::claude_json_path()?;
+
+    // Claude Code rewrites .claude.json constantly, so only patch our server entry under the lock
+    update_json_file(&claude_json_path, |json_value| {
+        let root = json_value
+            .as_object_mut()
+            .ok_or(".claude.json is not a JSON object")?;
+
+        // Update mcpServers object
+        let mcp_servers = root
+            .entry("mcpServers".to_string())
+            .or_insert_with(|| Value::Object(serde_json::Map::new()))
+            .as_object_mut()
+            .ok_or("mcpServers in .claude.json is not an object")?;
+
+        // Update the specific server
+        mcp_servers.insert(server_name.clone(), server_config.clone());
+        Ok(())
+    })
+    .map_err(|e| e.context("Failed to update .claude.json"))
 }
 
 #[tauri::command]
-pub async fn delete_global_mcp_server(server_name: String) -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let claude_json_path = home_dir.join(".claude.json");
+pub async fn delete_global_mcp_server(server_name: String) -> Result<(), AppError> {
+    let claude_json_path = paths::claude_json_path()?;
 
     if !claude_json_path.exists() {
-        return Err("Claude configuration file does not exist".to_string());
+        return Err("Claude configuration file does not exist".into());
     }
 
-    // Read existing .claude.json
-    let content = std::fs::read_to_string(&claude_json_path)
-        .map_err(|e| format!("Failed to read .claude.json: {}", e))?;
-
-    let mut json_value: Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse .claude.json: {}", e))?;
-
-    // Check if mcpServers exists
-    let mcp_servers = json_value
-        .as_object_mut()
-        .unwrap()
-        .get_mut("mcpServers")
-        .and_then(|servers| servers.as_object_mut());
+    let snapshot = undo::Snapshot::new(format!("Delete MCP server \"{}\"", server_name))
+        .json_value(&claude_json_path, &["mcpServers", &server_name])?;
 
-    let mcp_servers = match mcp_servers {
-        Some(servers) => servers,
-        None => return Err("No mcpServers found in .claude.json".to_string()),
-    };
+    update_json_file(&claude_json_path, |json_value| {
+        let root = json_value
+            .as_object_mut()
+            .ok_or(".claude.json is not a JSON object")?;
 
-    // Check if the server exists
-    if !mcp_servers.contains_key(&server_name) {
-        return Err(format!("MCP server '{}' not found", server_name));
-    }
+        // Check if mcpServers exists
+        let mcp_servers = match root.get_mut("mcpServers").and_then(|servers| servers.as_object_mut()) {
+            Some(servers) => servers,
+            None => return Err(AppError::not_found("No mcpServers found in .claude.json")),
+        };
 
-    // Remove the server
-    mcp_servers.remove(&server_name);
+        // Check if the server exists
+        if !mcp_servers.contains_key(&server_name) {
+            return Err(AppError::not_found(format!("MCP server '{}' not found", server_name)));
+        }
 
-    // If mcpServers is now empty, we can optionally remove the entire mcpServers object
-    if mcp_servers.is_empty() {
-        json_value.as_object_mut().unwrap().remove("mcpServers");
-    }
+        // Remove the server
+        mcp_servers.remove(&server_name);
 
-    // Write back to file
-    let json_content = serde_json::to_string_pretty(&json_value)
-        .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+        // If mcpServers is now empty, we can optionally remove the entire mcpServers object
+        if mcp_servers.is_empty() {
+            root.remove("mcpServers");
+        }
 
-    std::fs::write(&claude_json_path, json_content)
-        .map_err(|e| format!("Failed to write .claude.json: {}", e))?;
+        Ok(())
+    })?;
 
+    record_undo(snapshot);
     Ok(())
 }
 
@@ -919,7 +1945,7 @@ pub struct UpdateInfo {
 }
 
 #[tauri::command]
-pub async fn check_for_updates(app: tauri::AppHandle) -> Result<UpdateInfo, String> {
+pub async fn check_for_updates(app: tauri::AppHandle) -> Result<UpdateInfo, AppError> {
     println!("🔍 Checking for updates...");
     println!("📱 App version: {}", app.package_info().version);
     println!("🏷️  App identifier: {}", app.package_info().name);
@@ -957,40 +1983,39 @@ pub async fn check_for_updates(app: tauri::AppHandle) -> Result<UpdateInfo, Stri
                 }
                 Err(e) => {
                     println!("❌ Error checking for updates: {}", e);
-                    Err(format!("Failed to check for updates: {}", e))
+                    Err(AppError::network(format!("Failed to check for updates: {}", e)))
                 }
             }
         }
         Err(e) => {
             println!("❌ Failed to initialize updater: {}", e);
-            Err(format!("Failed to get updater: {}", e))
+            Err(format!("Failed to get updater: {}", e).into())
         }
     }
 }
 
 #[tauri::command]
-pub async fn rebuild_tray_menu_command(app: tauri::AppHandle) -> Result<(), String> {
-    crate::tray::rebuild_tray_menu(app).await
+pub async fn rebuild_tray_menu_command(app: tauri::AppHandle) -> Result<(), AppError> {
+    Ok(crate::tray::rebuild_tray_menu(app).await?)
 }
 
 #[tauri::command]
-pub async fn unlock_cc_ext() -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let claude_config_path = home_dir.join(".claude/config.json");
+pub async fn unlock_cc_ext() -> Result<(), AppError> {
+    let claude_config_path = paths::claude_path("config.json")?;
 
     // Ensure .claude directory exists
     if let Some(parent) = claude_config_path.parent() {
         std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+            .map_err(|e| AppError::io("Failed to create .claude directory", parent, e))?;
     }
 
     if claude_config_path.exists() {
         // File exists, check if primaryApiKey key exists
         let content = std::fs::read_to_string(&claude_config_path)
-            .map_err(|e| format!("Failed to read config.json: {}", e))?;
+            .map_err(|e| AppError::io("Failed to read config.json", &claude_config_path, e))?;
 
         let mut json_value: Value = serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse config.json: {}", e))?;
+            .map_err(|e| AppError::parse("Failed to parse config.json", &claude_config_path, e))?;
 
         // Check if primaryApiKey exists
         if json_value.get("primaryApiKey").is_none() {
@@ -1003,12 +2028,12 @@ pub async fn unlock_cc_ext() -> Result<(), String> {
             let json_content = serde_json::to_string_pretty(&json_value)
                 .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
 
-            std::fs::write(&claude_config_path, json_content)
-                .map_err(|e| format!("Failed to write config.json: {}", e))?;
+            write_atomic(&claude_config_path, json_content)
+                .map_err(|e| AppError::io("Failed to write config.json", &claude_config_path, e))?;
 
-            println!("Added primaryApiKey to existing config.json");
+            eprintln!("Added primaryApiKey to existing config.json");
         } else {
-            println!("primaryApiKey already exists in config.json, no action needed");
+            eprintln!("primaryApiKey already exists in config.json, no action needed");
         }
     } else {
         // File doesn't exist, create it with primaryApiKey
@@ -1019,20 +2044,52 @@ pub async fn unlock_cc_ext() -> Result<(), String> {
         let json_content = serde_json::to_string_pretty(&config)
             .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
 
-        std::fs::write(&claude_config_path, json_content)
-            .map_err(|e| format!("Failed to write config.json: {}", e))?;
+        write_atomic(&claude_config_path, json_content)
+            .map_err(|e| AppError::io("Failed to write config.json", &claude_config_path, e))?;
 
-        println!("Created new config.json with primaryApiKey");
+        eprintln!("Created new config.json with primaryApiKey");
     }
 
     Ok(())
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Debug)]
+/// Token usage of one API response, as Claude Code writes it to the transcript.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
 pub struct UsageData {
     pub input_tokens: Option<u64>,
+    pub cache_creation_input_tokens: Option<u64>,
     pub cache_read_input_tokens: Option<u64>,
     pub output_tokens: Option<u64>,
+    /// `cache_creation_input_tokens` split by cache lifetime
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation: Option<CacheCreation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_tool_use: Option<ServerToolUse>,
+    /// Fields we don't know yet, kept so they aren't lost
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+pub struct CacheCreation {
+    #[serde(default)]
+    pub ephemeral_5m_input_tokens: Option<u64>,
+    #[serde(default)]
+    pub ephemeral_1h_input_tokens: Option<u64>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+pub struct ServerToolUse {
+    #[serde(default)]
+    pub web_search_requests: Option<u64>,
+    #[serde(default)]
+    pub web_fetch_requests: Option<u64>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
@@ -1041,137 +2098,76 @@ pub struct ProjectUsageRecord {
     pub timestamp: String,
     pub model: Option<String>,
     pub usage: Option<UsageData>,
+    /// Working directory of the session
+    #[serde(default)]
+    pub project_path: Option<String>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Estimated from the price table when the records are read, None without a price
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
 }
 
+/// Usage records of every Claude Code transcript, served from the usage
+/// index which only reads what was appended since the last call.
+/// Lines that aren't valid JSON are skipped and reported, not fatal.
 #[tauri::command]
-pub async fn read_project_usage_files() -> Result<Vec<ProjectUsageRecord>, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let projects_dir = home_dir.join(".claude/projects");
-
-    println!("🔍 Looking for projects directory: {}", projects_dir.display());
+pub async fn read_project_usage_files() -> Result<UsageScan, AppError> {
+    let projects_dir = paths::claude_path("projects")?;
 
     if !projects_dir.exists() {
-        println!("❌ Projects directory does not exist");
-        return Ok(vec![]);
+        eprintln!("❌ Projects directory does not exist: {}", projects_dir.display());
+        return Ok(UsageScan {
+            records: vec![],
+            skipped_lines: 0,
+            failures: vec![],
+        });
     }
 
-    println!("✅ Projects directory exists");
-
-    let mut all_records = Vec::new();
-    let mut files_processed = 0;
-    let mut lines_processed = 0;
-
-    // Recursively find all .jsonl files in the projects directory and subdirectories
-    fn find_jsonl_files(dir: &std::path::Path, files: &mut Vec<std::path::PathBuf>) -> Result<(), String> {
-        let entries = std::fs::read_dir(dir)
-            .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
-
-        for entry in entries {
-            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-            let path = entry.path();
-
-            if path.is_file() && path.extension().map(|ext| ext == "jsonl").unwrap_or(false) {
-                files.push(path);
-            } else if path.is_dir() {
-                // Recursively search subdirectories
-                if let Err(e) = find_jsonl_files(&path, files) {
-                    println!("Warning: {}", e);
-                }
-            }
-        }
-        Ok(())
+    let mut scan = usage_index::refresh(&projects_dir)?;
+    PriceTable::load()?.apply(&mut scan.records);
+    if let Err(e) = crate::tray::update_usage_today(&scan.records) {
+        eprintln!("Warning: Failed to update today's usage: {}", e);
     }
 
-    let mut jsonl_files = Vec::new();
-    find_jsonl_files(&projects_dir, &mut jsonl_files)?;
-
-    for path in jsonl_files {
-        files_processed += 1;
-        // println!("📄 Processing file: {}", path.display());
-
-        // Read the JSONL file
-        let content = std::fs::read_to_string(&path)
-            .map_err(|e| format!("Failed to read file {}: {}", path.display(), e))?;
-
-        // Process each line in the JSONL file
-        for line in content.lines() {
-            if line.trim().is_empty() {
-                continue;
-            }
-
-            lines_processed += 1;
-
-            // Parse the JSON line
-            let json_value: Value = serde_json::from_str(line)
-                .map_err(|e| format!("Failed to parse JSON line: {}", e))?;
-
-            // Extract the required fields
-            let uuid = json_value.get("uuid")
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-
-            let timestamp = json_value.get("timestamp")
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
+    eprintln!(
+        "📊 Summary: {} usage records, {} lines skipped",
+        scan.records.len(),
+        scan.skipped_lines
+    );
+    Ok(scan)
+}
 
-            // Extract model field (optional) - check both top-level and nested in message field
-            let model = if let Some(model_str) = json_value.get("model")
-                .and_then(|v| v.as_str()) {
-                Some(model_str.to_string())
-            } else if let Some(message_obj) = json_value.get("message") {
-                message_obj.get("model")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string())
-            } else {
-                None
-            };
+/// Estimated cost of all usage, per day, model and project.
+#[tauri::command]
+pub async fn get_usage_costs() -> Result<UsageCosts, AppError> {
+    let scan = read_project_usage_files().await?;
+    Ok(pricing::usage_costs(&scan.records))
+}
 
-            // Extract usage data (optional) - check both top-level and nested in message field
-            let usage = if let Some(usage_obj) = json_value.get("usage") {
-                Some(UsageData {
-                    input_tokens: usage_obj.get("input_tokens").and_then(|v| v.as_u64()),
-                    cache_read_input_tokens: usage_obj.get("cache_read_input_tokens").and_then(|v| v.as_u64()),
-                    output_tokens: usage_obj.get("output_tokens").and_then(|v| v.as_u64()),
-                })
-            } else if let Some(message_obj) = json_value.get("message") {
-                if let Some(usage_obj) = message_obj.get("usage") {
-                    Some(UsageData {
-                        input_tokens: usage_obj.get("input_tokens").and_then(|v| v.as_u64()),
-                        cache_read_input_tokens: usage_obj.get("cache_read_input_tokens").and_then(|v| v.as_u64()),
-                        output_tokens: usage_obj.get("output_tokens").and_then(|v| v.as_u64()),
-                    })
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
+/// Usage aggregated by time period, model, project and session, with the
+/// records filtered by time range, model, project and session first.
+#[tauri::command]
+pub async fn query_usage(query: UsageQuery) -> Result<UsageQueryResult, AppError> {
+    let scan = read_project_usage_files().await?;
+    let mut result = usage_query::run(&scan.records, &query)?;
+    result.skipped_lines = scan.skipped_lines;
+    result.failures = scan.failures;
+    Ok(result)
+}
 
-            // Only include records with valid uuid, timestamp, and valid usage data
-            if !uuid.is_empty() && !timestamp.is_empty() {
-                // Check if usage data exists and has meaningful token values
-                if let Some(ref usage_data) = usage {
-                    let input_tokens = usage_data.input_tokens.unwrap_or(0);
-                    let output_tokens = usage_data.output_tokens.unwrap_or(0);
-
-                    // Only include if input_tokens + output_tokens > 0
-                    if input_tokens + output_tokens > 0 {
-                        all_records.push(ProjectUsageRecord {
-                            uuid,
-                            timestamp,
-                            model,
-                            usage,
-                        });
-                    }
-                }
-            }
-        }
-    }
+/// The price table in effect: the user's prices, then the built-in ones.
+#[tauri::command]
+pub async fn get_model_prices() -> Result<Vec<ModelPrice>, AppError> {
+    let mut prices = pricing::user_prices()?;
+    prices.extend(pricing::builtin_prices()?);
+    Ok(prices)
+}
 
-    println!("📊 Summary: Processed {} files, {} lines, found {} records", files_processed, lines_processed, all_records.len());
-    Ok(all_records)
+/// Replace the user's prices, which take precedence over the built-in ones.
+#[tauri::command]
+pub async fn set_model_prices(prices: Vec<ModelPrice>) -> Result<(), AppError> {
+    pricing::save_user_prices(&prices)
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
@@ -1182,15 +2178,14 @@ pub struct MemoryFile {
 }
 
 #[tauri::command]
-pub async fn read_claude_memory() -> Result<MemoryFile, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let claude_md_path = home_dir.join(".claude/CLAUDE.md");
+pub async fn read_claude_memory() -> Result<MemoryFile, AppError> {
+    let claude_md_path = paths::claude_path("CLAUDE.md")?;
 
     let path_str = claude_md_path.to_string_lossy().to_string();
 
     if claude_md_path.exists() {
         let content = std::fs::read_to_string(&claude_md_path)
-            .map_err(|e| format!("Failed to read CLAUDE.md file: {}", e))?;
+            .map_err(|e| AppError::io("Failed to read CLAUDE.md file", &claude_md_path, e))?;
 
         Ok(MemoryFile {
             path: path_str,
@@ -1207,24 +2202,23 @@ pub async fn read_claude_memory() -> Result<MemoryFile, String> {
 }
 
 #[tauri::command]
-pub async fn write_claude_memory(content: String) -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let claude_md_path = home_dir.join(".claude/CLAUDE.md");
+pub async fn write_claude_memory(content: String) -> Result<(), AppError> {
+    let claude_md_path = paths::claude_path("CLAUDE.md")?;
 
     // Ensure .claude directory exists
     if let Some(parent) = claude_md_path.parent() {
         std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+            .map_err(|e| AppError::io("Failed to create .claude directory", parent, e))?;
     }
 
-    std::fs::write(&claude_md_path, content)
-        .map_err(|e| format!("Failed to write CLAUDE.md file: {}", e))?;
+    write_atomic(&claude_md_path, content)
+        .map_err(|e| AppError::io("Failed to write CLAUDE.md file", &claude_md_path, e))?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub async fn install_and_restart(app: tauri::AppHandle) -> Result<(), String> {
+pub async fn install_and_restart(app: tauri::AppHandle) -> Result<(), AppError> {
     println!("🚀 Starting update installation process...");
 
     match app.updater() {
@@ -1266,44 +2260,43 @@ pub async fn install_and_restart(app: tauri::AppHandle) -> Result<(), String> {
                         }
                         Err(e) => {
                             println!("❌ Failed to install update: {}", e);
-                            Err(format!("Failed to install update: {}", e))
+                            Err(AppError::network(format!("Failed to install update: {}", e)))
                         }
                     }
                 }
                 Ok(None) => {
                     println!("ℹ️  No update available for installation");
-                    Err("No update available".to_string())
+                    Err("No update available".into())
                 }
                 Err(e) => {
                     println!("❌ Error checking for updates before installation: {}", e);
-                    Err(format!("Failed to check for updates: {}", e))
+                    Err(AppError::network(format!("Failed to check for updates: {}", e)))
                 }
             }
         }
         Err(e) => {
             println!("❌ Failed to get updater for installation: {}", e);
-            Err(format!("Failed to get updater: {}", e))
+            Err(format!("Failed to get updater: {}", e).into())
         }
     }
 }
 
 // Get or create distinct_id from stores.json
-async fn get_or_create_distinct_id() -> Result<String, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
-    let stores_file = app_config_path.join("stores.json");
+async fn get_or_create_distinct_id() -> Result<String, AppError> {
+    let app_config_path = paths::app_config_dir()?;
+    let stores_file = paths::stores_file()?;
 
     // Ensure app config directory exists
     std::fs::create_dir_all(&app_config_path)
-        .map_err(|e| format!("Failed to create app config directory: {}", e))?;
+        .map_err(|e| AppError::io("Failed to create app config directory", &app_config_path, e))?;
 
     // Read existing stores.json or create new one
     let mut stores_data = if stores_file.exists() {
         let content = std::fs::read_to_string(&stores_file)
-            .map_err(|e| format!("Failed to read stores file: {}", e))?;
+            .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
 
         serde_json::from_str::<StoresData>(&content)
-            .map_err(|e| format!("Failed to parse stores file: {}", e))?
+            .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?
     } else {
         StoresData {
             configs: vec![],
@@ -1327,8 +2320,8 @@ async fn get_or_create_distinct_id() -> Result<String, String> {
         let json_content = serde_json::to_string_pretty(&stores_data)
             .map_err(|e| format!("Failed to serialize stores data: {}", e))?;
 
-        std::fs::write(&stores_file, json_content)
-            .map_err(|e| format!("Failed to write stores file: {}", e))?;
+        write_atomic(&stores_file, json_content)
+            .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))?;
 
         println!("Created new distinct_id: {}", new_id);
         Ok(new_id)
@@ -1348,7 +2341,7 @@ fn get_os_name() -> &'static str {
 }
 
 // Get operating system version
-fn get_os_version() -> Result<String, String> {
+fn get_os_version() -> Result<String, AppError> {
     #[cfg(target_os = "macos")]
     {
         use std::process::Command;
@@ -1423,19 +2416,18 @@ pub struct ProjectConfig {
 }
 
 #[tauri::command]
-pub async fn read_claude_projects() -> Result<Vec<ProjectConfig>, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let claude_json_path = home_dir.join(".claude.json");
+pub async fn read_claude_projects() -> Result<Vec<ProjectConfig>, AppError> {
+    let claude_json_path = paths::claude_json_path()?;
 
     if !claude_json_path.exists() {
         return Ok(vec![]);
     }
 
     let content = std::fs::read_to_string(&claude_json_path)
-        .map_err(|e| format!("Failed to read .claude.json: {}", e))?;
+        .map_err(|e| AppError::io("Failed to read .claude.json", &claude_json_path, e))?;
 
     let json_value: Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse .claude.json: {}", e))?;
+        .map_err(|e| AppError::parse("Failed to parse .claude.json", &claude_json_path, e))?;
 
     let projects_obj = json_value.get("projects")
         .and_then(|projects| projects.as_object())
@@ -1462,18 +2454,17 @@ pub struct ClaudeConfigFile {
 }
 
 #[tauri::command]
-pub async fn read_claude_config_file() -> Result<ClaudeConfigFile, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let claude_json_path = home_dir.join(".claude.json");
+pub async fn read_claude_config_file() -> Result<ClaudeConfigFile, AppError> {
+    let claude_json_path = paths::claude_json_path()?;
 
     let path_str = claude_json_path.to_string_lossy().to_string();
 
     if claude_json_path.exists() {
         let content = std::fs::read_to_string(&claude_json_path)
-            .map_err(|e| format!("Failed to read .claude.json: {}", e))?;
+            .map_err(|e| AppError::io("Failed to read .claude.json", &claude_json_path, e))?;
 
         let json_content: Value = serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+            .map_err(|e| AppError::parse("Failed to parse JSON", &claude_json_path, e))?;
 
         Ok(ClaudeConfigFile {
             path: path_str,
@@ -1489,22 +2480,83 @@ pub async fn read_claude_config_file() -> Result<ClaudeConfigFile, String> {
     }
 }
 
+/// Saves `.claude.json` as edited in the UI.
+///
+/// `base` is the content the editor started from. When given, only the changes
+/// between `base` and `content` are applied on top of the file as it is on disk,
+/// so anything Claude Code wrote in the meantime is kept.
 #[tauri::command]
-pub async fn write_claude_config_file(content: Value) -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let claude_json_path = home_dir.join(".claude.json");
+pub async fn write_claude_config_file(content: Value, base: Option<Value>) -> Result<(), AppError> {
+    let claude_json_path = paths::claude_json_path()?;
 
-    let json_content = serde_json::to_string_pretty(&content)
-        .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+    let patch = base.map(|base| merge_patch_diff(&base, &content));
 
-    std::fs::write(&claude_json_path, json_content)
-        .map_err(|e| format!("Failed to write file: {}", e))?;
+    update_json_file(&claude_json_path, |json_value| {
+        match &patch {
+            Some(patch) => apply_merge_patch(json_value, patch),
+            None => *json_value = content.clone(),
+        }
+        Ok(())
+    })
+    .map_err(|e| e.context("Failed to write file"))
+}
 
-    Ok(())
+// Compute an RFC 7396 merge patch that turns `base` into `target`
+fn merge_patch_diff(base: &Value, target: &Value) -> Value {
+    match (base.as_object(), target.as_object()) {
+        (Some(base_obj), Some(target_obj)) => {
+            let mut patch = serde_json::Map::new();
+
+            for key in base_obj.keys() {
+                if !target_obj.contains_key(key) {
+                    patch.insert(key.clone(), Value::Null);
+                }
+            }
+
+            for (key, target_value) in target_obj {
+                match base_obj.get(key) {
+                    Some(base_value) if base_value == target_value => {}
+                    Some(base_value) => {
+                        patch.insert(key.clone(), merge_patch_diff(base_value, target_value));
+                    }
+                    None => {
+                        patch.insert(key.clone(), target_value.clone());
+                    }
+                }
+            }
+
+            Value::Object(patch)
+        }
+        _ => target.clone(),
+    }
+}
+
+// Apply an RFC 7396 merge patch in place
+fn apply_merge_patch(target: &mut Value, patch: &Value) {
+    let Some(patch_obj) = patch.as_object() else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(serde_json::Map::new());
+    }
+
+    let target_obj = target.as_object_mut().unwrap();
+    for (key, patch_value) in patch_obj {
+        if patch_value.is_null() {
+            target_obj.remove(key);
+        } else {
+            apply_merge_patch(
+                target_obj.entry(key.clone()).or_insert(Value::Null),
+                patch_value,
+            );
+        }
+    }
 }
 
 #[tauri::command]
-pub async fn track(event: String, properties: serde_json::Value, app: tauri::AppHandle) -> Result<(), String> {
+pub async fn track(event: String, properties: serde_json::Value, app: tauri::AppHandle) -> Result<(), AppError> {
     println!("📊 Tracking event: {}", event);
 
     // Get distinct_id
@@ -1563,7 +2615,7 @@ pub async fn track(event: String, properties: serde_json::Value, app: tauri::App
         let status = response.status();
         let error_text = response.text().await.unwrap_or_default();
         println!("❌ Failed to track event: {} - {}", status, error_text);
-        Err(format!("PostHog API error: {} - {}", status, error_text))
+        Err(AppError::network(format!("PostHog API error: {} - {}", status, error_text)))
     }
 }
 
@@ -1587,7 +2639,7 @@ fn get_latest_hook_command() -> serde_json::Value {
 }
 
 /// Update existing ccmate hooks for specified events (doesn't add new ones)
-fn update_existing_hooks(hooks_obj: &mut serde_json::Map<String, serde_json::Value>, events: &[&str]) -> Result<bool, String> {
+fn update_existing_hooks(hooks_obj: &mut serde_json::Map<String, serde_json::Value>, events: &[&str]) -> Result<bool, AppError> {
     let latest_hook_command = get_latest_hook_command();
     let latest_command_str = latest_hook_command.get("command")
         .and_then(|cmd| cmd.as_str())
@@ -1608,7 +2660,7 @@ fn update_existing_hooks(hooks_obj: &mut serde_json::Map<String, serde_json::Val
                                     // Update only the command field, preserve other properties
                                     hook["command"] = serde_json::Value::String(latest_command_str.to_string());
                                     hook_updated = true;
-                                    println!("🔄 Updated {} hook command: {}", event, latest_command_str);
+                                    eprintln!("🔄 Updated {} hook command: {}", event, latest_command_str);
                                 }
                             }
                         }
@@ -1622,7 +2674,7 @@ fn update_existing_hooks(hooks_obj: &mut serde_json::Map<String, serde_json::Val
 }
 
 /// Update or add ccmate hooks for specified events
-fn update_or_add_hooks(hooks_obj: &mut serde_json::Map<String, serde_json::Value>, events: &[&str]) -> Result<bool, String> {
+fn update_or_add_hooks(hooks_obj: &mut serde_json::Map<String, serde_json::Value>, events: &[&str]) -> Result<bool, AppError> {
     let latest_hook_command = get_latest_hook_command();
     let mut hook_updated = false;
 
@@ -1673,28 +2725,25 @@ fn update_or_add_hooks(hooks_obj: &mut serde_json::Map<String, serde_json::Value
 }
 
 #[tauri::command]
-pub async fn get_notification_settings() -> Result<Option<NotificationSettings>, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
-    let stores_file = app_config_path.join("stores.json");
+pub async fn get_notification_settings() -> Result<Option<NotificationSettings>, AppError> {
+    let stores_file = paths::stores_file()?;
 
     if !stores_file.exists() {
         return Ok(None);
     }
 
     let content = std::fs::read_to_string(&stores_file)
-        .map_err(|e| format!("Failed to read stores file: {}", e))?;
+        .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
 
     let stores_data: StoresData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse stores file: {}", e))?;
+        .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?;
 
     Ok(stores_data.notification)
 }
 
 #[tauri::command]
-pub async fn update_claude_code_hook() -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let settings_path = home_dir.join(".claude/settings.json");
+pub async fn update_claude_code_hook() -> Result<(), AppError> {
+    let settings_path = paths::user_settings_path()?;
 
     if !settings_path.exists() {
         // If settings file doesn't exist, just add the hooks
@@ -1703,10 +2752,10 @@ pub async fn update_claude_code_hook() -> Result<(), String> {
 
     // Read existing settings
     let content = std::fs::read_to_string(&settings_path)
-        .map_err(|e| format!("Failed to read settings.json: {}", e))?;
+        .map_err(|e| AppError::io("Failed to read settings.json", &settings_path, e))?;
 
     let mut settings: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse settings.json: {}", e))?;
+        .map_err(|e| AppError::parse("Failed to parse settings.json", &settings_path, e))?;
 
     // Ensure hooks object exists
     let hooks_obj = settings
@@ -1729,31 +2778,30 @@ pub async fn update_claude_code_hook() -> Result<(), String> {
         // Create .claude directory if it doesn't exist
         if let Some(parent) = settings_path.parent() {
             std::fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+                .map_err(|e| AppError::io("Failed to create .claude directory", parent, e))?;
         }
 
-        std::fs::write(&settings_path, json_content)
-            .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+        write_atomic(&settings_path, json_content)
+            .map_err(|e| AppError::io("Failed to write settings.json", &settings_path, e))?;
 
-        println!("✅ Claude Code hooks updated successfully");
+        eprintln!("✅ Claude Code hooks updated successfully");
     } else {
-        println!("ℹ️  Claude Code hooks are already up to date - no updates needed");
+        eprintln!("ℹ️  Claude Code hooks are already up to date - no updates needed");
     }
 
     Ok(())
 }
 
 #[tauri::command]
-pub async fn add_claude_code_hook() -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let settings_path = home_dir.join(".claude/settings.json");
+pub async fn add_claude_code_hook() -> Result<(), AppError> {
+    let settings_path = paths::user_settings_path()?;
 
     // Read existing settings or create new structure
     let mut settings = if settings_path.exists() {
         let content = std::fs::read_to_string(&settings_path)
-            .map_err(|e| format!("Failed to read settings.json: {}", e))?;
+            .map_err(|e| AppError::io("Failed to read settings.json", &settings_path, e))?;
         serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse settings.json: {}", e))?
+            .map_err(|e| AppError::parse("Failed to parse settings.json", &settings_path, e))?
     } else {
         serde_json::Value::Object(serde_json::Map::new())
     };
@@ -1778,20 +2826,19 @@ pub async fn add_claude_code_hook() -> Result<(), String> {
     // Create .claude directory if it doesn't exist
     if let Some(parent) = settings_path.parent() {
         std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+            .map_err(|e| AppError::io("Failed to create .claude directory", parent, e))?;
     }
 
-    std::fs::write(&settings_path, json_content)
-        .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+    write_atomic(&settings_path, json_content)
+        .map_err(|e| AppError::io("Failed to write settings.json", &settings_path, e))?;
 
-    println!("✅ Claude Code hooks added successfully");
+    eprintln!("✅ Claude Code hooks added successfully");
     Ok(())
 }
 
 #[tauri::command]
-pub async fn remove_claude_code_hook() -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let settings_path = home_dir.join(".claude/settings.json");
+pub async fn remove_claude_code_hook() -> Result<(), AppError> {
+    let settings_path = paths::user_settings_path()?;
 
     if !settings_path.exists() {
         return Ok(()); // Settings file doesn't exist, nothing to remove
@@ -1799,10 +2846,10 @@ pub async fn remove_claude_code_hook() -> Result<(), String> {
 
     // Read existing settings
     let content = std::fs::read_to_string(&settings_path)
-        .map_err(|e| format!("Failed to read settings.json: {}", e))?;
+        .map_err(|e| AppError::io("Failed to read settings.json", &settings_path, e))?;
 
     let mut settings: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse settings.json: {}", e))?;
+        .map_err(|e| AppError::parse("Failed to parse settings.json", &settings_path, e))?;
 
     // Check if hooks object exists
     if let Some(hooks_obj) = settings.get_mut("hooks").and_then(|h| h.as_object_mut()) {
@@ -1850,18 +2897,17 @@ pub async fn remove_claude_code_hook() -> Result<(), String> {
     let json_content = serde_json::to_string_pretty(&settings)
         .map_err(|e| format!("Failed to serialize settings: {}", e))?;
 
-    std::fs::write(&settings_path, json_content)
-        .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+    write_atomic(&settings_path, json_content)
+        .map_err(|e| AppError::io("Failed to write settings.json", &settings_path, e))?;
 
-    println!("✅ Claude Code hooks removed successfully");
+    eprintln!("✅ Claude Code hooks removed successfully");
     Ok(())
 }
 
 #[tauri::command]
-pub async fn update_notification_settings(settings: NotificationSettings) -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let app_config_path = home_dir.join(APP_CONFIG_DIR);
-    let stores_file = app_config_path.join("stores.json");
+pub async fn update_notification_settings(settings: NotificationSettings) -> Result<(), AppError> {
+    let app_config_path = paths::app_config_dir()?;
+    let stores_file = paths::stores_file()?;
 
     if !stores_file.exists() {
         // Create stores.json with notification settings if it doesn't exist
@@ -1873,13 +2919,13 @@ pub async fn update_notification_settings(settings: NotificationSettings) -> Res
 
         // Ensure app config directory exists
         std::fs::create_dir_all(&app_config_path)
-            .map_err(|e| format!("Failed to create app config directory: {}", e))?;
+            .map_err(|e| AppError::io("Failed to create app config directory", &app_config_path, e))?;
 
         let json_content = serde_json::to_string_pretty(&stores_data)
             .map_err(|e| format!("Failed to serialize stores: {}", e))?;
 
-        std::fs::write(&stores_file, json_content)
-            .map_err(|e| format!("Failed to write stores file: {}", e))?;
+        write_atomic(&stores_file, json_content)
+            .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))?;
 
         println!("Created stores.json with notification settings");
         return Ok(());
@@ -1887,10 +2933,10 @@ pub async fn update_notification_settings(settings: NotificationSettings) -> Res
 
     // Read existing stores
     let content = std::fs::read_to_string(&stores_file)
-        .map_err(|e| format!("Failed to read stores file: {}", e))?;
+        .map_err(|e| AppError::io("Failed to read stores file", &stores_file, e))?;
 
     let mut stores_data: StoresData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse stores file: {}", e))?;
+        .map_err(|e| AppError::parse("Failed to parse stores file", &stores_file, e))?;
 
     // Update notification settings
     stores_data.notification = Some(settings);
@@ -1899,8 +2945,8 @@ pub async fn update_notification_settings(settings: NotificationSettings) -> Res
     let json_content = serde_json::to_string_pretty(&stores_data)
         .map_err(|e| format!("Failed to serialize stores: {}", e))?;
 
-    std::fs::write(&stores_file, json_content)
-        .map_err(|e| format!("Failed to write stores file: {}", e))?;
+    write_atomic(&stores_file, json_content)
+        .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))?;
 
     println!("✅ Notification settings updated successfully");
     Ok(())
@@ -1916,9 +2962,8 @@ pub struct CommandFile {
 }
 
 #[tauri::command]
-pub async fn read_claude_commands() -> Result<Vec<CommandFile>, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let commands_dir = home_dir.join(".claude/commands");
+pub async fn read_claude_commands() -> Result<Vec<CommandFile>, AppError> {
+    let commands_dir = paths::claude_path("commands")?;
 
     if !commands_dir.exists() {
         return Ok(vec![]);
@@ -1928,10 +2973,10 @@ pub async fn read_claude_commands() -> Result<Vec<CommandFile>, String> {
 
     // Read all .md files in the commands directory
     let entries = std::fs::read_dir(&commands_dir)
-        .map_err(|e| format!("Failed to read commands directory: {}", e))?;
+        .map_err(|e| AppError::io("Failed to read commands directory", &commands_dir, e))?;
 
     for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let entry = entry.map_err(|e| AppError::io("Failed to read directory entry", &commands_dir, e))?;
         let path = entry.path();
 
         if path.is_file() && path.extension().map(|ext| ext == "md").unwrap_or(false) {
@@ -1941,7 +2986,7 @@ pub async fn read_claude_commands() -> Result<Vec<CommandFile>, String> {
                 .to_string();
 
             let content = std::fs::read_to_string(&path)
-                .map_err(|e| format!("Failed to read command file {}: {}", path.display(), e))?;
+                .map_err(|e| AppError::io("Failed to read command file", &path, e))?;
 
             command_files.push(CommandFile {
                 name: file_name,
@@ -1958,30 +3003,30 @@ pub async fn read_claude_commands() -> Result<Vec<CommandFile>, String> {
 }
 
 #[tauri::command]
-pub async fn write_claude_command(command_name: String, content: String) -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let commands_dir = home_dir.join(".claude/commands");
+pub async fn write_claude_command(command_name: String, content: String) -> Result<(), AppError> {
+    let commands_dir = paths::claude_path("commands")?;
     let command_file_path = commands_dir.join(format!("{}.md", command_name));
 
     // Ensure .claude/commands directory exists
     std::fs::create_dir_all(&commands_dir)
-        .map_err(|e| format!("Failed to create .claude/commands directory: {}", e))?;
+        .map_err(|e| AppError::io("Failed to create .claude/commands directory", &commands_dir, e))?;
 
-    std::fs::write(&command_file_path, content)
-        .map_err(|e| format!("Failed to write command file: {}", e))?;
+    write_atomic(&command_file_path, content)
+        .map_err(|e| AppError::io("Failed to write command file", &command_file_path, e))?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub async fn delete_claude_command(command_name: String) -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let commands_dir = home_dir.join(".claude/commands");
+pub async fn delete_claude_command(command_name: String) -> Result<(), AppError> {
+    let commands_dir = paths::claude_path("commands")?;
     let command_file_path = commands_dir.join(format!("{}.md", command_name));
 
     if command_file_path.exists() {
+        let snapshot = undo::Snapshot::new(format!("Delete command \"{}\"", command_name)).file(&command_file_path)?;
         std::fs::remove_file(&command_file_path)
-            .map_err(|e| format!("Failed to delete command file: {}", e))?;
+            .map_err(|e| AppError::io("Failed to delete command file", &command_file_path, e))?;
+        record_undo(snapshot);
     }
 
     Ok(())
@@ -1997,9 +3042,8 @@ pub struct AgentFile {
 }
 
 #[tauri::command]
-pub async fn read_claude_agents() -> Result<Vec<AgentFile>, String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let agents_dir = home_dir.join(".claude/agents");
+pub async fn read_claude_agents() -> Result<Vec<AgentFile>, AppError> {
+    let agents_dir = paths::claude_path("agents")?;
 
     if !agents_dir.exists() {
         return Ok(vec![]);
@@ -2009,10 +3053,10 @@ pub async fn read_claude_agents() -> Result<Vec<AgentFile>, String> {
 
     // Read all .md files in the agents directory
     let entries = std::fs::read_dir(&agents_dir)
-        .map_err(|e| format!("Failed to read agents directory: {}", e))?;
+        .map_err(|e| AppError::io("Failed to read agents directory", &agents_dir, e))?;
 
     for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+        let entry = entry.map_err(|e| AppError::io("Failed to read directory entry", &agents_dir, e))?;
         let path = entry.path();
 
         if path.is_file() && path.extension().map(|ext| ext == "md").unwrap_or(false) {
@@ -2022,7 +3066,7 @@ pub async fn read_claude_agents() -> Result<Vec<AgentFile>, String> {
                 .to_string();
 
             let content = std::fs::read_to_string(&path)
-                .map_err(|e| format!("Failed to read agent file {}: {}", path.display(), e))?;
+                .map_err(|e| AppError::io("Failed to read agent file", &path, e))?;
 
             agent_files.push(AgentFile {
                 name: file_name,
@@ -2039,31 +3083,56 @@ pub async fn read_claude_agents() -> Result<Vec<AgentFile>, String> {
 }
 
 #[tauri::command]
-pub async fn write_claude_agent(agent_name: String, content: String) -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let agents_dir = home_dir.join(".claude/agents");
+pub async fn write_claude_agent(agent_name: String, content: String) -> Result<(), AppError> {
+    let agents_dir = paths::claude_path("agents")?;
     let agent_file_path = agents_dir.join(format!("{}.md", agent_name));
 
     // Ensure .claude/agents directory exists
     std::fs::create_dir_all(&agents_dir)
-        .map_err(|e| format!("Failed to create .claude/agents directory: {}", e))?;
+        .map_err(|e| AppError::io("Failed to create .claude/agents directory", &agents_dir, e))?;
 
-    std::fs::write(&agent_file_path, content)
-        .map_err(|e| format!("Failed to write agent file: {}", e))?;
+    write_atomic(&agent_file_path, content)
+        .map_err(|e| AppError::io("Failed to write agent file", &agent_file_path, e))?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub async fn delete_claude_agent(agent_name: String) -> Result<(), String> {
-    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-    let agents_dir = home_dir.join(".claude/agents");
+pub async fn delete_claude_agent(agent_name: String) -> Result<(), AppError> {
+    let agents_dir = paths::claude_path("agents")?;
     let agent_file_path = agents_dir.join(format!("{}.md", agent_name));
 
     if agent_file_path.exists() {
+        let snapshot = undo::Snapshot::new(format!("Delete agent \"{}\"", agent_name)).file(&agent_file_path)?;
         std::fs::remove_file(&agent_file_path)
-            .map_err(|e| format!("Failed to delete agent file: {}", e))?;
+            .map_err(|e| AppError::io("Failed to delete agent file", &agent_file_path, e))?;
+        record_undo(snapshot);
     }
 
     Ok(())
 }
+
+// The change itself went through, so failing to journal it is only worth a warning
+fn record_undo(snapshot: undo::Snapshot) {
+    if let Err(e) = snapshot.commit() {
+        eprintln!("Warning: Failed to record undo entry: {}", e);
+    }
+}
+
+/// The change `undo_last_change` would revert, if any.
+#[tauri::command]
+pub async fn get_last_undoable_change() -> Result<Option<UndoEntry>, AppError> {
+    undo::latest()
+}
+
+/// Put back what the most recent destructive command removed or overwrote.
+#[tauri::command]
+pub async fn undo_last_change(app: tauri::AppHandle) -> Result<Option<UndoEntry>, AppError> {
+    let entry = undo::undo_last().map_err(|e| e.context("Failed to undo"))?;
+
+    if let Err(e) = crate::tray::rebuild_tray_menu(app).await {
+        eprintln!("Warning: Failed to rebuild tray menu: {}", e);
+    }
+
+    Ok(entry)
+}
@@ -0,0 +1,398 @@
+use serde_json::Value;
+use std::error::Error as _;
+use std::time::{Duration, Instant};
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const DEFAULT_MODEL: &str = "claude-sonnet-4-5";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where and how a profile talks to the model, taken from its settings the
+/// same way Claude Code reads them.
+#[derive(Debug, Clone)]
+pub struct ConnectionTarget {
+    pub base_url: String,
+    pub model: String,
+    pub auth: Option<Auth>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Auth {
+    /// `ANTHROPIC_AUTH_TOKEN`, sent as a bearer token
+    Bearer(String),
+    /// `ANTHROPIC_API_KEY`, sent as `x-api-key`
+    ApiKey(String),
+}
+
+impl ConnectionTarget {
+    /// From resolved settings, with vault references already replaced by the tokens.
+    pub fn from_settings(settings: &Value) -> ConnectionTarget {
+        let env_var = |key: &str| {
+            settings
+                .pointer(&format!("/env/{}", key))
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        let auth = env_var("ANTHROPIC_AUTH_TOKEN")
+            .map(Auth::Bearer)
+            .or_else(|| env_var("ANTHROPIC_API_KEY").map(Auth::ApiKey));
+
+        let model = env_var("ANTHROPIC_MODEL")
+            .or_else(|| settings.get("model").and_then(Value::as_str).map(str::to_string))
+            .or_else(|| env_var("ANTHROPIC_DEFAULT_SONNET_MODEL"))
+            .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+
+        ConnectionTarget {
+            base_url: env_var("ANTHROPIC_BASE_URL").unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            model,
+            auth,
+        }
+    }
+
+    fn messages_url(&self) -> String {
+        format!("{}/v1/messages", self.base_url.trim_end_matches('/'))
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConnectionStatus {
+    Ok,
+    /// No token in the profile
+    MissingCredentials,
+    /// 401 or 403
+    AuthFailed,
+    /// The provider doesn't know the configured model
+    UnknownModel,
+    /// 404 that isn't about the model, usually a wrong base URL
+    EndpointNotFound,
+    /// 429, the credentials work but the account is out of quota for now
+    RateLimited,
+    ServerError,
+    UnexpectedResponse,
+    InvalidUrl,
+    Dns,
+    Tls,
+    ConnectionFailed,
+    Timeout,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionReport {
+    pub status: ConnectionStatus,
+    pub base_url: String,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    /// Time until the response headers arrived, None when nothing was sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// What went wrong, with the provider's own error message when it sent one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl ConnectionReport {
+    fn new(target: &ConnectionTarget, status: ConnectionStatus) -> ConnectionReport {
+        ConnectionReport {
+            status,
+            base_url: target.base_url.clone(),
+            model: target.model.clone(),
+            http_status: None,
+            latency_ms: None,
+            message: None,
+        }
+    }
+
+    fn message(mut self, message: impl Into<String>) -> ConnectionReport {
+        self.message = Some(message.into());
+        self
+    }
+}
+
+/// Send the smallest possible messages request (`max_tokens: 1`) and
+/// classify the outcome. Never fails: every problem is part of the report.
+pub async fn check(target: &ConnectionTarget, timeout: Duration) -> ConnectionReport {
+    let Some(auth) = &target.auth else {
+        return ConnectionReport::new(target, ConnectionStatus::MissingCredentials)
+            .message("The profile has no ANTHROPIC_AUTH_TOKEN or ANTHROPIC_API_KEY");
+    };
+
+    let client = match reqwest::Client::builder().timeout(timeout).build() {
+        Ok(client) => client,
+        Err(e) => return ConnectionReport::new(target, ConnectionStatus::ConnectionFailed).message(e.to_string()),
+    };
+
+    let request = client
+        .post(target.messages_url())
+        .header("anthropic-version", ANTHROPIC_VERSION)
+        .json(&serde_json::json!({
+            "model": target.model,
+            "max_tokens": 1,
+            "messages": [{ "role": "user", "content": "ping" }],
+        }));
+    let request = match auth {
+        Auth::Bearer(token) => request.bearer_auth(token),
+        Auth::ApiKey(key) => request.header("x-api-key", key),
+    };
+
+    let started = Instant::now();
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            let mut report = ConnectionReport::new(target, classify_error(&e)).message(error_chain(&e));
+            if report.status == ConnectionStatus::Timeout {
+                report.latency_ms = Some(started.elapsed().as_millis() as u64);
+            }
+            return report;
+        }
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let http_status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let provider_message = error_message(&body);
+
+    let status = match http_status.as_u16() {
+        200..=299 => ConnectionStatus::Ok,
+        401 | 403 => ConnectionStatus::AuthFailed,
+        429 => ConnectionStatus::RateLimited,
+        400 | 404 if is_model_error(&body, provider_message.as_deref(), &target.model) => {
+            ConnectionStatus::UnknownModel
+        }
+        404 => ConnectionStatus::EndpointNotFound,
+        500..=599 => ConnectionStatus::ServerError,
+        _ => ConnectionStatus::UnexpectedResponse,
+    };
+
+    let mut report = ConnectionReport::new(target, status);
+    report.http_status = Some(http_status.as_u16());
+    report.latency_ms = Some(latency_ms);
+    if status != ConnectionStatus::Ok {
+        report.message = Some(provider_message.unwrap_or_else(|| format!("HTTP {}", http_status)));
+    }
+    report
+}
+
+fn classify_error(error: &reqwest::Error) -> ConnectionStatus {
+    if error.is_builder() {
+        return ConnectionStatus::InvalidUrl;
+    }
+    if error.is_timeout() {
+        return ConnectionStatus::Timeout;
+    }
+
+    // reqwest doesn't type these, the underlying hyper/TLS errors only show up in the text
+    let chain = error_chain(error).to_lowercase();
+    if chain.contains("dns error") || chain.contains("failed to lookup address") {
+        ConnectionStatus::Dns
+    } else if chain.contains("certificate") || chain.contains("tls") || chain.contains("ssl") {
+        ConnectionStatus::Tls
+    } else {
+        ConnectionStatus::ConnectionFailed
+    }
+}
+
+fn error_chain(error: &reqwest::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+// `{"type": "error", "error": {"type": "...", "message": "..."}}`, or whatever
+// short text an Anthropic-compatible gateway answers with
+fn error_message(body: &str) -> Option<String> {
+    if let Ok(value) = serde_json::from_str::<Value>(body) {
+        let message = value
+            .pointer("/error/message")
+            .or_else(|| value.get("message"))
+            .or_else(|| value.get("error"))
+            .and_then(Value::as_str);
+        if let Some(message) = message {
+            return Some(message.to_string());
+        }
+    }
+
+    let body = body.trim();
+    (!body.is_empty() && body.len() <= 300).then(|| body.to_string())
+}
+
+// The provider says so with its error type (Anthropic answers `not_found_error`
+// for an unknown model), or names the configured model in its message
+fn is_model_error(body: &str, message: Option<&str>, model: &str) -> bool {
+    let error_type = serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|value| value.pointer("/error/type").and_then(Value::as_str).map(str::to_lowercase));
+    if error_type.is_some_and(|error_type| error_type == "not_found_error" || error_type.contains("model")) {
+        return true;
+    }
+
+    let model = model.trim().to_lowercase();
+    !model.is_empty() && message.is_some_and(|message| message.to_lowercase().contains(&model))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    const MODEL: &str = "glm-4.6";
+
+    fn target(base_url: String) -> ConnectionTarget {
+        ConnectionTarget {
+            base_url,
+            model: MODEL.to_string(),
+            auth: Some(Auth::Bearer("test-token".to_string())),
+        }
+    }
+
+    // Answer one request with `status` and `body`, handing back the request as received
+    fn serve(status: &str, body: &str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let response = format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = read_request(&mut stream);
+            stream.write_all(response.as_bytes()).unwrap();
+            request
+        });
+        (base_url, server)
+    }
+
+    fn read_request(stream: &mut impl Read) -> String {
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        loop {
+            let read = stream.read(&mut buffer).unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let content_length = text[..header_end]
+                    .lines()
+                    .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|n| n.trim().to_string()))
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .unwrap_or(0);
+                if request.len() >= header_end + 4 + content_length {
+                    return text;
+                }
+            }
+            if read == 0 {
+                return text;
+            }
+        }
+    }
+
+    fn check_against(status: &str, body: &str) -> (ConnectionReport, String) {
+        let (base_url, server) = serve(status, body);
+        let report = tauri::async_runtime::block_on(check(&target(base_url), Duration::from_secs(5)));
+        (report, server.join().unwrap())
+    }
+
+    #[test]
+    fn ok_response() {
+        let (report, request) = check_against("200 OK", r#"{"type":"message","content":[]}"#);
+        assert_eq!(report.status, ConnectionStatus::Ok);
+        assert_eq!(report.http_status, Some(200));
+        assert!(report.latency_ms.is_some());
+        assert!(report.message.is_none());
+
+        assert!(request.starts_with("POST /v1/messages "));
+        assert!(request.to_lowercase().contains("authorization: bearer test-token"));
+        assert!(request.contains(&format!(r#""model":"{}""#, MODEL)));
+    }
+
+    #[test]
+    fn rejected_token() {
+        let (report, _) = check_against(
+            "401 Unauthorized",
+            r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#,
+        );
+        assert_eq!(report.status, ConnectionStatus::AuthFailed);
+        assert_eq!(report.message.as_deref(), Some("invalid x-api-key"));
+    }
+
+    #[test]
+    fn wrong_endpoint() {
+        let (report, _) = check_against("404 Not Found", "404 page not found");
+        assert_eq!(report.status, ConnectionStatus::EndpointNotFound);
+        assert_eq!(report.message.as_deref(), Some("404 page not found"));
+    }
+
+    #[test]
+    fn model_not_found() {
+        let (report, _) = check_against(
+            "404 Not Found",
+            r#"{"type":"error","error":{"type":"not_found_error","message":"model: claude-x"}}"#,
+        );
+        assert_eq!(report.status, ConnectionStatus::UnknownModel);
+    }
+
+    #[test]
+    fn unknown_model() {
+        let (report, _) = check_against(
+            "400 Bad Request",
+            r#"{"type":"error","error":{"type":"invalid_request_error","message":"Unknown Model GLM-4.6, please check the model code"}}"#,
+        );
+        assert_eq!(report.status, ConnectionStatus::UnknownModel);
+        assert_eq!(report.http_status, Some(400));
+    }
+
+    #[test]
+    fn other_bad_request() {
+        let (report, _) = check_against(
+            "400 Bad Request",
+            r#"{"type":"error","error":{"type":"invalid_request_error","message":"max_tokens: must be at most 8192 for this model"}}"#,
+        );
+        assert_eq!(report.status, ConnectionStatus::UnexpectedResponse);
+    }
+
+    #[test]
+    fn rate_limited() {
+        let (report, _) = check_against(
+            "429 Too Many Requests",
+            r#"{"type":"error","error":{"type":"rate_limit_error","message":"Number of requests has exceeded your rate limit"}}"#,
+        );
+        assert_eq!(report.status, ConnectionStatus::RateLimited);
+        assert_eq!(report.http_status, Some(429));
+    }
+
+    #[test]
+    fn connection_refused() {
+        // Nothing listens on a port that was just released
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let report = tauri::async_runtime::block_on(check(&target(base_url), Duration::from_secs(5)));
+        assert_eq!(report.status, ConnectionStatus::ConnectionFailed);
+        assert!(report.http_status.is_none());
+        assert!(report.latency_ms.is_none());
+    }
+
+    #[test]
+    fn missing_credentials() {
+        let mut target = target("http://127.0.0.1:9".to_string());
+        target.auth = None;
+        let report = tauri::async_runtime::block_on(check(&target, Duration::from_secs(5)));
+        assert_eq!(report.status, ConnectionStatus::MissingCredentials);
+    }
+}
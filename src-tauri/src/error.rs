@@ -0,0 +1,128 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use crate::settings_schema::Diagnostic;
+
+/// What went wrong, so the UI can offer a matching action (retry, fix JSON, ...).
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    NotFound,
+    PermissionDenied,
+    Io,
+    Parse,
+    Validation,
+    Conflict,
+    Network,
+    // The secret vault has to be unlocked with its passphrase first
+    Locked,
+    Other,
+}
+
+/// Error returned by every Tauri command.
+///
+/// `message` is always a complete, human readable sentence; the other fields
+/// are there for the frontend to react to specific failures.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct AppError {
+    pub kind: ErrorKind,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    // 1-based position of a parse error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<Vec<Diagnostic>>,
+}
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> AppError {
+        AppError {
+            kind,
+            message: message.into(),
+            path: None,
+            line: None,
+            column: None,
+            diagnostics: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> AppError {
+        AppError::new(ErrorKind::NotFound, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> AppError {
+        AppError::new(ErrorKind::Conflict, message)
+    }
+
+    pub fn network(message: impl Into<String>) -> AppError {
+        AppError::new(ErrorKind::Network, message)
+    }
+
+    /// A failed filesystem operation on `path`.
+    pub fn io(context: &str, path: impl AsRef<Path>, error: io::Error) -> AppError {
+        let kind = match error.kind() {
+            io::ErrorKind::NotFound => ErrorKind::NotFound,
+            io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+            _ => ErrorKind::Io,
+        };
+
+        AppError::new(kind, format!("{}: {}", context, error)).with_path(path)
+    }
+
+    /// A JSON document at `path` that couldn't be parsed.
+    pub fn parse(context: &str, path: impl AsRef<Path>, error: serde_json::Error) -> AppError {
+        let mut app_error =
+            AppError::new(ErrorKind::Parse, format!("{}: {}", context, error)).with_path(path);
+
+        // serde_json reports line 0 for errors that aren't tied to a position
+        if error.line() > 0 {
+            app_error.line = Some(error.line());
+            app_error.column = Some(error.column());
+        }
+        app_error
+    }
+
+    /// Settings rejected by the schema, carrying all diagnostics found.
+    pub fn validation(message: impl Into<String>, diagnostics: Vec<Diagnostic>) -> AppError {
+        let mut app_error = AppError::new(ErrorKind::Validation, message);
+        app_error.diagnostics = Some(diagnostics);
+        app_error
+    }
+
+    /// Prefix the message with what was being done, keeping everything else.
+    pub fn context(mut self, context: &str) -> AppError {
+        self.message = format!("{}: {}", context, self.message);
+        self
+    }
+
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> AppError {
+        self.path = Some(path.as_ref().display().to_string());
+        self
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+// Helpers still report plain messages
+impl From<String> for AppError {
+    fn from(message: String) -> AppError {
+        AppError::new(ErrorKind::Other, message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> AppError {
+        AppError::new(ErrorKind::Other, message)
+    }
+}
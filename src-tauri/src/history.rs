@@ -0,0 +1,139 @@
+use serde_json::Value;
+use std::path::PathBuf;
+
+use crate::commands::ConfigStore;
+use crate::error::{AppError, ErrorKind};
+use crate::merge::ArrayStrategy;
+use crate::paths;
+use crate::persistence::write_atomic;
+
+const HISTORY_DIR: &str = "history";
+
+// Oldest revisions are dropped beyond this
+const MAX_REVISIONS: usize = 50;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RevisionTrigger {
+    Created,
+    Updated,
+    Restored,
+    Deleted,
+}
+
+/// Snapshot of a store as it was after a change.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct Revision {
+    pub id: String,
+    pub timestamp: u64,
+    pub title: String,
+    pub settings: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<Vec<String>>,
+    /// None in revisions recorded before the strategy was kept
+    #[serde(rename = "arrayStrategy", default, skip_serializing_if = "Option::is_none")]
+    pub array_strategy: Option<ArrayStrategy>,
+    pub trigger: RevisionTrigger,
+}
+
+fn history_file(store_id: &str) -> Result<PathBuf, AppError> {
+    if store_id.is_empty() || store_id.contains(['/', '\\', '.']) {
+        return Err(AppError::new(ErrorKind::Validation, format!("Invalid store id '{}'", store_id)));
+    }
+
+    Ok(paths::app_config_dir()?
+        .join(HISTORY_DIR)
+        .join(format!("{}.json", store_id)))
+}
+
+/// Revisions of a store, oldest first.
+pub fn load(store_id: &str) -> Result<Vec<Revision>, AppError> {
+    let path = history_file(store_id)?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::io("Failed to read history file", &path, e))?;
+    serde_json::from_str(&content).map_err(|e| AppError::parse("Failed to parse history file", &path, e))
+}
+
+/// Append the current state of `store` to its history.
+pub fn record(store: &ConfigStore, trigger: RevisionTrigger) -> Result<(), AppError> {
+    let mut revisions = load(&store.id)?;
+    let array_strategy = Some(store.array_strategy.unwrap_or_default());
+
+    // Saving without changes shouldn't push useful revisions out
+    if let Some(latest) = revisions.last() {
+        if trigger == RevisionTrigger::Updated
+            && latest.title == store.title
+            && latest.settings == store.settings
+            && latest.extends == store.extends
+            && latest.array_strategy == array_strategy
+        {
+            return Ok(());
+        }
+    }
+
+    revisions.push(Revision {
+        id: nanoid::nanoid!(8),
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| format!("Failed to get timestamp: {}", e))?
+            .as_secs(),
+        title: store.title.clone(),
+        settings: store.settings.clone(),
+        extends: store.extends.clone(),
+        array_strategy,
+        trigger,
+    });
+
+    if revisions.len() > MAX_REVISIONS {
+        let excess = revisions.len() - MAX_REVISIONS;
+        revisions.drain(..excess);
+    }
+
+    let json_content = serde_json::to_string_pretty(&revisions)
+        .map_err(|e| format!("Failed to serialize history: {}", e))?;
+    let path = history_file(&store.id)?;
+    write_atomic(&path, json_content).map_err(|e| AppError::io("Failed to write history file", &path, e))
+}
+
+pub fn find(store_id: &str, revision_id: &str) -> Result<Revision, AppError> {
+    load(store_id)?
+        .into_iter()
+        .find(|revision| revision.id == revision_id)
+        .ok_or_else(|| AppError::not_found(format!("Revision '{}' not found", revision_id)))
+}
+
+/// Ids of the stores that have a history, deleted ones included.
+pub fn store_ids() -> Result<Vec<String>, AppError> {
+    let dir = paths::app_config_dir()?.join(HISTORY_DIR);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(AppError::io("Failed to read history directory", &dir, e)),
+    };
+
+    let mut ids = vec![];
+    for entry in entries {
+        let path = entry.map_err(|e| AppError::io("Failed to read history directory", &dir, e))?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                ids.push(id.to_string());
+            }
+        }
+    }
+    ids.sort();
+    Ok(ids)
+}
+
+/// Forget a store's history.
+pub fn remove(store_id: &str) -> Result<(), AppError> {
+    let path = history_file(store_id)?;
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(AppError::io("Failed to delete history file", &path, e)),
+    }
+}
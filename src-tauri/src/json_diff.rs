@@ -0,0 +1,69 @@
+use serde_json::Value;
+
+use crate::merge::escape_pointer_token;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// One difference between two JSON documents, addressed by a JSON pointer.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    pub path: String,
+    pub kind: ChangeKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+/// Structural diff of two JSON values. Objects are compared key by key,
+/// arrays and scalars as a whole.
+pub fn diff(old: &Value, new: &Value) -> Vec<DiffEntry> {
+    let mut entries = Vec::new();
+    diff_at("", old, new, &mut entries);
+    entries
+}
+
+fn diff_at(path: &str, old: &Value, new: &Value, entries: &mut Vec<DiffEntry>) {
+    match (old, new) {
+        (Value::Object(old_obj), Value::Object(new_obj)) => {
+            for (key, old_value) in old_obj {
+                let child_path = format!("{}/{}", path, escape_pointer_token(key));
+                match new_obj.get(key) {
+                    Some(new_value) => diff_at(&child_path, old_value, new_value, entries),
+                    None => entries.push(DiffEntry {
+                        path: child_path,
+                        kind: ChangeKind::Removed,
+                        old: Some(old_value.clone()),
+                        new: None,
+                    }),
+                }
+            }
+
+            for (key, new_value) in new_obj {
+                if !old_obj.contains_key(key) {
+                    entries.push(DiffEntry {
+                        path: format!("{}/{}", path, escape_pointer_token(key)),
+                        kind: ChangeKind::Added,
+                        old: None,
+                        new: Some(new_value.clone()),
+                    });
+                }
+            }
+
+            entries.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        _ if old != new => entries.push(DiffEntry {
+            path: path.to_string(),
+            kind: ChangeKind::Modified,
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+        }),
+        _ => {}
+    }
+}
@@ -1,6 +1,25 @@
-mod commands;
+mod backups;
+pub mod bundle;
+pub mod commands;
+pub mod connection;
+pub mod error;
 mod tray;
+mod history;
 mod hook_server;
+pub mod json_diff;
+mod merge;
+pub mod paths;
+mod persistence;
+pub mod pricing;
+mod profiles;
+pub mod providers;
+mod secrets;
+mod settings_schema;
+mod sync;
+mod undo;
+pub mod usage_index;
+pub mod usage_query;
+mod watcher;
 
 use commands::*;
 use hook_server::start_hook_server;
@@ -103,6 +122,23 @@ pub fn run() {
                 eprintln!("Failed to create system tray: {}", e);
             }
 
+            // Keep the UI and tray in sync with edits made outside the app
+            if let Err(e) = watcher::start(app.handle()) {
+                eprintln!("Failed to start file watcher: {}", e);
+            }
+
+            // The tray shows usage from the index, bring it up to date once
+            // without holding up startup
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = commands::read_project_usage_files().await {
+                    eprintln!("Failed to update usage index: {}", e);
+                }
+                if let Err(e) = tray::rebuild_tray_menu(app_handle).await {
+                    eprintln!("Failed to rebuild tray menu: {}", e);
+                }
+            });
+
             // Handle menu events (both app menu and tray menu)
             app.on_menu_event(|app_handle, event| {
                 use tauri::Manager;
@@ -167,18 +203,54 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             read_config_file,
             write_config_file,
+            validate_settings,
+            check_profile_drift,
+            reapply_profile,
+            adopt_profile_drift,
             list_config_files,
             check_app_config_exists,
             create_app_config_dir,
             backup_claude_configs,
+            list_backups,
+            verify_backup,
+            restore_backup,
+            delete_backup,
             get_stores,
             get_store,
             create_config,
+            list_provider_presets,
+            create_config_from_preset,
+            test_config_connection,
             update_config,
+            list_config_revisions,
+            diff_config_revisions,
+            restore_config_revision,
+            purge_deleted_configs,
+            diff_configs,
+            export_profiles,
+            preview_profile_bundle,
+            import_profiles,
             delete_config,
             set_using_config,
             reset_to_original_config,
             get_current_store,
+            get_project_store,
+            set_config_project,
+            get_vault_status,
+            list_secrets,
+            set_secret,
+            delete_secret,
+            unlock_vault,
+            set_vault_passphrase,
+            set_vault_backend,
+            list_claude_homes,
+            add_claude_home,
+            remove_claude_home,
+            set_active_claude_home,
+            get_sync_status,
+            set_sync_source,
+            run_sync,
+            resolve_sync_conflict,
             open_config_path,
             get_global_mcp_servers,
             update_global_mcp_server,
@@ -192,6 +264,10 @@ pub fn run() {
             rebuild_tray_menu_command,
             unlock_cc_ext,
             read_project_usage_files,
+            get_usage_costs,
+            query_usage,
+            get_model_prices,
+            set_model_prices,
             read_claude_memory,
             write_claude_memory,
             track,
@@ -205,7 +281,9 @@ pub fn run() {
             delete_claude_command,
             read_claude_agents,
             write_claude_agent,
-            delete_claude_agent
+            delete_claude_agent,
+            get_last_undoable_change,
+            undo_last_change
         ])
         .on_window_event(|window, event| {
             #[cfg(target_os = "macos")]
@@ -0,0 +1,426 @@
+use serde_json::{Map, Value};
+
+/// How arrays are combined when a profile is merged into existing settings.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ArrayStrategy {
+    /// The profile's array replaces the existing one
+    #[default]
+    Replace,
+    /// The profile's items are appended to the existing array
+    Append,
+    /// Like append, but items already present are not added again
+    Union,
+}
+
+/// A value a profile wrote into a settings file, and what was there before.
+///
+/// `path` is a JSON pointer. Keeping `value` lets us tell whether the user
+/// changed the key after the profile was applied, in which case it is left
+/// alone when switching away.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct OwnedKey {
+    pub path: String,
+    pub value: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<Value>,
+}
+
+/// Recursively merge `overlay` into `base`. Objects are merged key by key,
+/// arrays are combined according to `arrays`, everything else is replaced.
+pub fn deep_merge(base: &mut Value, overlay: &Value, arrays: ArrayStrategy) {
+    match (base, overlay) {
+        (Value::Object(base_obj), Value::Object(overlay_obj)) => {
+            for (key, overlay_value) in overlay_obj {
+                match base_obj.get_mut(key) {
+                    Some(base_value) => deep_merge(base_value, overlay_value, arrays),
+                    None => {
+                        base_obj.insert(key.clone(), overlay_value.clone());
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = merge_arrays(base, overlay, arrays),
+    }
+}
+
+fn merge_arrays(existing: &Value, incoming: &Value, arrays: ArrayStrategy) -> Value {
+    match (existing, incoming, arrays) {
+        (Value::Array(existing_items), Value::Array(incoming_items), ArrayStrategy::Append) => {
+            let mut items = existing_items.clone();
+            items.extend(incoming_items.iter().cloned());
+            Value::Array(items)
+        }
+        (Value::Array(existing_items), Value::Array(incoming_items), ArrayStrategy::Union) => {
+            let mut items = existing_items.clone();
+            for item in incoming_items {
+                if !items.contains(item) {
+                    items.push(item.clone());
+                }
+            }
+            Value::Array(items)
+        }
+        _ => incoming.clone(),
+    }
+}
+
+/// Merge a profile's settings into `target` and return the keys it now owns.
+///
+/// Keys missing from `target` are owned at the highest level the profile
+/// introduced them (e.g. the whole `env` object when there was none), existing
+/// objects are descended into, and existing leaves are owned together with
+/// their previous value so they can be restored later.
+pub fn apply_profile(target: &mut Value, profile: &Value, arrays: ArrayStrategy) -> Vec<OwnedKey> {
+    let mut owned = Vec::new();
+
+    match (target.as_object_mut(), profile.as_object()) {
+        (Some(target_obj), Some(profile_obj)) => {
+            apply_object(target_obj, profile_obj, arrays, "", &mut owned);
+        }
+        _ => {
+            // Not objects, so the profile takes over the whole document
+            owned.push(OwnedKey {
+                path: String::new(),
+                value: profile.clone(),
+                previous: Some(target.clone()),
+            });
+            *target = profile.clone();
+        }
+    }
+
+    owned
+}
+
+fn apply_object(
+    target: &mut Map<String, Value>,
+    profile: &Map<String, Value>,
+    arrays: ArrayStrategy,
+    prefix: &str,
+    owned: &mut Vec<OwnedKey>,
+) {
+    for (key, profile_value) in profile {
+        let path = format!("{}/{}", prefix, escape_pointer_token(key));
+
+        match target.get_mut(key) {
+            None => {
+                target.insert(key.clone(), profile_value.clone());
+                owned.push(OwnedKey {
+                    path,
+                    value: profile_value.clone(),
+                    previous: None,
+                });
+            }
+            Some(Value::Object(target_child)) if profile_value.is_object() => {
+                let profile_child = profile_value.as_object().unwrap();
+                apply_object(target_child, profile_child, arrays, &path, owned);
+            }
+            Some(existing) => {
+                let value = merge_arrays(existing, profile_value, arrays);
+                owned.push(OwnedKey {
+                    path,
+                    value: value.clone(),
+                    previous: Some(existing.clone()),
+                });
+                *existing = value;
+            }
+        }
+    }
+}
+
+/// Undo what a profile applied: restore or remove every key it owns,
+/// unless the user has changed that key since.
+pub fn revert_profile(target: &mut Value, owned: &[OwnedKey]) {
+    for key in owned.iter().rev() {
+        if key.path.is_empty() {
+            if *target == key.value {
+                *target = key
+                    .previous
+                    .clone()
+                    .unwrap_or_else(|| Value::Object(Map::new()));
+            }
+            continue;
+        }
+
+        let tokens = parse_pointer(&key.path);
+        let Some((last, parents)) = tokens.split_last() else {
+            continue;
+        };
+        let Some(parent) = pointer_object_mut(target, parents) else {
+            continue;
+        };
+
+        match (&key.previous, parent.get_mut(last)) {
+            (Some(previous), Some(current)) if *current == key.value => {
+                *current = previous.clone();
+            }
+            (None, Some(current)) if *current == key.value => {
+                parent.remove(last);
+            }
+            (None, Some(Value::Object(current_obj))) => {
+                // The user added keys next to ours, only take back what we introduced
+                if let Some(value_obj) = key.value.as_object() {
+                    remove_matching(current_obj, value_obj);
+                    if current_obj.is_empty() {
+                        parent.remove(last);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn remove_matching(current: &mut Map<String, Value>, introduced: &Map<String, Value>) {
+    for (key, introduced_value) in introduced {
+        let Some(current_value) = current.get_mut(key) else {
+            continue;
+        };
+
+        if current_value == introduced_value {
+            current.remove(key);
+        } else if let (Some(current_obj), Some(introduced_obj)) =
+            (current_value.as_object_mut(), introduced_value.as_object())
+        {
+            remove_matching(current_obj, introduced_obj);
+            if current_obj.is_empty() {
+                current.remove(key);
+            }
+        }
+    }
+}
+
+/// Ownership for a profile that was applied before ownership was recorded:
+/// assume it introduced every top-level key it holds.
+pub fn legacy_ownership(profile: &Value) -> Vec<OwnedKey> {
+    profile
+        .as_object()
+        .map(|obj| {
+            obj.iter()
+                .map(|(key, value)| OwnedKey {
+                    path: format!("/{}", escape_pointer_token(key)),
+                    value: value.clone(),
+                    previous: None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Escape a key for use as a JSON pointer token (RFC 6901)
+pub fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Split a JSON pointer into unescaped tokens
+pub fn parse_pointer(pointer: &str) -> Vec<String> {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+/// Set (or remove, with None) the value at `tokens`, creating missing objects on the way
+pub fn set_pointer(root: &mut Value, tokens: &[String], value: Option<Value>) {
+    let Some((last, parents)) = tokens.split_last() else {
+        return;
+    };
+
+    let mut current = root;
+    for token in parents {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        let Value::Object(object) = current else {
+            return;
+        };
+        current = object
+            .entry(token.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+
+    if !current.is_object() {
+        *current = Value::Object(Map::new());
+    }
+    let Value::Object(object) = current else {
+        return;
+    };
+    match value {
+        Some(value) => {
+            object.insert(last.clone(), value);
+        }
+        None => {
+            object.remove(last);
+        }
+    }
+}
+
+fn pointer_object_mut<'a>(target: &'a mut Value, tokens: &[String]) -> Option<&'a mut Map<String, Value>> {
+    let mut current = target;
+    for token in tokens {
+        current = current.as_object_mut()?.get_mut(token)?;
+    }
+    current.as_object_mut()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn settings() -> Value {
+        json!({
+            "model": "opus",
+            "env": { "DISABLE_TELEMETRY": "1" },
+            "permissions": { "allow": ["Bash(ls)"] }
+        })
+    }
+
+    fn profile() -> Value {
+        json!({
+            "model": "sonnet",
+            "env": { "ANTHROPIC_BASE_URL": "https://example.com" },
+            "permissions": { "allow": ["Read"] },
+            "statusLine": { "type": "command", "command": "status" }
+        })
+    }
+
+    fn paths(owned: &[OwnedKey]) -> Vec<&str> {
+        owned.iter().map(|key| key.path.as_str()).collect()
+    }
+
+    #[test]
+    fn apply_then_revert_restores_the_settings() {
+        for arrays in [ArrayStrategy::Replace, ArrayStrategy::Append, ArrayStrategy::Union] {
+            let mut target = settings();
+            let owned = apply_profile(&mut target, &profile(), arrays);
+            revert_profile(&mut target, &owned);
+            assert_eq!(target, settings(), "{:?}", arrays);
+        }
+    }
+
+    #[test]
+    fn owns_new_keys_at_the_highest_level_and_descends_into_objects() {
+        let mut target = settings();
+        let owned = apply_profile(&mut target, &profile(), ArrayStrategy::Replace);
+
+        assert_eq!(
+            paths(&owned),
+            vec!["/env/ANTHROPIC_BASE_URL", "/model", "/permissions/allow", "/statusLine"]
+        );
+        assert_eq!(owned[1].previous, Some(json!("opus")));
+        assert_eq!(owned[3].previous, None);
+        assert_eq!(target["env"], json!({ "DISABLE_TELEMETRY": "1", "ANTHROPIC_BASE_URL": "https://example.com" }));
+    }
+
+    #[test]
+    fn array_strategies() {
+        let existing = json!(["a", "b"]);
+        let incoming = json!(["b", "c"]);
+        assert_eq!(merge_arrays(&existing, &incoming, ArrayStrategy::Replace), json!(["b", "c"]));
+        assert_eq!(merge_arrays(&existing, &incoming, ArrayStrategy::Append), json!(["a", "b", "b", "c"]));
+        assert_eq!(merge_arrays(&existing, &incoming, ArrayStrategy::Union), json!(["a", "b", "c"]));
+
+        // Anything that isn't two arrays is replaced
+        for arrays in [ArrayStrategy::Replace, ArrayStrategy::Append, ArrayStrategy::Union] {
+            assert_eq!(merge_arrays(&json!("a"), &incoming, arrays), incoming);
+            assert_eq!(merge_arrays(&existing, &json!(1), arrays), json!(1));
+        }
+    }
+
+    #[test]
+    fn owned_arrays_hold_the_merged_value() {
+        let mut target = settings();
+        let owned = apply_profile(&mut target, &profile(), ArrayStrategy::Union);
+        let allow = owned.iter().find(|key| key.path == "/permissions/allow").unwrap();
+        assert_eq!(allow.value, json!(["Bash(ls)", "Read"]));
+        assert_eq!(allow.previous, Some(json!(["Bash(ls)"])));
+        assert_eq!(target["permissions"]["allow"], allow.value);
+    }
+
+    #[test]
+    fn revert_leaves_keys_the_user_changed() {
+        let mut target = settings();
+        let owned = apply_profile(&mut target, &profile(), ArrayStrategy::Append);
+        target["model"] = json!("haiku");
+        target["permissions"]["allow"] = json!(["Write"]);
+        target["statusLine"]["command"] = json!("mine");
+
+        revert_profile(&mut target, &owned);
+        assert_eq!(target["model"], json!("haiku"));
+        assert_eq!(target["permissions"]["allow"], json!(["Write"]));
+        // Only what the profile introduced is taken back from an object it created
+        assert_eq!(target["statusLine"], json!({ "command": "mine" }));
+        assert_eq!(target["env"], json!({ "DISABLE_TELEMETRY": "1" }));
+    }
+
+    #[test]
+    fn revert_keeps_keys_the_user_added_next_to_ours() {
+        let mut target = json!({});
+        let owned = apply_profile(&mut target, &profile(), ArrayStrategy::Replace);
+        target["env"]["MY_VAR"] = json!("x");
+
+        revert_profile(&mut target, &owned);
+        assert_eq!(target, json!({ "env": { "MY_VAR": "x" } }));
+    }
+
+    #[test]
+    fn revert_skips_keys_that_are_gone() {
+        let mut target = settings();
+        let owned = apply_profile(&mut target, &profile(), ArrayStrategy::Replace);
+        target.as_object_mut().unwrap().remove("env");
+
+        revert_profile(&mut target, &owned);
+        assert_eq!(target.get("env"), None);
+        assert_eq!(target["model"], json!("opus"));
+    }
+
+    #[test]
+    fn a_profile_that_isnt_an_object_takes_over_the_document() {
+        let mut target = settings();
+        let owned = apply_profile(&mut target, &json!(["x"]), ArrayStrategy::Append);
+        assert_eq!(paths(&owned), vec![""]);
+        assert_eq!(target, json!(["x"]));
+
+        revert_profile(&mut target, &owned);
+        assert_eq!(target, settings());
+    }
+
+    #[test]
+    fn legacy_ownership_owns_every_top_level_key() {
+        let owned = legacy_ownership(&json!({ "model": "sonnet", "a/b": { "c": 1 } }));
+        assert_eq!(
+            owned,
+            vec![
+                OwnedKey {
+                    path: "/a~1b".to_string(),
+                    value: json!({ "c": 1 }),
+                    previous: None,
+                },
+                OwnedKey {
+                    path: "/model".to_string(),
+                    value: json!("sonnet"),
+                    previous: None,
+                },
+            ]
+        );
+        assert!(legacy_ownership(&json!("x")).is_empty());
+
+        // Reverting removes the keys even though nothing recorded them
+        let mut target = json!({ "model": "sonnet", "a/b": { "c": 1 }, "theme": "dark" });
+        revert_profile(&mut target, &owned);
+        assert_eq!(target, json!({ "theme": "dark" }));
+    }
+
+    #[test]
+    fn pointer_tokens_round_trip() {
+        let tokens = parse_pointer(&format!("/{}/{}", escape_pointer_token("a/b"), escape_pointer_token("~c")));
+        assert_eq!(tokens, vec!["a/b", "~c"]);
+
+        let mut root = json!({ "x": 1 });
+        set_pointer(&mut root, &tokens, Some(json!(true)));
+        assert_eq!(root, json!({ "x": 1, "a/b": { "~c": true } }));
+        set_pointer(&mut root, &tokens, None);
+        assert_eq!(root, json!({ "x": 1, "a/b": {} }));
+    }
+}
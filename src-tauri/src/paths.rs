@@ -0,0 +1,179 @@
+use std::path::{Path, PathBuf};
+
+use crate::persistence::write_atomic;
+
+// Directory under the user's home where CC Mate keeps its own data
+const APP_CONFIG_DIR: &str = ".ccconfig";
+
+// Same variable Claude Code reads to relocate its config directory
+const CLAUDE_CONFIG_DIR_ENV: &str = "CLAUDE_CONFIG_DIR";
+
+// Moves CC Mate's own data directory, mostly useful for testing against a temp dir
+const APP_CONFIG_DIR_ENV: &str = "CCMATE_CONFIG_DIR";
+
+const HOMES_FILE: &str = "homes.json";
+
+/// A named Claude config directory, e.g. separate homes for work and personal use.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ClaudeHome {
+    pub name: String,
+    pub path: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct ClaudeHomes {
+    #[serde(default)]
+    pub homes: Vec<ClaudeHome>,
+    // None means the default location (CLAUDE_CONFIG_DIR or ~/.claude)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active: Option<String>,
+}
+
+pub fn home_dir() -> Result<PathBuf, String> {
+    dirs::home_dir().ok_or_else(|| "Could not find home directory".to_string())
+}
+
+/// Where CC Mate keeps its own data (`stores.json`, history, vault, ...).
+pub fn app_config_dir() -> Result<PathBuf, String> {
+    match env_dir(APP_CONFIG_DIR_ENV) {
+        Some(dir) => Ok(dir),
+        None => Ok(home_dir()?.join(APP_CONFIG_DIR)),
+    }
+}
+
+pub fn stores_file() -> Result<PathBuf, String> {
+    Ok(app_config_dir()?.join("stores.json"))
+}
+
+/// The Claude config directory in use: the active named home, else
+/// `CLAUDE_CONFIG_DIR`, else `~/.claude`.
+pub fn claude_dir() -> Result<PathBuf, String> {
+    if let Some(home) = active_home()? {
+        return expand_home(&home.path);
+    }
+
+    match env_dir(CLAUDE_CONFIG_DIR_ENV) {
+        Some(dir) => Ok(dir),
+        None => Ok(home_dir()?.join(".claude")),
+    }
+}
+
+/// `.claude.json` sits next to `.claude/` by default, but inside the config
+/// directory once it has been relocated.
+pub fn claude_json_path() -> Result<PathBuf, String> {
+    if active_home()?.is_some() || env_dir(CLAUDE_CONFIG_DIR_ENV).is_some() {
+        return Ok(claude_dir()?.join(".claude.json"));
+    }
+
+    Ok(home_dir()?.join(".claude.json"))
+}
+
+pub fn user_settings_path() -> Result<PathBuf, String> {
+    Ok(claude_dir()?.join("settings.json"))
+}
+
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+// Accept `~/...` in paths typed into the UI
+fn expand_home(path: &str) -> Result<PathBuf, String> {
+    match path.strip_prefix("~/") {
+        Some(rest) => Ok(home_dir()?.join(rest)),
+        None if path == "~" => home_dir(),
+        None => Ok(PathBuf::from(path)),
+    }
+}
+
+fn homes_file() -> Result<PathBuf, String> {
+    Ok(app_config_dir()?.join(HOMES_FILE))
+}
+
+pub fn load_homes() -> Result<ClaudeHomes, String> {
+    let path = homes_file()?;
+    if !path.exists() {
+        return Ok(ClaudeHomes::default());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read homes file: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse homes file: {}", e))
+}
+
+fn save_homes(homes: &ClaudeHomes) -> Result<(), String> {
+    let json_content = serde_json::to_string_pretty(homes)
+        .map_err(|e| format!("Failed to serialize homes: {}", e))?;
+    write_atomic(homes_file()?, json_content).map_err(|e| format!("Failed to write homes file: {}", e))
+}
+
+fn active_home() -> Result<Option<ClaudeHome>, String> {
+    let homes = load_homes()?;
+    Ok(homes
+        .active
+        .as_ref()
+        .and_then(|name| homes.homes.iter().find(|home| &home.name == name))
+        .cloned())
+}
+
+pub fn add_home(name: &str, path: &str) -> Result<ClaudeHomes, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Home name cannot be empty".to_string());
+    }
+
+    let dir = expand_home(path.trim())?;
+    if !dir.is_absolute() {
+        return Err(format!("'{}' is not an absolute path", path));
+    }
+    if !dir.is_dir() {
+        return Err(format!("Directory '{}' does not exist", dir.display()));
+    }
+
+    let mut homes = load_homes()?;
+    if homes.homes.iter().any(|home| home.name == name) {
+        return Err(format!("A Claude home named '{}' already exists", name));
+    }
+
+    homes.homes.push(ClaudeHome {
+        name: name.to_string(),
+        path: path.trim().to_string(),
+    });
+    save_homes(&homes)?;
+    Ok(homes)
+}
+
+pub fn remove_home(name: &str) -> Result<ClaudeHomes, String> {
+    let mut homes = load_homes()?;
+    let count = homes.homes.len();
+    homes.homes.retain(|home| home.name != name);
+    if homes.homes.len() == count {
+        return Err(format!("Claude home '{}' not found", name));
+    }
+
+    if homes.active.as_deref() == Some(name) {
+        homes.active = None;
+    }
+    save_homes(&homes)?;
+    Ok(homes)
+}
+
+/// Switch to a named home, or back to the default location with `None`.
+pub fn set_active_home(name: Option<&str>) -> Result<ClaudeHomes, String> {
+    let mut homes = load_homes()?;
+    if let Some(name) = name {
+        if !homes.homes.iter().any(|home| home.name == name) {
+            return Err(format!("Claude home '{}' not found", name));
+        }
+    }
+
+    homes.active = name.map(str::to_string);
+    save_homes(&homes)?;
+    Ok(homes)
+}
+
+/// Resolve a path relative to the Claude config directory.
+pub fn claude_path<P: AsRef<Path>>(relative: P) -> Result<PathBuf, String> {
+    Ok(claude_dir()?.join(relative))
+}
@@ -0,0 +1,280 @@
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::error::AppError;
+
+// Suffix of the copy that keeps the previous version of a file around
+const BACKUP_SUFFIX: &str = "bak";
+
+// Suffix of the sidecar file used for advisory locking
+const LOCK_SUFFIX: &str = "lock";
+
+// How many times a read-modify-write is re-applied when the file keeps changing under us
+const MAX_UPDATE_ATTEMPTS: usize = 5;
+
+/// Crash-safe replacement for `std::fs::write`.
+///
+/// The contents are written to a temporary file next to `path`, flushed to disk
+/// and then renamed over the target, so a reader (or a crash) never observes a
+/// half-written file. The previous version is kept as `<file>.bak`.
+pub fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    write_file(path.as_ref(), contents.as_ref(), false)
+}
+
+/// `write_atomic` for files only the current user may read (key material,
+/// secrets). The file and its `.bak` are readable by nobody else at any point,
+/// whatever the permissions of the file being replaced.
+pub fn write_private<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    write_file(path.as_ref(), contents.as_ref(), true)
+}
+
+fn write_file(path: &Path, contents: &[u8], private: bool) -> io::Result<()> {
+    let target = resolve_target(path)?;
+    let parent = target
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let file_name = target
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+        .to_string_lossy()
+        .to_string();
+
+    fs::create_dir_all(&parent)?;
+
+    let temp_path = parent.join(format!(".{}.{}.tmp", file_name, nanoid::nanoid!(8)));
+    if let Err(e) = write_temp_file(&temp_path, &target, contents, private) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    // Keep the previous version before it gets replaced
+    if target.is_file() {
+        let backup = backup_path(&target);
+        let copied = fs::copy(&target, &backup).and_then(|_| if private { restrict(&backup) } else { Ok(()) });
+        if let Err(e) = copied {
+            eprintln!("Warning: Failed to keep previous version of {}: {}", target.display(), e);
+        }
+    }
+
+    if let Err(e) = fs::rename(&temp_path, &target) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    sync_dir(&parent);
+    Ok(())
+}
+
+/// Files in `dir` (not recursive) with the given extension, sorted by path.
+pub fn files_with_extension(dir: &Path, extension: &str) -> Result<Vec<PathBuf>, AppError> {
+    let entries = fs::read_dir(dir).map_err(|e| AppError::io("Failed to read directory", dir, e))?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| AppError::io("Failed to read directory entry", dir, e))?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == extension) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Path of the copy holding the version of `path` before the last write.
+pub fn backup_path(path: &Path) -> PathBuf {
+    sidecar_path(path, BACKUP_SUFFIX)
+}
+
+fn sidecar_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+// Write through symlinks (e.g. dotfile managers) instead of replacing the link itself
+fn resolve_target(path: &Path) -> io::Result<PathBuf> {
+    if path.is_symlink() {
+        fs::canonicalize(path)
+    } else {
+        Ok(path.to_path_buf())
+    }
+}
+
+fn write_temp_file(temp_path: &Path, target: &Path, contents: &[u8], private: bool) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(temp_path)?;
+    file.write_all(contents)?;
+
+    // Preserve the permissions of the file being replaced (e.g. 0600 on .claude.json)
+    if !private {
+        if let Ok(metadata) = fs::metadata(target) {
+            file.set_permissions(metadata.permissions())?;
+        }
+    }
+
+    file.sync_all()
+}
+
+// Only the current user can read and write the file
+fn restrict(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Ok(())
+    }
+}
+
+// Persist the rename itself; directories can't be opened for syncing on Windows
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
+/// Exclusive advisory lock on `<file>.lock`, released when dropped.
+///
+/// The lock lives in a sidecar file because `write_atomic` replaces the target
+/// file itself, which would silently drop a lock held on the old inode.
+pub struct FileLock {
+    file: File,
+}
+
+impl FileLock {
+    pub fn acquire(path: &Path) -> io::Result<FileLock> {
+        let lock_path = sidecar_path(path, LOCK_SUFFIX);
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+        file.lock()?;
+
+        Ok(FileLock { file })
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+// What we know about a file at the moment we read it
+#[derive(PartialEq, Eq)]
+struct Fingerprint {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl Fingerprint {
+    fn of(path: &Path, contents: Option<&[u8]>) -> Fingerprint {
+        let metadata = fs::metadata(path).ok();
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        contents.hash(&mut hasher);
+
+        Fingerprint {
+            modified: metadata.as_ref().and_then(|m| m.modified().ok()),
+            len: metadata.map(|m| m.len()).unwrap_or(0),
+            hash: hasher.finish(),
+        }
+    }
+}
+
+fn read_optional(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Read-modify-write a JSON file under its advisory lock.
+///
+/// Other processes (most notably Claude Code itself) don't take our lock, so
+/// before writing we check whether the file changed since it was read. If it
+/// did, the fresh contents are read again and `patch` is re-applied to them,
+/// which means `patch` must only touch the parts of the document it owns.
+/// A missing file is handed to `patch` as an empty object.
+pub fn update_json_file<T, F>(path: &Path, mut patch: F) -> Result<T, AppError>
+where
+    F: FnMut(&mut Value) -> Result<T, AppError>,
+{
+    let _lock = FileLock::acquire(path)
+        .map_err(|e| AppError::io(&format!("Failed to lock {}", path.display()), path, e))?;
+
+    for attempt in 1..=MAX_UPDATE_ATTEMPTS {
+        let original = read_optional(path)
+            .map_err(|e| AppError::io(&format!("Failed to read {}", path.display()), path, e))?;
+        let fingerprint = Fingerprint::of(path, original.as_deref());
+
+        let mut value = match &original {
+            Some(bytes) => match serde_json::from_slice(bytes) {
+                Ok(value) => value,
+                // Probably caught another writer mid-write, give it a moment
+                Err(_) if attempt < MAX_UPDATE_ATTEMPTS => {
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                    continue;
+                }
+                Err(e) => return Err(AppError::parse(&format!("Failed to parse {}", path.display()), path, e)),
+            },
+            None => Value::Object(serde_json::Map::new()),
+        };
+
+        let result = patch(&mut value)?;
+
+        let json_content = serde_json::to_string_pretty(&value)
+            .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+
+        let current = read_optional(path)
+            .map_err(|e| AppError::io(&format!("Failed to read {}", path.display()), path, e))?;
+        if Fingerprint::of(path, current.as_deref()) != fingerprint {
+            println!(
+                "{} changed while updating it, re-applying changes (attempt {})",
+                path.display(),
+                attempt
+            );
+            continue;
+        }
+
+        write_atomic(path, json_content)
+            .map_err(|e| AppError::io(&format!("Failed to write {}", path.display()), path, e))?;
+
+        return Ok(result);
+    }
+
+    Err(AppError::conflict(format!(
+        "{} kept changing while updating it, please try again",
+        path.display()
+    ))
+    .with_path(path))
+}
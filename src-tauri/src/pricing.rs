@@ -0,0 +1,326 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use crate::commands::{ProjectUsageRecord, UsageData};
+use crate::error::AppError;
+use crate::paths;
+use crate::persistence::write_atomic;
+
+// Anthropic's list prices, shipped with the app
+const BUILTIN: &str = include_str!("../pricing/models.json");
+
+// The user's prices, checked before the built-in ones
+const USER_PRICES_FILE: &str = "pricing.json";
+
+/// Prices in USD per million tokens for the models matching `pattern`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    /// Model id, `*` matches any run of characters, e.g. `claude-sonnet-4*`
+    pub pattern: String,
+    pub input: f64,
+    pub output: f64,
+    /// 5 minute cache writes
+    pub cache_write: f64,
+    pub cache_read: f64,
+    /// 1 hour cache writes, twice the input price when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_1h: Option<f64>,
+    /// Per 1,000 web search requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub web_search: Option<f64>,
+}
+
+impl ModelPrice {
+    pub fn cost(&self, usage: &UsageData) -> f64 {
+        let tokens = |count: Option<u64>| count.unwrap_or(0) as f64 / 1_000_000.0;
+        let cache_write_1h = self.cache_write_1h.unwrap_or(self.input * 2.0);
+
+        // Without the breakdown all cache writes are taken to be 5 minute ones. Some
+        // gateways send an empty breakdown, which doesn't count as one.
+        let breakdown = usage.cache_creation.as_ref().filter(|cache_creation| {
+            cache_creation.ephemeral_5m_input_tokens.unwrap_or(0)
+                + cache_creation.ephemeral_1h_input_tokens.unwrap_or(0)
+                > 0
+        });
+        let cache_writes = match breakdown {
+            Some(cache_creation) => {
+                tokens(cache_creation.ephemeral_5m_input_tokens) * self.cache_write
+                    + tokens(cache_creation.ephemeral_1h_input_tokens) * cache_write_1h
+            }
+            None => tokens(usage.cache_creation_input_tokens) * self.cache_write,
+        };
+
+        let web_searches = usage
+            .server_tool_use
+            .as_ref()
+            .and_then(|server_tool_use| server_tool_use.web_search_requests)
+            .unwrap_or(0) as f64
+            / 1000.0;
+
+        tokens(usage.input_tokens) * self.input
+            + tokens(usage.output_tokens) * self.output
+            + cache_writes
+            + tokens(usage.cache_read_input_tokens) * self.cache_read
+            + web_searches * self.web_search.unwrap_or(0.0)
+    }
+}
+
+/// The price table in effect, first match wins.
+#[derive(Debug, Clone)]
+pub struct PriceTable {
+    prices: Vec<ModelPrice>,
+}
+
+impl PriceTable {
+    /// User prices followed by the built-in ones. A broken `pricing.json` is
+    /// skipped, costs then come from the built-in prices alone.
+    pub fn load() -> Result<PriceTable, AppError> {
+        let mut prices = user_prices().unwrap_or_else(|e| {
+            eprintln!("Warning: Ignoring your model prices: {}", e);
+            vec![]
+        });
+        prices.extend(builtin_prices()?);
+        Ok(PriceTable { prices })
+    }
+
+    pub fn price(&self, model: &str) -> Option<&ModelPrice> {
+        let model = model.to_lowercase();
+        self.prices
+            .iter()
+            .find(|price| matches_pattern(&price.pattern.to_lowercase(), &model))
+    }
+
+    /// None when the model or usage is unknown, or there is no price for the model.
+    pub fn cost(&self, model: Option<&str>, usage: Option<&UsageData>) -> Option<f64> {
+        Some(self.price(model?)?.cost(usage?))
+    }
+
+    /// Fill in `cost_usd` of each record.
+    pub fn apply(&self, records: &mut [ProjectUsageRecord]) {
+        for record in records {
+            record.cost_usd = self.cost(record.model.as_deref(), record.usage.as_ref());
+        }
+    }
+}
+
+pub fn builtin_prices() -> Result<Vec<ModelPrice>, AppError> {
+    Ok(serde_json::from_str(BUILTIN).map_err(|e| format!("Failed to parse built-in model prices: {}", e))?)
+}
+
+fn user_prices_file() -> Result<PathBuf, String> {
+    Ok(paths::app_config_dir()?.join(USER_PRICES_FILE))
+}
+
+pub fn user_prices() -> Result<Vec<ModelPrice>, AppError> {
+    let path = user_prices_file()?;
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(AppError::io("Failed to read model prices", &path, e)),
+    };
+    serde_json::from_str(&content).map_err(|e| AppError::parse("Failed to parse model prices", &path, e))
+}
+
+pub fn save_user_prices(prices: &[ModelPrice]) -> Result<(), AppError> {
+    let path = user_prices_file()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| AppError::io("Failed to create directory", parent, e))?;
+    }
+
+    let json_content =
+        serde_json::to_string_pretty(prices).map_err(|e| format!("Failed to serialize model prices: {}", e))?;
+    write_atomic(&path, json_content).map_err(|e| AppError::io("Failed to write model prices", &path, e))
+}
+
+// Glob with `*` only, which is all model ids need
+fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` at all
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Estimated cost of the records that have a price, in USD.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct UsageCosts {
+    pub total_usd: f64,
+    /// Local dates, `YYYY-MM-DD`
+    pub by_day: BTreeMap<String, f64>,
+    pub by_model: BTreeMap<String, f64>,
+    pub by_project: BTreeMap<String, f64>,
+    /// Models used without a price in the table, their usage isn't counted
+    pub unpriced_models: Vec<String>,
+}
+
+pub fn usage_costs(records: &[ProjectUsageRecord]) -> UsageCosts {
+    let mut costs = UsageCosts::default();
+    let mut unpriced = BTreeSet::new();
+
+    for record in records {
+        let Some(cost) = record.cost_usd else {
+            if let Some(model) = &record.model {
+                unpriced.insert(model.clone());
+            }
+            continue;
+        };
+
+        costs.total_usd += cost;
+        if let Some(day) = local_day(&record.timestamp) {
+            *costs.by_day.entry(day).or_default() += cost;
+        }
+        if let Some(model) = &record.model {
+            *costs.by_model.entry(model.clone()).or_default() += cost;
+        }
+        let project = record.project_path.clone().unwrap_or_else(|| "unknown".to_string());
+        *costs.by_project.entry(project).or_default() += cost;
+    }
+
+    costs.unpriced_models = unpriced.into_iter().collect();
+    costs
+}
+
+fn local_day(timestamp: &str) -> Option<String> {
+    let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+    Some(timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(value: serde_json::Value) -> UsageData {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn price() -> ModelPrice {
+        ModelPrice {
+            pattern: "claude-sonnet-4*".to_string(),
+            input: 3.0,
+            output: 15.0,
+            cache_write: 3.75,
+            cache_read: 0.3,
+            cache_write_1h: None,
+            web_search: Some(10.0),
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn pattern_without_star_matches_exactly() {
+        assert!(matches_pattern("claude-sonnet-4", "claude-sonnet-4"));
+        assert!(!matches_pattern("claude-sonnet-4", "claude-sonnet-4-5"));
+        assert!(!matches_pattern("claude-sonnet-4", "claude-sonnet"));
+    }
+
+    #[test]
+    fn star_matches_any_run() {
+        assert!(matches_pattern("claude-sonnet-4*", "claude-sonnet-4"));
+        assert!(matches_pattern("claude-sonnet-4*", "claude-sonnet-4-5-20250929"));
+        assert!(matches_pattern("*sonnet*", "claude-3-5-sonnet-latest"));
+        assert!(matches_pattern("claude-*-sonnet-*", "claude-3-7-sonnet-20250219"));
+        assert!(matches_pattern("*", ""));
+        assert!(!matches_pattern("claude-opus-4*", "claude-3-opus-20240229"));
+        assert!(!matches_pattern("*haiku", "claude-haiku-4-5"));
+    }
+
+    #[test]
+    fn star_parts_match_in_order() {
+        assert!(matches_pattern("a*b*c", "a-b-c"));
+        assert!(!matches_pattern("a*c*b", "a-b-c"));
+        // The prefix and suffix can't share characters
+        assert!(!matches_pattern("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn first_matching_price_wins() {
+        let table = PriceTable {
+            prices: builtin_prices().unwrap(),
+        };
+        assert_eq!(table.price("claude-opus-4-5-20251101").unwrap().input, 5.0);
+        assert_eq!(table.price("claude-opus-4-1-20250805").unwrap().input, 15.0);
+        assert_eq!(table.price("Claude-Sonnet-4-5").unwrap().pattern, "claude-sonnet-4*");
+        assert!(table.price("glm-4.6").is_none());
+    }
+
+    #[test]
+    fn cost_adds_up_every_kind_of_token() {
+        let usage = usage(serde_json::json!({
+            "input_tokens": 1_000_000,
+            "output_tokens": 2_000_000,
+            "cache_read_input_tokens": 10_000_000,
+            "server_tool_use": { "web_search_requests": 100 }
+        }));
+        assert_close(price().cost(&usage), 3.0 + 30.0 + 3.0 + 1.0);
+    }
+
+    #[test]
+    fn cache_writes_are_priced_by_lifetime() {
+        let usage = usage(serde_json::json!({
+            "cache_creation_input_tokens": 3_000_000,
+            "cache_creation": {
+                "ephemeral_5m_input_tokens": 1_000_000,
+                "ephemeral_1h_input_tokens": 2_000_000
+            }
+        }));
+        // 1 hour writes default to twice the input price
+        assert_close(price().cost(&usage), 3.75 + 2.0 * 6.0);
+
+        let price = ModelPrice {
+            cache_write_1h: Some(5.0),
+            ..price()
+        };
+        assert_close(price.cost(&usage), 3.75 + 2.0 * 5.0);
+    }
+
+    #[test]
+    fn cache_writes_without_breakdown_are_5_minute_ones() {
+        let usage = usage(serde_json::json!({ "cache_creation_input_tokens": 2_000_000 }));
+        assert_close(price().cost(&usage), 2.0 * 3.75);
+    }
+
+    #[test]
+    fn empty_cache_breakdown_falls_back_to_the_total() {
+        let empty = usage(serde_json::json!({
+            "cache_creation_input_tokens": 2_000_000,
+            "cache_creation": {}
+        }));
+        assert_close(price().cost(&empty), 2.0 * 3.75);
+
+        let zeroed = usage(serde_json::json!({
+            "cache_creation_input_tokens": 2_000_000,
+            "cache_creation": { "ephemeral_5m_input_tokens": 0, "ephemeral_1h_input_tokens": 0 }
+        }));
+        assert_close(price().cost(&zeroed), 2.0 * 3.75);
+    }
+
+    #[test]
+    fn missing_counts_cost_nothing() {
+        assert_close(price().cost(&UsageData::default()), 0.0);
+
+        let price = ModelPrice {
+            web_search: None,
+            ..price()
+        };
+        let usage = usage(serde_json::json!({ "server_tool_use": { "web_search_requests": 5 } }));
+        assert_close(price.cost(&usage), 0.0);
+    }
+}
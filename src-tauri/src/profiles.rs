@@ -0,0 +1,127 @@
+use serde_json::Value;
+
+use crate::commands::ConfigStore;
+use crate::merge;
+
+/// Compute the effective settings of a store: its parents (in `extends` order)
+/// merged together, with the store's own settings layered on top.
+pub fn resolve_settings(stores: &[ConfigStore], store_id: &str) -> Result<Value, String> {
+    let mut chain = Vec::new();
+    resolve_into(stores, store_id, &mut chain)
+}
+
+fn resolve_into(stores: &[ConfigStore], store_id: &str, chain: &mut Vec<String>) -> Result<Value, String> {
+    if chain.iter().any(|id| id == store_id) {
+        chain.push(store_id.to_string());
+        return Err(format!("Profile inheritance cycle: {}", describe_chain(stores, chain)));
+    }
+
+    let store = stores
+        .iter()
+        .find(|store| store.id == store_id)
+        .ok_or_else(|| match chain.last() {
+            Some(child) => format!("Profile '{}' extends unknown profile '{}'", child, store_id),
+            None => format!("Store with id '{}' not found", store_id),
+        })?;
+
+    chain.push(store_id.to_string());
+
+    let mut effective = Value::Object(serde_json::Map::new());
+    for parent_id in store.extends.iter().flatten() {
+        let parent_settings = resolve_into(stores, parent_id, chain)?;
+        let parent_arrays = stores
+            .iter()
+            .find(|s| &s.id == parent_id)
+            .and_then(|s| s.array_strategy)
+            .unwrap_or_default();
+        merge::deep_merge(&mut effective, &parent_settings, parent_arrays);
+    }
+    merge::deep_merge(&mut effective, &store.settings, store.array_strategy.unwrap_or_default());
+
+    chain.pop();
+    Ok(effective)
+}
+
+fn describe_chain(stores: &[ConfigStore], chain: &[String]) -> String {
+    chain
+        .iter()
+        .map(|id| {
+            stores
+                .iter()
+                .find(|store| &store.id == id)
+                .map(|store| format!("\"{}\"", store.title))
+                .unwrap_or_else(|| id.clone())
+        })
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+/// Whether `store_id` inherits from `ancestor_id`, directly or through other parents.
+pub fn inherits_from(stores: &[ConfigStore], store_id: &str, ancestor_id: &str) -> bool {
+    let mut pending = vec![store_id.to_string()];
+    let mut seen = Vec::new();
+
+    while let Some(id) = pending.pop() {
+        if seen.contains(&id) {
+            continue;
+        }
+        let Some(store) = stores.iter().find(|store| store.id == id) else {
+            continue;
+        };
+        for parent_id in store.extends.iter().flatten() {
+            if parent_id == ancestor_id {
+                return true;
+            }
+            pending.push(parent_id.clone());
+        }
+        seen.push(id);
+    }
+
+    false
+}
+
+/// `store_id` followed by every profile it inherits from, directly or through
+/// other parents. These are the profiles whose vault secrets it may use.
+pub fn lineage(stores: &[ConfigStore], store_id: &str) -> Vec<String> {
+    let mut lineage = Vec::new();
+    let mut pending = vec![store_id.to_string()];
+
+    while let Some(id) = pending.pop() {
+        if lineage.contains(&id) {
+            continue;
+        }
+        if let Some(store) = stores.iter().find(|store| store.id == id) {
+            pending.extend(store.extends.iter().flatten().cloned());
+        }
+        lineage.push(id);
+    }
+
+    lineage
+}
+
+/// Stores that list `store_id` directly in their `extends`.
+pub fn direct_children<'a>(stores: &'a [ConfigStore], store_id: &str) -> Vec<&'a ConfigStore> {
+    stores
+        .iter()
+        .filter(|store| store.extends.iter().flatten().any(|id| id == store_id))
+        .collect()
+}
+
+/// Make sure a store's parents exist and don't lead back to it.
+pub fn validate_extends(stores: &[ConfigStore], store_id: &str, extends: &[String]) -> Result<(), String> {
+    if extends.iter().any(|id| id == store_id) {
+        return Err("A profile cannot extend itself".to_string());
+    }
+
+    // Resolve each parent as if the store already had its new parents
+    let mut candidate = stores.to_vec();
+    if let Some(store) = candidate.iter_mut().find(|store| store.id == store_id) {
+        store.extends = Some(extends.to_vec());
+    }
+    for parent_id in extends {
+        let mut chain = vec![store_id.to_string()];
+        resolve_into(&candidate, parent_id, &mut chain)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::paths;
+use crate::persistence::files_with_extension;
+
+// Presets shipped with the app, shared with the uTools preload
+const BUILTIN: &str = include_str!("../../public/preload/providers.json");
+
+// User presets under the app config dir, one preset or a list of them per file
+const USER_PRESETS_DIR: &str = "providers";
+
+/// Claude Code's model variables, which an Anthropic-compatible provider maps to its own models.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelMapping {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opus: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sonnet: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub haiku: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub small_fast: Option<String>,
+}
+
+impl ModelMapping {
+    fn env(&self) -> Vec<(&'static str, &String)> {
+        [
+            ("ANTHROPIC_MODEL", &self.default),
+            ("ANTHROPIC_DEFAULT_OPUS_MODEL", &self.opus),
+            ("ANTHROPIC_DEFAULT_SONNET_MODEL", &self.sonnet),
+            ("ANTHROPIC_DEFAULT_HAIKU_MODEL", &self.haiku),
+            ("ANTHROPIC_SMALL_FAST_MODEL", &self.small_fast),
+        ]
+        .into_iter()
+        .filter_map(|(key, model)| model.as_ref().map(|model| (key, model)))
+        .collect()
+    }
+}
+
+/// A value the user has to supply, e.g. the API key.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretField {
+    /// Env variable the value is written to
+    pub env: String,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PresetSource {
+    #[default]
+    Builtin,
+    User,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderPreset {
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub base_url: String,
+    #[serde(default)]
+    pub models: ModelMapping,
+    /// Other env variables the provider needs, e.g. a longer `API_TIMEOUT_MS`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub env: Map<String, Value>,
+    #[serde(default)]
+    pub secrets: Vec<SecretField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_url: Option<String>,
+    /// Set when loading, not read from the preset files
+    #[serde(default, skip_deserializing)]
+    pub source: PresetSource,
+}
+
+impl ProviderPreset {
+    /// Settings of a new profile for this provider. Every secret field must
+    /// have a non-empty value; the tokens end up in the vault when the
+    /// profile is created, like any other.
+    pub fn settings(&self, secrets: &HashMap<String, String>) -> Result<Value, AppError> {
+        let mut env = self.env.clone();
+        env.insert("ANTHROPIC_BASE_URL".to_string(), Value::String(self.base_url.clone()));
+        for (key, model) in self.models.env() {
+            env.insert(key.to_string(), Value::String(model.clone()));
+        }
+
+        for field in &self.secrets {
+            let value = secrets.get(&field.env).map(|value| value.trim()).unwrap_or_default();
+            if value.is_empty() {
+                return Err(AppError::validation(format!("{} is required for {}", field.label, self.name), vec![]));
+            }
+            env.insert(field.env.clone(), Value::String(value.to_string()));
+        }
+
+        Ok(serde_json::json!({ "env": env }))
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.id.trim().is_empty() {
+            return Err("preset id is empty".to_string());
+        }
+        if !self.base_url.starts_with("https://") && !self.base_url.starts_with("http://") {
+            return Err(format!("preset '{}' has an invalid base URL: {}", self.id, self.base_url));
+        }
+        Ok(())
+    }
+}
+
+fn user_presets_dir() -> Result<PathBuf, String> {
+    Ok(paths::app_config_dir()?.join(USER_PRESETS_DIR))
+}
+
+/// Built-in presets followed by the user's. A user preset with the id of a
+/// built-in one replaces it; broken user files are skipped with a warning.
+pub fn load() -> Result<Vec<ProviderPreset>, AppError> {
+    let mut presets: Vec<ProviderPreset> =
+        serde_json::from_str(BUILTIN).map_err(|e| format!("Failed to parse built-in provider presets: {}", e))?;
+
+    let dir = user_presets_dir()?;
+    if dir.is_dir() {
+        for path in files_with_extension(&dir, "json")? {
+            let user_presets = match read_user_presets(&path) {
+                Ok(user_presets) => user_presets,
+                Err(e) => {
+                    eprintln!("Warning: skipping provider presets in {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            for preset in user_presets {
+                match presets.iter_mut().find(|existing| existing.id == preset.id) {
+                    Some(existing) => *existing = preset,
+                    None => presets.push(preset),
+                }
+            }
+        }
+    }
+
+    Ok(presets)
+}
+
+pub fn find(preset_id: &str) -> Result<ProviderPreset, AppError> {
+    load()?
+        .into_iter()
+        .find(|preset| preset.id == preset_id)
+        .ok_or_else(|| AppError::not_found(format!("Provider preset '{}' not found", preset_id)))
+}
+
+fn read_user_presets(path: &Path) -> Result<Vec<ProviderPreset>, String> {
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum PresetFile {
+        Many(Vec<ProviderPreset>),
+        One(Box<ProviderPreset>),
+    }
+
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut presets = match serde_json::from_str(&content).map_err(|e| e.to_string())? {
+        PresetFile::Many(presets) => presets,
+        PresetFile::One(preset) => vec![*preset],
+    };
+
+    for preset in &mut presets {
+        preset.check()?;
+        preset.source = PresetSource::User;
+    }
+    Ok(presets)
+}
@@ -0,0 +1,597 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::error::{AppError, ErrorKind};
+use crate::paths;
+use crate::persistence::write_private;
+
+/// Key of the object that stands in for a secret inside profile settings: `{"$secret": "glm-token"}`
+pub const SECRET_REF_KEY: &str = "$secret";
+
+// Env variables whose plaintext values are moved into the vault when a profile is saved
+pub const SECRET_ENV_KEYS: &[&str] = &["ANTHROPIC_AUTH_TOKEN", "ANTHROPIC_API_KEY"];
+
+const VAULT_FILE: &str = "secrets.json";
+const MACHINE_KEY_FILE: &str = "vault.key";
+const NONCE_LEN: usize = 12;
+
+// Plaintext encrypted into `check` so a wrong passphrase is detected before anything is decrypted
+const PASSPHRASE_CHECK: &[u8] = b"ccmate-vault";
+
+// Key derived from the passphrase, kept in memory once the vault is unlocked
+static UNLOCKED_KEY: Mutex<Option<[u8; 32]>> = Mutex::new(None);
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VaultBackend {
+    /// Encrypted entries in ~/.ccconfig/secrets.json
+    #[default]
+    File,
+    /// The operating system's keyring
+    Keyring,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct VaultStatus {
+    pub backend: VaultBackend,
+    pub keyring_available: bool,
+    pub passphrase_protected: bool,
+    pub unlocked: bool,
+    pub secret_count: usize,
+}
+
+/// Storage for secret values. The vault file always keeps the list of names,
+/// backends only hold the values.
+pub trait SecretBackend {
+    fn get(&self, name: &str) -> Result<Option<String>, AppError>;
+    fn set(&mut self, name: &str, value: &str) -> Result<(), AppError>;
+    fn delete(&mut self, name: &str) -> Result<(), AppError>;
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+struct VaultFile {
+    #[serde(default)]
+    backend: VaultBackend,
+    // Salt of the Argon2 passphrase key; without it the machine key is used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    passphrase_salt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    check: Option<String>,
+    // Name -> base64(nonce || ciphertext) for the file backend, empty for the keyring backend
+    #[serde(default)]
+    secrets: BTreeMap<String, String>,
+}
+
+fn vault_dir() -> Result<PathBuf, AppError> {
+    Ok(paths::app_config_dir()?)
+}
+
+fn read_vault_file() -> Result<VaultFile, AppError> {
+    let path = vault_dir()?.join(VAULT_FILE);
+    if !path.exists() {
+        return Ok(VaultFile::default());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::io("Failed to read secrets file", &path, e))?;
+    serde_json::from_str(&content).map_err(|e| AppError::parse("Failed to parse secrets file", &path, e))
+}
+
+fn write_vault_file(vault: &VaultFile) -> Result<(), AppError> {
+    let path = vault_dir()?.join(VAULT_FILE);
+    let json_content = serde_json::to_string_pretty(vault)
+        .map_err(|e| format!("Failed to serialize secrets: {}", e))?;
+
+    write_private(&path, json_content).map_err(|e| AppError::io("Failed to write secrets file", &path, e))?;
+    Ok(())
+}
+
+fn machine_key() -> Result<[u8; 32], AppError> {
+    let path = vault_dir()?.join(MACHINE_KEY_FILE);
+
+    if path.exists() {
+        let encoded = std::fs::read_to_string(&path)
+            .map_err(|e| AppError::io("Failed to read vault key", &path, e))?;
+        let bytes = BASE64
+            .decode(encoded.trim())
+            .map_err(|e| format!("Failed to decode vault key: {}", e))?;
+        return bytes
+            .try_into()
+            .map_err(|_| "Vault key has an invalid length".into());
+    }
+
+    let key: [u8; 32] = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+    write_private(&path, BASE64.encode(key)).map_err(|e| AppError::io("Failed to write vault key", &path, e))?;
+    println!("Created new vault key: {}", path.display());
+    Ok(key)
+}
+
+pub fn derive_passphrase_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], AppError> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive key from passphrase: {}", e))?;
+    Ok(key)
+}
+
+pub fn encrypt(key: &[u8; 32], name: &str, plaintext: &[u8]) -> Result<String, AppError> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    // The name is authenticated too, so entries can't be swapped around in the file
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plaintext, aad: name.as_bytes() })
+        .map_err(|_| format!("Failed to encrypt secret '{}'", name))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(BASE64.encode(sealed))
+}
+
+pub fn decrypt(key: &[u8; 32], name: &str, sealed: &str) -> Result<Vec<u8>, AppError> {
+    let bytes = BASE64
+        .decode(sealed)
+        .map_err(|e| format!("Failed to decode secret '{}': {}", name, e))?;
+    if bytes.len() < NONCE_LEN {
+        return Err(format!("Secret '{}' is corrupted", name).into());
+    }
+
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: name.as_bytes() })
+        .map_err(|_| format!("Failed to decrypt secret '{}', the vault key may be wrong", name).into())
+}
+
+// The key the file backend encrypts with: the unlocked passphrase key or the machine key
+fn vault_key(vault: &VaultFile) -> Result<[u8; 32], AppError> {
+    if vault.passphrase_salt.is_some() {
+        UNLOCKED_KEY
+            .lock()
+            .map_err(|e| format!("Failed to access vault key: {}", e))?
+            .ok_or_else(|| AppError::new(ErrorKind::Locked, "The secret vault is locked, unlock it with your passphrase first"))
+    } else {
+        machine_key()
+    }
+}
+
+struct FileBackend<'a> {
+    vault: &'a mut VaultFile,
+    key: [u8; 32],
+}
+
+impl SecretBackend for FileBackend<'_> {
+    fn get(&self, name: &str) -> Result<Option<String>, AppError> {
+        match self.vault.secrets.get(name) {
+            Some(sealed) => {
+                let plaintext = decrypt(&self.key, name, sealed)?;
+                String::from_utf8(plaintext)
+                    .map(Some)
+                    .map_err(|e| format!("Secret '{}' is not valid UTF-8: {}", name, e).into())
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), AppError> {
+        let sealed = encrypt(&self.key, name, value.as_bytes())?;
+        self.vault.secrets.insert(name.to_string(), sealed);
+        Ok(())
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), AppError> {
+        self.vault.secrets.remove(name);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "keyring")]
+struct KeyringBackend<'a> {
+    vault: &'a mut VaultFile,
+}
+
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "cc-mate";
+
+#[cfg(feature = "keyring")]
+impl SecretBackend for KeyringBackend<'_> {
+    fn get(&self, name: &str) -> Result<Option<String>, AppError> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, name)
+            .map_err(|e| format!("Failed to open keyring entry '{}': {}", name, e))?;
+        match entry.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(format!("Failed to read secret '{}' from keyring: {}", name, e).into()),
+        }
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), AppError> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, name)
+            .map_err(|e| format!("Failed to open keyring entry '{}': {}", name, e))?;
+        entry
+            .set_password(value)
+            .map_err(|e| format!("Failed to store secret '{}' in keyring: {}", name, e))?;
+        self.vault.secrets.insert(name.to_string(), String::new());
+        Ok(())
+    }
+
+    fn delete(&mut self, name: &str) -> Result<(), AppError> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, name)
+            .map_err(|e| format!("Failed to open keyring entry '{}': {}", name, e))?;
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(format!("Failed to delete secret '{}' from keyring: {}", name, e).into()),
+        }
+        self.vault.secrets.remove(name);
+        Ok(())
+    }
+}
+
+// Run `f` against the configured backend and save the vault file if it changed
+fn with_backend<T>(
+    mutate: bool,
+    f: impl FnOnce(&mut dyn SecretBackend) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    let mut vault = read_vault_file()?;
+    let backend = vault.backend;
+    let result = with_backend_for(&mut vault, backend, f)?;
+    if mutate {
+        write_vault_file(&vault)?;
+    }
+    Ok(result)
+}
+
+fn with_backend_for<T>(
+    vault: &mut VaultFile,
+    backend: VaultBackend,
+    f: impl FnOnce(&mut dyn SecretBackend) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    match backend {
+        VaultBackend::File => {
+            let key = vault_key(vault)?;
+            f(&mut FileBackend { vault, key })
+        }
+        #[cfg(feature = "keyring")]
+        VaultBackend::Keyring => f(&mut KeyringBackend { vault }),
+        #[cfg(not(feature = "keyring"))]
+        VaultBackend::Keyring => Err("CC Mate was built without OS keyring support".into()),
+    }
+}
+
+pub fn get_secret(name: &str) -> Result<Option<String>, AppError> {
+    with_backend(false, |backend| backend.get(name))
+}
+
+pub fn set_secret(name: &str, value: &str) -> Result<(), AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::new(ErrorKind::Validation, "Secret name cannot be empty"));
+    }
+    with_backend(true, |backend| backend.set(name, value))
+}
+
+pub fn delete_secret(name: &str) -> Result<(), AppError> {
+    with_backend(true, |backend| backend.delete(name))
+}
+
+pub fn list_secrets() -> Result<Vec<String>, AppError> {
+    Ok(read_vault_file()?.secrets.into_keys().collect())
+}
+
+pub fn status() -> Result<VaultStatus, AppError> {
+    let vault = read_vault_file()?;
+    let passphrase_protected = vault.passphrase_salt.is_some();
+    let unlocked = !passphrase_protected
+        || UNLOCKED_KEY
+            .lock()
+            .map(|key| key.is_some())
+            .unwrap_or(false);
+
+    Ok(VaultStatus {
+        backend: vault.backend,
+        keyring_available: cfg!(feature = "keyring"),
+        passphrase_protected,
+        unlocked,
+        secret_count: vault.secrets.len(),
+    })
+}
+
+/// Unlock a passphrase-protected vault for the rest of the session.
+pub fn unlock(passphrase: &str) -> Result<(), AppError> {
+    let vault = read_vault_file()?;
+    let (Some(salt), Some(check)) = (&vault.passphrase_salt, &vault.check) else {
+        return Err(AppError::conflict("The secret vault is not protected by a passphrase"));
+    };
+
+    let salt = BASE64
+        .decode(salt)
+        .map_err(|e| format!("Failed to decode vault salt: {}", e))?;
+    let key = derive_passphrase_key(passphrase, &salt)?;
+    decrypt(&key, "check", check).map_err(|_| AppError::new(ErrorKind::Validation, "Wrong passphrase"))?;
+
+    *UNLOCKED_KEY
+        .lock()
+        .map_err(|e| format!("Failed to access vault key: {}", e))? = Some(key);
+    Ok(())
+}
+
+/// Re-key the file vault with a passphrase, or with the machine key when `None`.
+pub fn set_passphrase(passphrase: Option<&str>) -> Result<(), AppError> {
+    let mut vault = read_vault_file()?;
+    if vault.backend != VaultBackend::File {
+        return Err(AppError::conflict("A passphrase only applies to the encrypted vault file"));
+    }
+    let old_key = vault_key(&vault)?;
+
+    let (new_key, salt) = match passphrase.filter(|p| !p.is_empty()) {
+        Some(passphrase) => {
+            let mut salt = [0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            (derive_passphrase_key(passphrase, &salt)?, Some(salt))
+        }
+        None => (machine_key()?, None),
+    };
+
+    for (name, sealed) in vault.secrets.iter_mut() {
+        let plaintext = decrypt(&old_key, name, sealed)?;
+        *sealed = encrypt(&new_key, name, &plaintext)?;
+    }
+
+    vault.passphrase_salt = salt.map(|salt| BASE64.encode(salt));
+    vault.check = match salt {
+        Some(_) => Some(encrypt(&new_key, "check", PASSPHRASE_CHECK)?),
+        None => None,
+    };
+    write_vault_file(&vault)?;
+
+    *UNLOCKED_KEY
+        .lock()
+        .map_err(|e| format!("Failed to access vault key: {}", e))? = salt.map(|_| new_key);
+    Ok(())
+}
+
+/// Move every secret to another backend.
+pub fn set_backend(backend: VaultBackend) -> Result<(), AppError> {
+    let mut vault = read_vault_file()?;
+    if vault.backend == backend {
+        return Ok(());
+    }
+
+    let names: Vec<String> = vault.secrets.keys().cloned().collect();
+    let current = vault.backend;
+    let mut values = Vec::new();
+    for name in &names {
+        if let Some(value) = with_backend_for(&mut vault, current, |b| b.get(name))? {
+            values.push((name.clone(), value));
+        }
+    }
+
+    // Copy into the new backend before removing anything from the old one
+    let mut moved = VaultFile {
+        backend,
+        passphrase_salt: vault.passphrase_salt.clone(),
+        check: vault.check.clone(),
+        secrets: BTreeMap::new(),
+    };
+    for (name, value) in &values {
+        with_backend_for(&mut moved, backend, |b| b.set(name, value))?;
+    }
+    // and only once the vault file points at the new backend, so a failure
+    // in between never leaves the secrets unreachable
+    write_vault_file(&moved)?;
+
+    for name in &names {
+        if let Err(e) = with_backend_for(&mut vault, current, |b| b.delete(name)) {
+            eprintln!("Warning: Failed to remove secret '{}' from the previous backend: {}", name, e);
+        }
+    }
+    Ok(())
+}
+
+pub fn secret_ref_name(value: &Value) -> Option<&str> {
+    let obj = value.as_object()?;
+    if obj.len() != 1 {
+        return None;
+    }
+    obj.get(SECRET_REF_KEY)?.as_str()
+}
+
+/// Reference object standing in for the secret `name`
+pub fn secret_ref(name: &str) -> Value {
+    serde_json::json!({ SECRET_REF_KEY: name })
+}
+
+/// Name of the vault secret `extract_secrets` keeps a store's env variable `key` in.
+pub fn store_secret_name(store_id: &str, key: &str) -> String {
+    format!("{}-{}", store_id, key.to_lowercase())
+}
+
+/// Name of the vault secret that keeps the token a store replaced in a settings
+/// file, until the store is switched off and it is put back.
+pub fn previous_secret_name(store_id: &str, key: &str) -> String {
+    format!("{}.previous", store_secret_name(store_id, key))
+}
+
+/// Whether `name` is the secret of one of the `owners` stores. Ids may contain
+/// `-`, so the whole name is compared rather than a prefix.
+pub fn is_owned(name: &str, owners: &[String]) -> bool {
+    owners.iter().any(|owner| {
+        SECRET_ENV_KEYS
+            .iter()
+            .any(|key| name == store_secret_name(owner, key) || name == previous_secret_name(owner, key))
+    })
+}
+
+/// Replace every `{"$secret": name}` with the secret's value. Returns the
+/// resolved settings and the (name, value) pairs that were used.
+///
+/// Only the secrets of `owners` (a profile and the ones it extends, see
+/// `profiles::lineage`) are resolved, so a reference copied into another
+/// profile can't send its token to a different base URL.
+pub fn resolve_refs(value: &Value, owners: &[String]) -> Result<(Value, Vec<(String, String)>), AppError> {
+    let mut used = Vec::new();
+    let resolved = resolve_into(value, owners, &mut used)?;
+    Ok((resolved, used))
+}
+
+fn resolve_into(value: &Value, owners: &[String], used: &mut Vec<(String, String)>) -> Result<Value, AppError> {
+    if let Some(name) = secret_ref_name(value) {
+        if !is_owned(name, owners) {
+            return Err(AppError::new(
+                ErrorKind::Validation,
+                format!("Secret '{}' belongs to another profile", name),
+            ));
+        }
+        let secret =
+            get_secret(name)?.ok_or_else(|| AppError::not_found(format!("Secret '{}' not found in vault", name)))?;
+        used.push((name.to_string(), secret.clone()));
+        return Ok(Value::String(secret));
+    }
+
+    match value {
+        Value::Object(obj) => {
+            let mut resolved = serde_json::Map::new();
+            for (key, child) in obj {
+                resolved.insert(key.clone(), resolve_into(child, owners, used)?);
+            }
+            Ok(Value::Object(resolved))
+        }
+        Value::Array(items) => items
+            .iter()
+            .map(|item| resolve_into(item, owners, used))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        _ => Ok(value.clone()),
+    }
+}
+
+/// Inverse of `resolve_refs`: swap known secret values back for their references,
+/// so resolved values never end up in stores.json.
+pub fn conceal(value: &Value, secrets: &[(String, String)]) -> Value {
+    match value {
+        Value::String(s) => secrets
+            .iter()
+            .find(|(_, secret)| secret == s)
+            .map(|(name, _)| secret_ref(name))
+            .unwrap_or_else(|| value.clone()),
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .map(|(key, child)| (key.clone(), conceal(child, secrets)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(|item| conceal(item, secrets)).collect()),
+        _ => value.clone(),
+    }
+}
+
+/// Settings safe to show: vault values become references and tokens that
+/// aren't in the vault are cut down to their last four characters.
+pub fn mask(settings: &Value, used_secrets: &[(String, String)]) -> Value {
+    let mut masked = conceal(settings, used_secrets);
+
+    if let Some(env) = masked.get_mut("env").and_then(|env| env.as_object_mut()) {
+        for key in SECRET_ENV_KEYS {
+            if let Some(Value::String(token)) = env.get_mut(*key) {
+                let chars: Vec<char> = token.chars().collect();
+                *token = if chars.len() > 8 {
+                    format!("****{}", chars[chars.len() - 4..].iter().collect::<String>())
+                } else {
+                    "****".to_string()
+                };
+            }
+        }
+    }
+
+    masked
+}
+
+/// Move plaintext tokens out of a profile's `env` into the vault, leaving references behind.
+pub fn extract_secrets(store_id: &str, settings: &mut Value) -> Result<(), AppError> {
+    let Some(env) = settings.get_mut("env").and_then(|env| env.as_object_mut()) else {
+        return Ok(());
+    };
+
+    for key in SECRET_ENV_KEYS {
+        let Some(Value::String(plaintext)) = env.get(*key) else {
+            continue;
+        };
+        if plaintext.is_empty() {
+            continue;
+        }
+        // A token is one word; this catches e.g. "[object Object]" or a masked "****abcd"
+        // sent back by a form, which would otherwise overwrite the real token in the vault
+        if plaintext.chars().any(char::is_whitespace) || plaintext.starts_with("****") {
+            return Err(AppError::new(
+                ErrorKind::Validation,
+                format!("{} doesn't look like a token, it was not saved", key),
+            ));
+        }
+
+        let name = store_secret_name(store_id, key);
+        set_secret(&name, plaintext)?;
+        env.insert(key.to_string(), secret_ref(&name));
+    }
+
+    Ok(())
+}
+
+/// Like `extract_secrets`, for what was at `pointer` in a settings file before a
+/// store replaced it. The tokens are kept under the store's `previous_secret_name`.
+pub fn extract_previous_secrets(store_id: &str, pointer: &str, value: &mut Value) -> Result<(), AppError> {
+    for key in SECRET_ENV_KEYS {
+        let token_pointer = format!("/env/{}", key);
+        let Some(relative) = token_pointer.strip_prefix(pointer) else {
+            continue;
+        };
+        let target = match relative {
+            "" => Some(&mut *value),
+            relative if relative.starts_with('/') => value.pointer_mut(relative),
+            _ => None,
+        };
+        let Some(target) = target else {
+            continue;
+        };
+        let plaintext = match &*target {
+            Value::String(plaintext) if !plaintext.is_empty() => plaintext.clone(),
+            _ => continue,
+        };
+
+        let name = previous_secret_name(store_id, key);
+        set_secret(&name, &plaintext)?;
+        *target = secret_ref(&name);
+    }
+
+    Ok(())
+}
+
+/// Names of the secrets `extract_secrets` created for a store.
+pub fn owned_by_store(store_id: &str, settings: &Value) -> Vec<String> {
+    let owners = [store_id.to_string()];
+    let mut names = ref_names(settings);
+    names.retain(|name| is_owned(name, &owners));
+    names
+}
+
+/// Names of all secrets `value` references.
+pub fn ref_names(value: &Value) -> Vec<String> {
+    let mut names = Vec::new();
+    collect_ref_names(value, &mut names);
+    names
+}
+
+fn collect_ref_names(value: &Value, names: &mut Vec<String>) {
+    if let Some(name) = secret_ref_name(value) {
+        names.push(name.to_string());
+        return;
+    }
+
+    match value {
+        Value::Object(obj) => obj.values().for_each(|child| collect_ref_names(child, names)),
+        Value::Array(items) => items.iter().for_each(|item| collect_ref_names(item, names)),
+        _ => {}
+    }
+}
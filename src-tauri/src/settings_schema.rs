@@ -0,0 +1,379 @@
+use serde_json::{Map, Value};
+
+use crate::error::AppError;
+use crate::merge::escape_pointer_token;
+use crate::secrets::SECRET_REF_KEY;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Claude Code would reject or misread the value, writes are refused
+    Error,
+    /// Probably a mistake, but written anyway (e.g. a key we don't know yet)
+    Warning,
+}
+
+/// A problem found in a settings document, located by a JSON pointer.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct Diagnostic {
+    pub path: String,
+    pub severity: Severity,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+enum Schema {
+    Any,
+    String,
+    Bool,
+    Integer,
+    Enum(&'static [&'static str]),
+    /// Like `Enum`, but other strings are only a warning since Claude Code
+    /// keeps adding values
+    KnownValues(&'static [&'static str]),
+    StringArray,
+    Env,
+    Object(&'static [Field]),
+    Hooks,
+}
+
+struct Field {
+    name: &'static str,
+    schema: Schema,
+    required: bool,
+}
+
+const fn optional(name: &'static str, schema: Schema) -> Field {
+    Field { name, schema, required: false }
+}
+
+const fn required(name: &'static str, schema: Schema) -> Field {
+    Field { name, schema, required: true }
+}
+
+// Based on specs/claude-code-settings-doc.md
+const SETTINGS: &[Field] = &[
+    optional("$schema", Schema::String),
+    optional("apiKeyHelper", Schema::String),
+    optional("cleanupPeriodDays", Schema::Integer),
+    optional("env", Schema::Env),
+    optional("includeCoAuthoredBy", Schema::Bool),
+    optional("permissions", Schema::Object(PERMISSIONS)),
+    optional("hooks", Schema::Hooks),
+    optional("disableAllHooks", Schema::Bool),
+    optional("model", Schema::String),
+    optional("statusLine", Schema::Object(STATUS_LINE)),
+    optional("outputStyle", Schema::String),
+    optional("forceLoginMethod", Schema::KnownValues(&["claudeai", "console"])),
+    optional("forceLoginOrgUUID", Schema::String),
+    optional("enableAllProjectMcpServers", Schema::Bool),
+    optional("enabledMcpjsonServers", Schema::StringArray),
+    optional("disabledMcpjsonServers", Schema::StringArray),
+    optional("useEnterpriseMcpConfigOnly", Schema::Bool),
+    optional("awsAuthRefresh", Schema::String),
+    optional("awsCredentialExport", Schema::String),
+    optional("alwaysThinkingEnabled", Schema::Bool),
+];
+
+const PERMISSIONS: &[Field] = &[
+    optional("allow", Schema::StringArray),
+    optional("ask", Schema::StringArray),
+    optional("deny", Schema::StringArray),
+    optional("additionalDirectories", Schema::StringArray),
+    optional(
+        "defaultMode",
+        Schema::KnownValues(&["default", "acceptEdits", "plan", "bypassPermissions"]),
+    ),
+    optional("disableBypassPermissionsMode", Schema::Enum(&["disable"])),
+];
+
+const STATUS_LINE: &[Field] = &[
+    required("type", Schema::Enum(&["command"])),
+    required("command", Schema::String),
+    optional("padding", Schema::Integer),
+];
+
+const HOOK_MATCHER: &[Field] = &[
+    optional("matcher", Schema::String),
+    required("hooks", Schema::Any),
+];
+
+const HOOK_COMMAND: &[Field] = &[
+    required("type", Schema::KnownValues(&["command", "prompt"])),
+    // `command` hooks run a command, `prompt` hooks ask the model
+    optional("command", Schema::String),
+    optional("prompt", Schema::String),
+    optional("timeout", Schema::Integer),
+    // Marks the hooks installed by this app
+    optional("__ccmate__", Schema::Bool),
+];
+
+const HOOK_EVENTS: &[&str] = &[
+    "PreToolUse",
+    "PostToolUse",
+    "Notification",
+    "UserPromptSubmit",
+    "Stop",
+    "SubagentStop",
+    "PreCompact",
+    "SessionStart",
+    "SessionEnd",
+];
+
+// Keys Claude Code used to read, and what replaced them
+const DEPRECATED: &[(&str, &str)] = &[("ignorePatterns", "permissions.deny")];
+
+/// Check a settings document against the known Claude Code settings.
+pub fn validate(settings: &Value) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    check(settings, &Schema::Object(SETTINGS), "", &mut diagnostics);
+    diagnostics
+}
+
+/// Like `validate`, but fails when there is any error.
+pub fn ensure_valid(settings: &Value) -> Result<(), AppError> {
+    let diagnostics = validate(settings);
+    let errors: Vec<String> = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .map(|diagnostic| {
+            let path = if diagnostic.path.is_empty() { "/" } else { &diagnostic.path };
+            format!("{}: {}", path, diagnostic.message)
+        })
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::validation(
+            format!("Invalid settings:\n{}", errors.join("\n")),
+            diagnostics,
+        ))
+    }
+}
+
+fn check(value: &Value, schema: &Schema, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+    match schema {
+        Schema::Any => {}
+        Schema::String => expect(value.is_string(), "a string", value, path, diagnostics),
+        Schema::Bool => expect(value.is_boolean(), "true or false", value, path, diagnostics),
+        Schema::Integer => expect(
+            value.as_i64().is_some_and(|n| n >= 0),
+            "a non-negative integer",
+            value,
+            path,
+            diagnostics,
+        ),
+        Schema::Enum(allowed) => match value.as_str() {
+            Some(s) if allowed.contains(&s) => {}
+            Some(s) => diagnostics.push(Diagnostic {
+                path: path.to_string(),
+                severity: Severity::Error,
+                message: format!("'{}' is not one of {}", s, quote_all(allowed)),
+                suggestion: closest(s, allowed.iter().copied()),
+            }),
+            None => expect(false, &format!("one of {}", quote_all(allowed)), value, path, diagnostics),
+        },
+        Schema::KnownValues(known) => match value.as_str() {
+            Some(s) if known.contains(&s) => {}
+            Some(s) => diagnostics.push(Diagnostic {
+                path: path.to_string(),
+                severity: Severity::Warning,
+                message: format!("Unknown value '{}', expected one of {}", s, quote_all(known)),
+                suggestion: closest(s, known.iter().copied()),
+            }),
+            None => expect(false, "a string", value, path, diagnostics),
+        },
+        Schema::StringArray => match value.as_array() {
+            Some(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    check(item, &Schema::String, &format!("{}/{}", path, index), diagnostics);
+                }
+            }
+            None => expect(false, "an array of strings", value, path, diagnostics),
+        },
+        Schema::Env => match value.as_object() {
+            Some(vars) => {
+                for (name, var) in vars {
+                    check_env_var(var, &child_path(path, name), diagnostics);
+                }
+            }
+            None => expect(false, "an object of environment variables", value, path, diagnostics),
+        },
+        Schema::Object(fields) => match value.as_object() {
+            Some(obj) => check_object(obj, fields, path, diagnostics),
+            None => expect(false, "an object", value, path, diagnostics),
+        },
+        Schema::Hooks => match value.as_object() {
+            Some(events) => check_hooks(events, path, diagnostics),
+            None => expect(false, "an object of hook events", value, path, diagnostics),
+        },
+    }
+}
+
+fn check_object(obj: &Map<String, Value>, fields: &[Field], path: &str, diagnostics: &mut Vec<Diagnostic>) {
+    for field in fields.iter().filter(|field| field.required) {
+        if !obj.contains_key(field.name) {
+            diagnostics.push(Diagnostic {
+                path: path.to_string(),
+                severity: Severity::Error,
+                message: format!("Missing required key '{}'", field.name),
+                suggestion: None,
+            });
+        }
+    }
+
+    for (key, value) in obj {
+        let key_path = child_path(path, key);
+        match fields.iter().find(|field| field.name == key) {
+            Some(field) => check(value, &field.schema, &key_path, diagnostics),
+            None => unknown_key(key, fields.iter().map(|field| field.name), &key_path, diagnostics),
+        }
+    }
+}
+
+fn check_hooks(events: &Map<String, Value>, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+    for (event, matchers) in events {
+        let event_path = child_path(path, event);
+        if !HOOK_EVENTS.contains(&event.as_str()) {
+            unknown_key(event, HOOK_EVENTS.iter().copied(), &event_path, diagnostics);
+        }
+
+        let Some(matchers) = matchers.as_array() else {
+            expect(false, "an array of hook matchers", matchers, &event_path, diagnostics);
+            continue;
+        };
+
+        for (index, matcher) in matchers.iter().enumerate() {
+            let matcher_path = format!("{}/{}", event_path, index);
+            check(matcher, &Schema::Object(HOOK_MATCHER), &matcher_path, diagnostics);
+
+            let Some(hooks) = matcher.get("hooks") else {
+                continue;
+            };
+            let hooks_path = format!("{}/hooks", matcher_path);
+            match hooks.as_array() {
+                Some(hooks) => {
+                    for (hook_index, hook) in hooks.iter().enumerate() {
+                        let hook_path = format!("{}/{}", hooks_path, hook_index);
+                        check(hook, &Schema::Object(HOOK_COMMAND), &hook_path, diagnostics);
+                    }
+                }
+                None => expect(false, "an array of hooks", hooks, &hooks_path, diagnostics),
+            }
+        }
+    }
+}
+
+fn check_env_var(value: &Value, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+    match value {
+        Value::String(_) => {}
+        // Tokens kept in the secret vault
+        Value::Object(obj) if obj.len() == 1 && obj.get(SECRET_REF_KEY).is_some_and(Value::is_string) => {}
+        Value::Number(_) | Value::Bool(_) => diagnostics.push(Diagnostic {
+            path: path.to_string(),
+            severity: Severity::Warning,
+            message: "Environment variables should be strings".to_string(),
+            suggestion: Some(value.to_string()),
+        }),
+        _ => expect(false, "a string", value, path, diagnostics),
+    }
+}
+
+fn unknown_key<'a>(
+    key: &str,
+    known: impl Iterator<Item = &'a str>,
+    path: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if let Some((_, replacement)) = DEPRECATED.iter().find(|(name, _)| *name == key) {
+        diagnostics.push(Diagnostic {
+            path: path.to_string(),
+            severity: Severity::Warning,
+            message: format!("'{}' is deprecated, use '{}' instead", key, replacement),
+            suggestion: Some(replacement.to_string()),
+        });
+        return;
+    }
+
+    let suggestion = closest(key, known);
+    let message = match &suggestion {
+        Some(name) => format!("Unknown key '{}', did you mean '{}'?", key, name),
+        None => format!("Unknown key '{}'", key),
+    };
+
+    // Claude Code gains settings and hook events faster than we learn about
+    // them, so an unknown key never blocks a write, even one that looks like a typo
+    diagnostics.push(Diagnostic {
+        path: path.to_string(),
+        severity: Severity::Warning,
+        message,
+        suggestion,
+    });
+}
+
+fn expect(ok: bool, expected: &str, value: &Value, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+    if !ok {
+        diagnostics.push(Diagnostic {
+            path: path.to_string(),
+            severity: Severity::Error,
+            message: format!("Expected {}, found {}", expected, describe(value)),
+            suggestion: None,
+        });
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!("\"{}\"", s),
+        Value::Array(_) => "an array".to_string(),
+        Value::Object(_) => "an object".to_string(),
+    }
+}
+
+fn quote_all(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| format!("'{}'", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    format!("{}/{}", path, escape_pointer_token(key))
+}
+
+// Closest known name, if it is near enough to be a likely typo
+fn closest<'a>(name: &str, known: impl Iterator<Item = &'a str>) -> Option<String> {
+    let lowercase = name.to_lowercase();
+    known
+        .map(|candidate| (levenshtein(&lowercase, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, candidate)| *distance <= (candidate.len() / 3).max(2))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if a_char == *b_char {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(current)
+            };
+            previous = current;
+        }
+    }
+
+    row[b.len()]
+}
@@ -0,0 +1,731 @@
+// Team sync: a shared directory (usually a git checkout) is the source of
+// truth for profiles, MCP servers, slash commands and agents.
+//
+// Layout of the source directory, every part optional:
+//
+//   profiles/*.json   one profile each: {"id", "title", "settings", "extends"?, "arrayStrategy"?},
+//                     without vault references (`{"$secret": ...}`)
+//   mcp.json          {"mcpServers": {"<name>": {...}}}
+//   commands/*.md     slash commands
+//   agents/*.md       agents
+//
+// Items written by sync are "managed": we remember the hash of what was
+// written and of the source it came from, so a later run can tell a local
+// edit from a team update and reports a conflict instead of overwriting.
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::bundle::BundleProfile;
+use crate::commands::{self, ConfigStore};
+use crate::error::{AppError, ErrorKind};
+use crate::merge::ArrayStrategy;
+use crate::paths;
+use crate::persistence::{files_with_extension, write_atomic};
+use crate::secrets;
+
+const SYNC_FILE: &str = "sync.json";
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncItemKind {
+    Profile,
+    Mcp,
+    Command,
+    Agent,
+}
+
+/// A profile (by id), MCP server, command or agent (by name).
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SyncItem {
+    pub kind: SyncItemKind,
+    pub name: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagedItem {
+    #[serde(flatten)]
+    pub item: SyncItem,
+    // Hash of the item as written locally, and of the source it was written from
+    pub local_hash: String,
+    pub source_hash: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictReason {
+    /// A local item with the same name that sync didn't create
+    Unmanaged,
+    /// A managed item was edited locally
+    ModifiedLocally,
+    /// A managed item was deleted locally but is still in the source
+    DeletedLocally,
+    /// The item was removed from the source but has local edits
+    RemovedUpstream,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    #[serde(flatten)]
+    pub item: SyncItem,
+    pub reason: ConflictReason,
+    // Whether the source has changed since the item was last synced
+    pub source_changed: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct SyncError {
+    #[serde(flatten)]
+    pub item: SyncItem,
+    pub message: String,
+}
+
+/// Which side wins when resolving a conflict.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncResolution {
+    /// Keep the local item and stop syncing it
+    Local,
+    /// Replace the local item with the source (or remove it if the source did)
+    Team,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    // Run `git pull --ff-only` in the source before syncing if it is a checkout
+    #[serde(default)]
+    pub pull: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_sync: Option<u64>,
+    #[serde(default)]
+    pub managed: Vec<ManagedItem>,
+    // Items the user chose to keep local after a conflict
+    #[serde(default)]
+    pub detached: Vec<SyncItem>,
+    // Conflicts found by the last run, until they are resolved
+    #[serde(default)]
+    pub conflicts: Vec<SyncConflict>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub pulled: bool,
+    pub added: Vec<SyncItem>,
+    pub updated: Vec<SyncItem>,
+    pub removed: Vec<SyncItem>,
+    pub conflicts: Vec<SyncConflict>,
+    pub errors: Vec<SyncError>,
+}
+
+fn sync_file() -> Result<PathBuf, String> {
+    Ok(paths::app_config_dir()?.join(SYNC_FILE))
+}
+
+pub fn load() -> Result<SyncConfig, String> {
+    let path = sync_file()?;
+    if !path.exists() {
+        return Ok(SyncConfig::default());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read sync file: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse sync file: {}", e))
+}
+
+fn save(config: &SyncConfig) -> Result<(), String> {
+    let json_content = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize sync file: {}", e))?;
+    write_atomic(sync_file()?, json_content).map_err(|e| format!("Failed to write sync file: {}", e))
+}
+
+/// Point sync at a directory, or turn it off with `None`. Managed items stay
+/// as they are locally.
+pub fn set_source(source: Option<&str>, pull: bool) -> Result<SyncConfig, AppError> {
+    let mut config = load()?;
+
+    match source.map(str::trim).filter(|source| !source.is_empty()) {
+        Some(source) => {
+            let dir = Path::new(source);
+            if !dir.is_absolute() {
+                return Err(AppError::new(ErrorKind::Validation, format!("'{}' is not an absolute path", source)));
+            }
+            if !dir.is_dir() {
+                return Err(AppError::not_found(format!("Directory '{}' does not exist", source)).with_path(dir));
+            }
+            if config.source.as_deref() != Some(source) {
+                // Hashes recorded against another source mean nothing for this one
+                config.managed.clear();
+                config.detached.clear();
+                config.conflicts.clear();
+                config.last_sync = None;
+            }
+            config.source = Some(source.to_string());
+        }
+        None => {
+            config.source = None;
+            config.managed.clear();
+            config.detached.clear();
+            config.conflicts.clear();
+            config.last_sync = None;
+        }
+    }
+    config.pull = pull;
+
+    save(&config)?;
+    Ok(config)
+}
+
+fn hash_text(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+// serde_json keeps object keys sorted, so equal values serialize identically
+fn hash_value(value: &Value) -> String {
+    hash_text(&value.to_string())
+}
+
+// Tokens are left out of the hashes: locally they are vault references, in the
+// source they are missing or plaintext, and setting one isn't a local edit
+fn without_secrets(settings: &Value) -> Value {
+    let mut settings = settings.clone();
+    if let Some(obj) = settings.as_object_mut() {
+        if let Some(env) = obj.get_mut("env").and_then(Value::as_object_mut) {
+            for key in secrets::SECRET_ENV_KEYS {
+                env.remove(*key);
+            }
+            if env.is_empty() {
+                obj.remove("env");
+            }
+        }
+    }
+    settings
+}
+
+fn profile_value(
+    title: &str,
+    settings: &Value,
+    extends: &Option<Vec<String>>,
+    array_strategy: &Option<ArrayStrategy>,
+) -> Value {
+    serde_json::json!({
+        "title": title,
+        "settings": without_secrets(settings),
+        "extends": extends.as_ref().filter(|parents| !parents.is_empty()),
+        "arrayStrategy": array_strategy,
+    })
+}
+
+fn store_hash(store: &ConfigStore) -> String {
+    hash_value(&profile_value(&store.title, &store.settings, &store.extends, &store.array_strategy))
+}
+
+fn source_profile_hash(profile: &BundleProfile) -> String {
+    hash_value(&profile_value(&profile.title, &profile.settings, &profile.extends, &profile.array_strategy))
+}
+
+/// Everything the source directory provides.
+#[derive(Default)]
+struct Source {
+    profiles: BTreeMap<String, BundleProfile>,
+    mcp_servers: BTreeMap<String, Value>,
+    commands: BTreeMap<String, String>,
+    agents: BTreeMap<String, String>,
+}
+
+impl Source {
+    fn read(dir: &Path) -> Result<Source, AppError> {
+        let mut source = Source::default();
+
+        let profiles_dir = dir.join("profiles");
+        if profiles_dir.is_dir() {
+            for path in files_with_extension(&profiles_dir, "json")? {
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|e| AppError::io("Failed to read team profile", &path, e))?;
+                let profile: BundleProfile = serde_json::from_str(&content)
+                    .map_err(|e| AppError::parse("Failed to parse team profile", &path, e))?;
+                source.profiles.insert(profile.id.clone(), profile);
+            }
+        }
+
+        let mcp_path = dir.join("mcp.json");
+        if mcp_path.is_file() {
+            let content = std::fs::read_to_string(&mcp_path)
+                .map_err(|e| AppError::io("Failed to read team MCP servers", &mcp_path, e))?;
+            let value: Value = serde_json::from_str(&content)
+                .map_err(|e| AppError::parse("Failed to parse team MCP servers", &mcp_path, e))?;
+            if let Some(servers) = value.get("mcpServers").and_then(Value::as_object) {
+                source.mcp_servers = servers.iter().map(|(name, config)| (name.clone(), config.clone())).collect();
+            }
+        }
+
+        source.commands = read_markdown(&dir.join("commands"))?;
+        source.agents = read_markdown(&dir.join("agents"))?;
+        Ok(source)
+    }
+
+    fn hashes(&self, kind: SyncItemKind) -> BTreeMap<String, String> {
+        match kind {
+            SyncItemKind::Profile => self
+                .profiles
+                .iter()
+                .map(|(id, profile)| (id.clone(), source_profile_hash(profile)))
+                .collect(),
+            SyncItemKind::Mcp => self
+                .mcp_servers
+                .iter()
+                .map(|(name, config)| (name.clone(), hash_value(config)))
+                .collect(),
+            SyncItemKind::Command => self.commands.iter().map(|(name, content)| (name.clone(), hash_text(content))).collect(),
+            SyncItemKind::Agent => self.agents.iter().map(|(name, content)| (name.clone(), hash_text(content))).collect(),
+        }
+    }
+}
+
+// Same naming as `read_claude_commands`: the file stem is the name
+fn read_markdown(dir: &Path) -> Result<BTreeMap<String, String>, AppError> {
+    let mut files = BTreeMap::new();
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+
+    for path in files_with_extension(dir, "md")? {
+        let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let content = std::fs::read_to_string(&path).map_err(|e| AppError::io("Failed to read file", &path, e))?;
+        files.insert(name.to_string(), content);
+    }
+    Ok(files)
+}
+
+/// Hashes of the local items, read through the same commands the UI uses.
+async fn local_hashes(kind: SyncItemKind) -> Result<BTreeMap<String, String>, AppError> {
+    Ok(match kind {
+        SyncItemKind::Profile => commands::get_stores()
+            .await?
+            .iter()
+            .map(|store| (store.id.clone(), store_hash(store)))
+            .collect(),
+        SyncItemKind::Mcp => commands::get_global_mcp_servers()
+            .await?
+            .into_iter()
+            .map(|(name, server)| (name, hash_value(&server.config)))
+            .collect(),
+        SyncItemKind::Command => commands::read_claude_commands()
+            .await?
+            .into_iter()
+            .map(|file| (file.name, hash_text(&file.content)))
+            .collect(),
+        SyncItemKind::Agent => commands::read_claude_agents()
+            .await?
+            .into_iter()
+            .map(|file| (file.name, hash_text(&file.content)))
+            .collect(),
+    })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Decision {
+    Write,
+    Adopt,
+    Remove,
+    Forget,
+    Conflict(ConflictReason),
+    Nothing,
+}
+
+fn decide(source: Option<&String>, local: Option<&String>, managed: Option<&ManagedItem>) -> Decision {
+    match (source, local, managed) {
+        (Some(_), None, None) => Decision::Write,
+        (Some(_), None, Some(_)) => Decision::Conflict(ConflictReason::DeletedLocally),
+        (Some(source), Some(local), None) if source == local => Decision::Adopt,
+        (Some(_), Some(_), None) => Decision::Conflict(ConflictReason::Unmanaged),
+        (Some(source), Some(local), Some(managed)) => {
+            if *local == managed.local_hash {
+                if *source == managed.source_hash {
+                    Decision::Nothing
+                } else {
+                    Decision::Write
+                }
+            } else if local == source {
+                Decision::Adopt
+            } else {
+                Decision::Conflict(ConflictReason::ModifiedLocally)
+            }
+        }
+        (None, None, Some(_)) => Decision::Forget,
+        (None, Some(local), Some(managed)) if *local == managed.local_hash => Decision::Remove,
+        (None, Some(_), Some(_)) => Decision::Conflict(ConflictReason::RemovedUpstream),
+        (None, _, None) => Decision::Nothing,
+    }
+}
+
+// Write one item from the source and return the hash it has locally afterwards
+async fn write_item(source: &Source, item: &SyncItem) -> Result<String, AppError> {
+    match item.kind {
+        SyncItemKind::Profile => {
+            let profile = source
+                .profiles
+                .get(&item.name)
+                .ok_or_else(|| AppError::not_found(format!("Profile '{}' is not in the team source", item.name)))?
+                .clone();
+            // A reference would pick up whatever secret of that name this machine's vault has
+            if let Some(name) = secrets::ref_names(&profile.settings).first() {
+                return Err(AppError::new(
+                    ErrorKind::Validation,
+                    format!(
+                        "Team profile '{}' references the vault secret '{}', team profiles can't use vault references",
+                        profile.title, name
+                    ),
+                ));
+            }
+            let exists = commands::get_stores().await?.iter().any(|store| store.id == profile.id);
+            let store = if exists {
+                commands::update_config(
+                    profile.id,
+                    profile.title,
+                    profile.settings,
+                    Some(profile.extends.unwrap_or_default()),
+                    profile.array_strategy,
+                )
+                .await?
+            } else {
+                commands::create_config(profile.id, profile.title, profile.settings, profile.extends, profile.array_strategy)
+                    .await?
+            };
+            // Tokens went to the vault, so hash what was actually stored
+            Ok(store_hash(&store))
+        }
+        SyncItemKind::Mcp => {
+            let config = source
+                .mcp_servers
+                .get(&item.name)
+                .ok_or_else(|| AppError::not_found(format!("MCP server '{}' is not in the team source", item.name)))?
+                .clone();
+            let hash = hash_value(&config);
+            commands::update_global_mcp_server(item.name.clone(), config).await?;
+            Ok(hash)
+        }
+        SyncItemKind::Command => {
+            let content = source
+                .commands
+                .get(&item.name)
+                .ok_or_else(|| AppError::not_found(format!("Command '{}' is not in the team source", item.name)))?
+                .clone();
+            let hash = hash_text(&content);
+            commands::write_claude_command(item.name.clone(), content).await?;
+            Ok(hash)
+        }
+        SyncItemKind::Agent => {
+            let content = source
+                .agents
+                .get(&item.name)
+                .ok_or_else(|| AppError::not_found(format!("Agent '{}' is not in the team source", item.name)))?
+                .clone();
+            let hash = hash_text(&content);
+            commands::write_claude_agent(item.name.clone(), content).await?;
+            Ok(hash)
+        }
+    }
+}
+
+// Deleting goes through the regular commands, so it lands in the undo journal
+async fn remove_item(item: &SyncItem) -> Result<(), AppError> {
+    match item.kind {
+        SyncItemKind::Profile => commands::delete_config(item.name.clone()).await,
+        SyncItemKind::Mcp => commands::delete_global_mcp_server(item.name.clone()).await,
+        SyncItemKind::Command => commands::delete_claude_command(item.name.clone()).await,
+        SyncItemKind::Agent => commands::delete_claude_agent(item.name.clone()).await,
+    }
+}
+
+fn source_dir(config: &SyncConfig) -> Result<PathBuf, AppError> {
+    let source = config
+        .source
+        .as_ref()
+        .ok_or_else(|| AppError::new(ErrorKind::Validation, "No team sync source is configured"))?;
+    let dir = PathBuf::from(source);
+    if !dir.is_dir() {
+        return Err(AppError::not_found(format!("Team sync source '{}' does not exist", source)).with_path(&dir));
+    }
+    Ok(dir)
+}
+
+fn git_pull(dir: &Path) -> Result<(), AppError> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["pull", "--ff-only"])
+        .output()
+        .map_err(|e| AppError::io("Failed to run git", dir, e))?;
+
+    if !output.status.success() {
+        return Err(AppError::network(format!(
+            "git pull failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .with_path(dir));
+    }
+    Ok(())
+}
+
+// Profiles before the ones extending them, so their parents exist when written
+fn write_order(source: &Source, items: Vec<SyncItem>) -> Vec<SyncItem> {
+    let (mut profiles, others): (Vec<_>, Vec<_>) = items.into_iter().partition(|item| item.kind == SyncItemKind::Profile);
+
+    let mut ordered = Vec::new();
+    let mut placed: HashSet<String> = HashSet::new();
+    while !profiles.is_empty() {
+        let (ready, waiting): (Vec<_>, Vec<_>) = profiles.into_iter().partition(|item| {
+            source
+                .profiles
+                .get(&item.name)
+                .and_then(|profile| profile.extends.as_ref())
+                .into_iter()
+                .flatten()
+                .all(|parent| placed.contains(parent) || !source.profiles.contains_key(parent))
+        });
+        if ready.is_empty() {
+            // A cycle; writing reports it
+            ordered.extend(waiting);
+            break;
+        }
+        placed.extend(ready.iter().map(|item| item.name.clone()));
+        ordered.extend(ready);
+        profiles = waiting;
+    }
+
+    ordered.extend(others);
+    ordered
+}
+
+/// Bring local profiles, MCP servers, commands and agents in line with the
+/// team source. Local edits are never overwritten, they come back as conflicts.
+pub async fn run() -> Result<SyncReport, AppError> {
+    let mut config = load()?;
+    let dir = source_dir(&config)?;
+    let mut report = SyncReport::default();
+
+    if config.pull && dir.join(".git").exists() {
+        git_pull(&dir)?;
+        report.pulled = true;
+    }
+
+    let source = Source::read(&dir)?;
+    let mut to_write = Vec::new();
+
+    for kind in [SyncItemKind::Profile, SyncItemKind::Mcp, SyncItemKind::Command, SyncItemKind::Agent] {
+        let source_hashes = source.hashes(kind);
+        let local = local_hashes(kind).await?;
+
+        // Nothing left to stay detached from once the source drops an item
+        config
+            .detached
+            .retain(|item| item.kind != kind || source_hashes.contains_key(&item.name));
+
+        let mut names: Vec<String> = source_hashes.keys().cloned().collect();
+        names.extend(
+            config
+                .managed
+                .iter()
+                .filter(|managed| managed.item.kind == kind && !source_hashes.contains_key(&managed.item.name))
+                .map(|managed| managed.item.name.clone()),
+        );
+
+        for name in &names {
+            let item = SyncItem { kind, name: name.clone() };
+            if config.detached.contains(&item) {
+                continue;
+            }
+
+            let source_hash = source_hashes.get(name);
+            let managed_index = config.managed.iter().position(|managed| managed.item == item);
+            let managed = managed_index.map(|index| &config.managed[index]);
+
+            match decide(source_hash, local.get(name), managed) {
+                Decision::Write => to_write.push(item),
+                Decision::Adopt => {
+                    let managed = ManagedItem {
+                        item,
+                        local_hash: local[name].clone(),
+                        source_hash: source_hashes[name].clone(),
+                    };
+                    match managed_index {
+                        Some(index) => config.managed[index] = managed,
+                        None => config.managed.push(managed),
+                    }
+                }
+                Decision::Remove => match remove_item(&item).await {
+                    Ok(()) => {
+                        config.managed.retain(|managed| managed.item != item);
+                        report.removed.push(item);
+                    }
+                    Err(e) => report.errors.push(SyncError { item, message: e.message }),
+                },
+                Decision::Forget => config.managed.retain(|managed| managed.item != item),
+                Decision::Conflict(reason) => {
+                    let source_changed = match (source_hash, managed) {
+                        (Some(source_hash), Some(managed)) => *source_hash != managed.source_hash,
+                        _ => source_hash.is_some(),
+                    };
+                    report.conflicts.push(SyncConflict { item, reason, source_changed });
+                }
+                Decision::Nothing => {}
+            }
+        }
+    }
+
+    for item in write_order(&source, to_write) {
+        let existed = config.managed.iter().any(|managed| managed.item == item);
+        match write_item(&source, &item).await {
+            Ok(local_hash) => {
+                let source_hash = source.hashes(item.kind).remove(&item.name).unwrap_or_default();
+                config.managed.retain(|managed| managed.item != item);
+                config.managed.push(ManagedItem {
+                    item: item.clone(),
+                    local_hash,
+                    source_hash,
+                });
+                if existed {
+                    report.updated.push(item);
+                } else {
+                    report.added.push(item);
+                }
+            }
+            Err(e) => report.errors.push(SyncError { item, message: e.message }),
+        }
+    }
+
+    config.managed.sort_by(|a, b| a.item.cmp(&b.item));
+    config.conflicts = report.conflicts.clone();
+    config.last_sync = Some(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| format!("Failed to get timestamp: {}", e))?
+            .as_secs(),
+    );
+    save(&config)?;
+
+    Ok(report)
+}
+
+/// Settle a conflict from the last run by keeping either side.
+pub async fn resolve(item: SyncItem, resolution: SyncResolution) -> Result<SyncConfig, AppError> {
+    let mut config = load()?;
+    let conflict = config
+        .conflicts
+        .iter()
+        .find(|conflict| conflict.item == item)
+        .cloned()
+        .ok_or_else(|| AppError::not_found(format!("No sync conflict for {:?} '{}'", item.kind, item.name)))?;
+
+    match resolution {
+        SyncResolution::Local => {
+            config.managed.retain(|managed| managed.item != item);
+            // Only items still in the source would conflict again
+            if conflict.reason != ConflictReason::RemovedUpstream && !config.detached.contains(&item) {
+                config.detached.push(item.clone());
+            }
+        }
+        SyncResolution::Team => {
+            let dir = source_dir(&config)?;
+            let source = Source::read(&dir)?;
+            match source.hashes(item.kind).remove(&item.name) {
+                Some(source_hash) => {
+                    let local_hash = write_item(&source, &item).await?;
+                    config.managed.retain(|managed| managed.item != item);
+                    config.managed.push(ManagedItem {
+                        item: item.clone(),
+                        local_hash,
+                        source_hash,
+                    });
+                    config.managed.sort_by(|a, b| a.item.cmp(&b.item));
+                }
+                None => {
+                    remove_item(&item).await?;
+                    config.managed.retain(|managed| managed.item != item);
+                }
+            }
+        }
+    }
+
+    config.conflicts.retain(|conflict| conflict.item != item);
+    save(&config)?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn managed(local_hash: &str, source_hash: &str) -> ManagedItem {
+        ManagedItem {
+            item: SyncItem {
+                kind: SyncItemKind::Command,
+                name: "review".to_string(),
+            },
+            local_hash: local_hash.to_string(),
+            source_hash: source_hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn decide_table() {
+        let hash = |hash: &str| Some(hash.to_string());
+        // (source, local, managed (local, source) hashes, decision)
+        let cases = [
+            (hash("s"), None, None, Decision::Write),
+            (hash("s"), None, Some(("l", "s")), Decision::Conflict(ConflictReason::DeletedLocally)),
+            (hash("s"), hash("s"), None, Decision::Adopt),
+            (hash("s"), hash("l"), None, Decision::Conflict(ConflictReason::Unmanaged)),
+            (hash("s"), hash("l"), Some(("l", "s")), Decision::Nothing),
+            (hash("s2"), hash("l"), Some(("l", "s")), Decision::Write),
+            (hash("s"), hash("s"), Some(("l", "s")), Decision::Adopt),
+            (hash("s"), hash("l2"), Some(("l", "s")), Decision::Conflict(ConflictReason::ModifiedLocally)),
+            (hash("s2"), hash("l2"), Some(("l", "s")), Decision::Conflict(ConflictReason::ModifiedLocally)),
+            (None, None, Some(("l", "s")), Decision::Forget),
+            (None, hash("l"), Some(("l", "s")), Decision::Remove),
+            (None, hash("l2"), Some(("l", "s")), Decision::Conflict(ConflictReason::RemovedUpstream)),
+            (None, hash("l"), None, Decision::Nothing),
+            (None, None, None, Decision::Nothing),
+        ];
+
+        for (source, local, hashes, expected) in cases {
+            let managed = hashes.map(|(local_hash, source_hash)| managed(local_hash, source_hash));
+            assert_eq!(
+                decide(source.as_ref(), local.as_ref(), managed.as_ref()),
+                expected,
+                "source {:?}, local {:?}, managed {:?}",
+                source,
+                local,
+                hashes
+            );
+        }
+    }
+
+    #[test]
+    fn profile_hashes_leave_tokens_out() {
+        let settings = |env: Value| serde_json::json!({ "model": "sonnet", "env": env });
+        let hash = |settings: Value| hash_value(&profile_value("Team", &settings, &None, &None));
+
+        let without_token = hash(settings(serde_json::json!({ "ANTHROPIC_BASE_URL": "https://example.com" })));
+        let with_reference = hash(settings(serde_json::json!({
+            "ANTHROPIC_BASE_URL": "https://example.com",
+            "ANTHROPIC_AUTH_TOKEN": { "$secret": "abc123-anthropic_auth_token" },
+        })));
+        assert_eq!(without_token, with_reference);
+
+        // An env holding nothing but a token is the same as none
+        let no_env = hash(serde_json::json!({ "model": "sonnet" }));
+        assert_eq!(no_env, hash(settings(serde_json::json!({ "ANTHROPIC_API_KEY": "sk-ant-1" }))));
+        assert_ne!(no_env, without_token);
+    }
+}
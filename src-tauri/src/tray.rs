@@ -6,11 +6,20 @@ use tauri::{
 };
 use tauri_plugin_notification::NotificationExt;
 
-use crate::commands::{get_store, get_stores, set_using_config};
+use std::sync::Mutex;
+
+use crate::commands::{check_profile_drift, get_store, get_stores, set_using_config, ProjectUsageRecord};
+use crate::error::{AppError, ErrorKind};
+use crate::undo;
+use crate::usage_query::{self, UsageQuery, UsageTotals};
 
 // Store the tray icon ID globally
 const TRAY_ID: &str = "main-tray";
 
+// Today's usage as of the last index refresh, with the day it is for, so
+// rebuilding the menu doesn't read the whole index
+static USAGE_TODAY: Mutex<Option<(String, UsageTotals)>> = Mutex::new(None);
+
 pub fn create_tray<R: Runtime>(app: &AppHandle<R>) -> Result<(), Box<dyn std::error::Error>> {
     println!("🔧 Creating system tray icon...");
 
@@ -79,6 +88,35 @@ pub fn create_tray<R: Runtime>(app: &AppHandle<R>) -> Result<(), Box<dyn std::er
     Ok(())
 }
 
+/// Remember today's usage for the menu, from the priced records of a fresh
+/// index refresh.
+pub fn update_usage_today(records: &[ProjectUsageRecord]) -> Result<(), AppError> {
+    let today = usage_query::start_of_today();
+    let query = UsageQuery {
+        from: Some(today.clone()),
+        ..Default::default()
+    };
+    let totals = usage_query::run(records, &query)?.total;
+    *USAGE_TODAY.lock().unwrap_or_else(|e| e.into_inner()) = Some((today, totals));
+    Ok(())
+}
+
+// Nothing until the index has been refreshed today
+fn usage_today() -> Option<UsageTotals> {
+    let usage_today = USAGE_TODAY.lock().unwrap_or_else(|e| e.into_inner());
+    let (day, totals) = usage_today.as_ref()?;
+    (*day == usage_query::start_of_today() && totals.requests > 0).then(|| totals.clone())
+}
+
+fn format_tokens(tokens: u64) -> String {
+    match tokens {
+        tokens if tokens >= 1_000_000_000 => format!("{:.1}B", tokens as f64 / 1e9),
+        tokens if tokens >= 1_000_000 => format!("{:.1}M", tokens as f64 / 1e6),
+        tokens if tokens >= 1_000 => format!("{:.1}K", tokens as f64 / 1e3),
+        tokens => tokens.to_string(),
+    }
+}
+
 pub async fn build_tray_menu<R: Runtime>(
     app: &AppHandle<R>,
 ) -> Result<tauri::menu::Menu<R>, Box<dyn std::error::Error>> {
@@ -104,6 +142,15 @@ pub async fn build_tray_menu<R: Runtime>(
             } else {
                 let mut builder = menu_builder;
 
+                // Active profiles whose settings file was changed behind their back
+                let drifted: Vec<String> = match check_profile_drift().await {
+                    Ok(drift) => drift.into_iter().map(|drift| drift.store_id).collect(),
+                    Err(e) => {
+                        eprintln!("Failed to check profile drift for tray menu: {}", e);
+                        vec![]
+                    }
+                };
+
                 // Add "Show Window" item
                 let show_item = MenuItemBuilder::with_id(
                     "show_window",
@@ -121,8 +168,22 @@ pub async fn build_tray_menu<R: Runtime>(
 
                 // Add config items
                 for store in stores {
-                    let prefix = if store.using { "✓ " } else { "  " };
-                    let label = format!("{}{}", prefix, store.title);
+                    let prefix = match (store.using, drifted.contains(&store.id)) {
+                        (true, true) => "⚠ ",
+                        (true, false) => "✓ ",
+                        _ => "  ",
+                    };
+                    let label = match &store.project {
+                        // Project profiles can be active alongside the user-level one
+                        Some(project) => {
+                            let project_name = std::path::Path::new(&project.path)
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or_else(|| project.path.clone());
+                            format!("{}{} ({})", prefix, store.title, project_name)
+                        }
+                        None => format!("{}{}", prefix, store.title),
+                    };
 
                     println!(
                         "  {} Config: {}",
@@ -140,6 +201,30 @@ pub async fn build_tray_menu<R: Runtime>(
                 let separator = tauri::menu::PredefinedMenuItem::separator(app)?;
                 builder = builder.item(&separator);
 
+                // Today's usage as of the last index refresh
+                if let Some(today) = usage_today() {
+                    let label = format!("Today: {} tokens · ${:.2}", format_tokens(today.tokens()), today.cost_usd);
+                    let usage_item = tauri::menu::MenuItem::with_id(app, "usage_today", label, false, None::<&str>)?;
+                    builder = builder.item(&usage_item);
+
+                    let separator = tauri::menu::PredefinedMenuItem::separator(app)?;
+                    builder = builder.item(&separator);
+                }
+
+                // Offer to revert the last destructive change, if there is one
+                match undo::latest() {
+                    Ok(Some(entry)) => {
+                        let undo_item = MenuItemBuilder::with_id("undo_last_change", format!("Undo: {}", entry.action))
+                            .build(app)?;
+                        builder = builder.item(&undo_item);
+
+                        let separator = tauri::menu::PredefinedMenuItem::separator(app)?;
+                        builder = builder.item(&separator);
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Failed to read undo journal for tray menu: {}", e),
+                }
+
                 // Add "Quit" item
                 let quit_item = MenuItemBuilder::with_id(
                     "quit_app",
@@ -207,6 +292,35 @@ pub fn handle_tray_menu_event<R: Runtime>(app_handle: &AppHandle<R>, event_id: &
             app_handle.exit(0);
             true
         }
+        "undo_last_change" => {
+            let app_clone = app_handle.clone();
+
+            tauri::async_runtime::spawn(async move {
+                let notification_body = match undo::undo_last() {
+                    Ok(Some(entry)) => {
+                        println!("✅ Undid: {}", entry.action);
+                        format!("Undone: {}", entry.action)
+                    }
+                    Ok(None) => "Nothing to undo".to_string(),
+                    Err(e) => {
+                        eprintln!("❌ Failed to undo: {}", e);
+                        format!("Error: {}", e)
+                    }
+                };
+
+                if let Err(e) = rebuild_tray_menu(app_clone.clone()).await {
+                    eprintln!("❌ Failed to rebuild tray menu: {}", e);
+                }
+
+                let _ = app_clone
+                    .notification()
+                    .builder()
+                    .title("CC Mate")
+                    .body(&notification_body)
+                    .show();
+            });
+            true
+        }
         id if id.starts_with("config_") => {
             // Extract store ID from the menu item ID and convert to owned String
             let store_id = id.trim_start_matches("config_").to_string();
@@ -250,12 +364,20 @@ pub fn handle_tray_menu_event<R: Runtime>(app_handle: &AppHandle<R>, event_id: &
                     Err(e) => {
                         eprintln!("❌ Failed to switch config: {}", e);
 
+                        // Point at the broken file rather than repeating the parser message
+                        let error_body = match (e.kind, &e.path, e.line) {
+                            (ErrorKind::Parse, Some(path), Some(line)) => {
+                                format!("{} is not valid JSON (line {})", path, line)
+                            }
+                            _ => format!("Error: {}", e),
+                        };
+
                         // Show error notification
                         let _ = app_clone
                             .notification()
                             .builder()
                             .title("CC Mate")
-                            .body(&format!("Error: {}", e))
+                            .body(&error_body)
                             .show();
                     }
                 }
@@ -0,0 +1,311 @@
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::commands::{ConfigStore, StoresData};
+use crate::error::AppError;
+use crate::merge::{escape_pointer_token, parse_pointer, set_pointer};
+use crate::paths;
+use crate::persistence::{update_json_file, write_atomic, write_private};
+
+const JOURNAL_FILE: &str = "undo.json";
+
+// Oldest entries are dropped beyond this
+const MAX_ENTRIES: usize = 20;
+
+/// State of something a destructive command touched, as it was before.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Change {
+    /// A whole file. `contents` is None if it didn't exist; `after` is the
+    /// sha256 it had once the command was done, so undo can tell whether
+    /// something else has changed it since.
+    #[serde(rename_all = "camelCase")]
+    File {
+        path: String,
+        contents: Option<String>,
+        after: Option<String>,
+    },
+    /// A single value inside a JSON file that other programs write too
+    /// (`.claude.json`), restored without touching the rest of the file.
+    #[serde(rename_all = "camelCase")]
+    JsonValue {
+        path: String,
+        pointer: String,
+        value: Option<Value>,
+    },
+    /// A config store removed from `stores.json`.
+    #[serde(rename_all = "camelCase")]
+    Store { store: ConfigStore, index: usize },
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoEntry {
+    pub id: String,
+    pub timestamp: u64,
+    /// Human readable description, e.g. `Delete agent "reviewer"`.
+    pub action: String,
+    pub changes: Vec<Change>,
+}
+
+/// Changes captured before a destructive command runs. Nothing is written to
+/// the journal until `commit`, so failed commands leave no entry behind.
+pub struct Snapshot {
+    action: String,
+    changes: Vec<Change>,
+}
+
+impl Snapshot {
+    pub fn new(action: impl Into<String>) -> Snapshot {
+        Snapshot {
+            action: action.into(),
+            changes: vec![],
+        }
+    }
+
+    pub fn file(mut self, path: &Path) -> Result<Snapshot, AppError> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => Some(contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(AppError::io("Failed to snapshot file for undo", path, e)),
+        };
+
+        self.changes.push(Change::File {
+            path: path.display().to_string(),
+            contents,
+            after: None,
+        });
+        Ok(self)
+    }
+
+    pub fn json_value(mut self, path: &Path, tokens: &[&str]) -> Result<Snapshot, AppError> {
+        let pointer: String = tokens
+            .iter()
+            .map(|token| format!("/{}", escape_pointer_token(token)))
+            .collect();
+
+        let value = match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str::<Value>(&content)
+                .map_err(|e| AppError::parse("Failed to snapshot file for undo", path, e))?
+                .pointer(&pointer)
+                .cloned(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(AppError::io("Failed to snapshot file for undo", path, e)),
+        };
+
+        self.changes.push(Change::JsonValue {
+            path: path.display().to_string(),
+            pointer,
+            value,
+        });
+        Ok(self)
+    }
+
+    pub fn store(mut self, store: ConfigStore, index: usize) -> Snapshot {
+        self.changes.push(Change::Store { store, index });
+        self
+    }
+
+    /// Add the snapshot to the journal once the command has succeeded.
+    pub fn commit(mut self) -> Result<UndoEntry, AppError> {
+        for change in &mut self.changes {
+            if let Change::File { path, after, .. } = change {
+                *after = file_hash(Path::new(path))?;
+            }
+        }
+
+        let entry = UndoEntry {
+            id: nanoid::nanoid!(8),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_err(|e| format!("Failed to get timestamp: {}", e))?
+                .as_secs(),
+            action: self.action,
+            changes: self.changes,
+        };
+
+        let mut entries = load()?;
+        entries.push(entry.clone());
+        if entries.len() > MAX_ENTRIES {
+            entries.drain(..entries.len() - MAX_ENTRIES);
+        }
+        save(&entries)?;
+
+        Ok(entry)
+    }
+}
+
+fn journal_file() -> Result<PathBuf, AppError> {
+    Ok(paths::app_config_dir()?.join(JOURNAL_FILE))
+}
+
+/// Journal entries, oldest first.
+pub fn load() -> Result<Vec<UndoEntry>, AppError> {
+    let path = journal_file()?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::io("Failed to read undo journal", &path, e))?;
+    serde_json::from_str(&content).map_err(|e| AppError::parse("Failed to parse undo journal", &path, e))
+}
+
+// Snapshots hold whole settings files and MCP server env, tokens included, so the
+// journal is as private as the vault
+fn save(entries: &[UndoEntry]) -> Result<(), AppError> {
+    let json_content = serde_json::to_string_pretty(entries)
+        .map_err(|e| format!("Failed to serialize undo journal: {}", e))?;
+    let path = journal_file()?;
+    write_private(&path, json_content).map_err(|e| AppError::io("Failed to write undo journal", &path, e))
+}
+
+pub fn latest() -> Result<Option<UndoEntry>, AppError> {
+    Ok(load()?.pop())
+}
+
+/// Restore the state captured by the most recent entry and drop it from the
+/// journal. Returns None when there is nothing to undo.
+pub fn undo_last() -> Result<Option<UndoEntry>, AppError> {
+    let mut entries = load()?;
+    let Some(entry) = entries.last().cloned() else {
+        return Ok(None);
+    };
+
+    // Check everything first so an undo is never applied halfway
+    for change in &entry.changes {
+        check(change)?;
+    }
+    for change in entry.changes.iter().rev() {
+        apply(change)?;
+    }
+
+    entries.pop();
+    save(&entries)?;
+
+    Ok(Some(entry))
+}
+
+fn file_hash(path: &Path) -> Result<Option<String>, AppError> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(format!("{:x}", Sha256::digest(&bytes)))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(AppError::io("Failed to read file for undo", path, e)),
+    }
+}
+
+// Refuse to undo over changes made after the destructive command
+fn check(change: &Change) -> Result<(), AppError> {
+    match change {
+        Change::File { path, after, .. } => {
+            if file_hash(Path::new(path))? != *after {
+                return Err(AppError::conflict(format!(
+                    "{} has changed since, undoing would overwrite those changes",
+                    path
+                ))
+                .with_path(path));
+            }
+        }
+        Change::JsonValue { path, pointer, value } => {
+            let Some(value) = value else {
+                return Ok(());
+            };
+            let current = match std::fs::read_to_string(path) {
+                Ok(content) => serde_json::from_str::<Value>(&content)
+                    .map_err(|e| AppError::parse("Failed to read file for undo", path, e))?
+                    .pointer(pointer)
+                    .cloned(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(AppError::io("Failed to read file for undo", path, e)),
+            };
+            if current.is_some_and(|current| current != *value) {
+                return Err(AppError::conflict(format!(
+                    "{} in {} has been set again since, undoing would overwrite it",
+                    pointer, path
+                ))
+                .with_path(path));
+            }
+        }
+        Change::Store { store, .. } => {
+            let stores_file = paths::stores_file()?;
+            if let Some(stores_data) = read_stores(&stores_file)? {
+                if stores_data.configs.iter().any(|existing| existing.id == store.id) {
+                    return Err(AppError::conflict(format!(
+                        "A store with id {} exists again, nothing to restore",
+                        store.id
+                    )));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn apply(change: &Change) -> Result<(), AppError> {
+    match change {
+        Change::File { path, contents, .. } => match contents {
+            Some(contents) => {
+                if let Some(parent) = Path::new(path).parent() {
+                    std::fs::create_dir_all(parent)
+                        .map_err(|e| AppError::io("Failed to create directory", parent, e))?;
+                }
+                write_atomic(path, contents).map_err(|e| AppError::io("Failed to restore file", path, e))?;
+            }
+            None => {
+                if Path::new(path).exists() {
+                    std::fs::remove_file(path).map_err(|e| AppError::io("Failed to remove file", path, e))?;
+                }
+            }
+        },
+        Change::JsonValue { path, pointer, value } => {
+            let tokens = parse_pointer(pointer);
+            update_json_file(Path::new(path), |root| {
+                set_pointer(root, &tokens, value.clone());
+                Ok(())
+            })?;
+        }
+        Change::Store { store, index } => {
+            let stores_file = paths::stores_file()?;
+            let mut stores_data = read_stores(&stores_file)?.unwrap_or(StoresData {
+                configs: vec![],
+                distinct_id: None,
+                notification: None,
+            });
+
+            let mut store = store.clone();
+            // Don't end up with two active profiles for the same scope
+            let scope = store.project.as_ref().map(|project| &project.path);
+            if store.using
+                && stores_data
+                    .configs
+                    .iter()
+                    .any(|other| other.using && other.project.as_ref().map(|project| &project.path) == scope)
+            {
+                store.using = false;
+                store.owned_keys = None;
+            }
+
+            let index = (*index).min(stores_data.configs.len());
+            stores_data.configs.insert(index, store);
+
+            let json_content = serde_json::to_string_pretty(&stores_data)
+                .map_err(|e| format!("Failed to serialize stores: {}", e))?;
+            write_atomic(&stores_file, json_content)
+                .map_err(|e| AppError::io("Failed to write stores file", &stores_file, e))?;
+        }
+    }
+    Ok(())
+}
+
+fn read_stores(stores_file: &Path) -> Result<Option<StoresData>, AppError> {
+    if !stores_file.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(stores_file)
+        .map_err(|e| AppError::io("Failed to read stores file", stores_file, e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| AppError::parse("Failed to parse stores file", stores_file, e))
+}
@@ -0,0 +1,818 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::commands::{ProjectUsageRecord, UsageData};
+use crate::error::AppError;
+use crate::paths;
+use crate::persistence::{write_atomic, FileLock};
+
+const INDEX_DIR: &str = "usage";
+const STATE_FILE: &str = "files.json";
+const RECORDS_FILE: &str = "records.jsonl";
+
+// Bump when the record format changes, older indexes are rebuilt from scratch
+const INDEX_VERSION: u32 = 1;
+
+// Failures kept per transcript, the rest are only counted
+const MAX_FAILURES_PER_FILE: usize = 10;
+
+/// How far each transcript has been read.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct IndexState {
+    version: u32,
+    projects_dir: String,
+    files: BTreeMap<String, FileState>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct FileState {
+    /// Bytes consumed, always just after a newline
+    offset: u64,
+    /// Lines consumed, so errors point at the right line
+    lines: usize,
+    len: u64,
+    /// Seconds since the epoch
+    modified: u64,
+    /// Lines that aren't valid JSON
+    skipped: usize,
+    failures: Vec<SkippedLine>,
+}
+
+/// A transcript line that couldn't be parsed and was left out.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SkippedLine {
+    pub path: String,
+    /// 1-based
+    pub line: usize,
+    pub message: String,
+}
+
+// A transcript with lines the index hasn't seen
+struct Pending<'a> {
+    key: String,
+    path: &'a Path,
+    /// What the previous scans found, None for a new file
+    known: Option<FileState>,
+    current: FileState,
+}
+
+// A record with the transcript it was read from
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+struct IndexedRecord {
+    file: String,
+    #[serde(flatten)]
+    record: ProjectUsageRecord,
+}
+
+struct Index {
+    dir: PathBuf,
+    state: IndexState,
+    /// Every copy of a message, one per transcript it is in
+    records: Vec<IndexedRecord>,
+    /// Position of each (transcript, uuid) in `records`
+    positions: HashMap<(String, String), usize>,
+    /// Started over, dropped records or found repeated lines, so records.jsonl
+    /// is replaced instead of appended to
+    rewrite: bool,
+}
+
+/// Every record in the index, with the lines left out because they aren't
+/// valid JSON. `failures` is capped per file, `skipped_lines` counts them all.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct UsageScan {
+    pub records: Vec<ProjectUsageRecord>,
+    pub skipped_lines: usize,
+    pub failures: Vec<SkippedLine>,
+}
+
+/// Bring the index up to date with the transcripts under `projects_dir` and
+/// return every record in it.
+///
+/// Claude Code only ever appends to a transcript, so each file is read from
+/// where the last scan stopped and a trailing line without its newline is left
+/// for the next scan, it isn't an error. A file that got shorter was rewritten
+/// and the index is rebuilt, the records of a deleted one are dropped.
+///
+/// Records are deduplicated by `uuid`: resumed sessions copy earlier messages
+/// into the new transcript. The earliest copy is kept, on a tie the one of the
+/// first transcript by path, so the result doesn't depend on the order the
+/// transcripts were read in.
+pub fn refresh(projects_dir: &Path) -> Result<UsageScan, AppError> {
+    refresh_at(&paths::app_config_dir()?.join(INDEX_DIR), projects_dir)
+}
+
+fn refresh_at(dir: &Path, projects_dir: &Path) -> Result<UsageScan, AppError> {
+    let dir = dir.to_path_buf();
+    std::fs::create_dir_all(&dir).map_err(|e| AppError::io("Failed to create usage index directory", &dir, e))?;
+
+    // The app and the CLI may both be scanning
+    let state_path = dir.join(STATE_FILE);
+    let _lock = FileLock::acquire(&state_path).map_err(|e| AppError::io("Failed to lock usage index", &state_path, e))?;
+
+    let mut index = Index::load(dir, projects_dir)?;
+    let files = find_jsonl_files(projects_dir)?;
+
+    // Transcripts Claude Code cleaned up take their records with them
+    let keys: HashSet<String> = files.iter().map(|path| path.display().to_string()).collect();
+    let deleted: Vec<String> = index
+        .state
+        .files
+        .keys()
+        .filter(|key| !keys.contains(*key))
+        .cloned()
+        .collect();
+    if !deleted.is_empty() {
+        index.remove_files(&deleted);
+    }
+
+    let mut stale = false;
+    let mut pending = vec![];
+    for path in &files {
+        let key = path.display().to_string();
+        let Some(current) = file_state(path) else {
+            continue;
+        };
+        match index.state.files.get(&key) {
+            Some(known) if current.len < known.offset => {
+                stale = true;
+                break;
+            }
+            Some(known) if current.len == known.offset && current.modified == known.modified => {}
+            known => pending.push(Pending {
+                key,
+                path,
+                known: known.cloned(),
+                current,
+            }),
+        }
+    }
+
+    if stale {
+        eprintln!("Usage index is out of date, rebuilding it");
+        index = Index::empty(index.dir, projects_dir);
+        pending = files
+            .iter()
+            .filter_map(|path| {
+                file_state(path).map(|current| Pending {
+                    key: path.display().to_string(),
+                    path,
+                    known: None,
+                    current,
+                })
+            })
+            .collect();
+    }
+
+    if pending.is_empty() && !index.rewrite {
+        return Ok(index.scan(&files));
+    }
+
+    let mut added = vec![];
+    let mut lines_processed = 0;
+    for Pending {
+        key,
+        path,
+        known,
+        current,
+    } in pending
+    {
+        let mut state = known.unwrap_or(FileState {
+            offset: 0,
+            lines: 0,
+            ..current.clone()
+        });
+        let new_lines = read_new_lines(path, state.offset)?;
+        lines_processed += new_lines.lines.len();
+
+        for (line_index, line) in new_lines.lines {
+            let json_value: Value = match serde_json::from_str(&line) {
+                Ok(json_value) => json_value,
+                Err(e) => {
+                    state.skipped += 1;
+                    if state.failures.len() < MAX_FAILURES_PER_FILE {
+                        state.failures.push(SkippedLine {
+                            path: key.clone(),
+                            line: state.lines + line_index,
+                            message: e.to_string(),
+                        });
+                    }
+                    continue;
+                }
+            };
+            if let Some(record) = parse_record(&json_value, path) {
+                added.push(IndexedRecord {
+                    file: key.clone(),
+                    record,
+                });
+            }
+        }
+
+        state.offset += new_lines.bytes;
+        state.lines += new_lines.count;
+        state.len = current.len;
+        state.modified = current.modified;
+        index.state.files.insert(key, state);
+    }
+
+    eprintln!(
+        "📊 Usage index: read {} new lines, found {} new records",
+        lines_processed,
+        added.len()
+    );
+
+    index.save(added)?;
+    Ok(index.scan(&files))
+}
+
+// Which copy of a message is kept, see `refresh`. Timestamps are compared as
+// times, their fractional seconds vary in length.
+fn precedes(a: &IndexedRecord, b: &IndexedRecord) -> bool {
+    let key = |indexed: &IndexedRecord| {
+        let time = chrono::DateTime::parse_from_rfc3339(&indexed.record.timestamp).ok();
+        (time.is_none(), time, indexed.file.clone())
+    };
+    key(a) < key(b)
+}
+
+impl Index {
+    fn empty(dir: PathBuf, projects_dir: &Path) -> Index {
+        Index {
+            dir,
+            state: IndexState {
+                version: INDEX_VERSION,
+                projects_dir: projects_dir.display().to_string(),
+                files: BTreeMap::new(),
+            },
+            records: vec![],
+            positions: HashMap::new(),
+            rewrite: true,
+        }
+    }
+
+    // A transcript read again after an interrupted scan repeats its records,
+    // the later line replaces the earlier one
+    fn insert(&mut self, indexed: IndexedRecord) -> bool {
+        let key = (indexed.file.clone(), indexed.record.uuid.clone());
+        match self.positions.get(&key) {
+            Some(&position) => {
+                self.records[position] = indexed;
+                false
+            }
+            None => {
+                self.positions.insert(key, self.records.len());
+                self.records.push(indexed);
+                true
+            }
+        }
+    }
+
+    fn remove_files(&mut self, files: &[String]) {
+        for file in files {
+            self.state.files.remove(file);
+        }
+        self.records.retain(|indexed| !files.contains(&indexed.file));
+        self.positions = self
+            .records
+            .iter()
+            .enumerate()
+            .map(|(position, indexed)| ((indexed.file.clone(), indexed.record.uuid.clone()), position))
+            .collect();
+        self.rewrite = true;
+    }
+
+    // Anything unreadable, from another index version or for another Claude
+    // home starts over
+    fn load(dir: PathBuf, projects_dir: &Path) -> Result<Index, AppError> {
+        let state_path = dir.join(STATE_FILE);
+        let state = match std::fs::read_to_string(&state_path) {
+            Ok(content) => serde_json::from_str::<IndexState>(&content).ok(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(AppError::io("Failed to read usage index", &state_path, e)),
+        };
+        let Some(state) = state.filter(|state| {
+            state.version == INDEX_VERSION && state.projects_dir == projects_dir.display().to_string()
+        }) else {
+            return Ok(Index::empty(dir, projects_dir));
+        };
+
+        let records_path = dir.join(RECORDS_FILE);
+        let file = match File::open(&records_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Index::empty(dir, projects_dir)),
+            Err(e) => return Err(AppError::io("Failed to read usage index", &records_path, e)),
+        };
+
+        let mut index = Index {
+            state,
+            rewrite: false,
+            ..Index::empty(dir, projects_dir)
+        };
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| AppError::io("Failed to read usage index", &records_path, e))?;
+            // A half written line from an interrupted scan, its file is read again
+            let Ok(indexed) = serde_json::from_str::<IndexedRecord>(&line) else {
+                index.rewrite = true;
+                continue;
+            };
+            // Repeated lines are compacted away on the next save
+            if !index.insert(indexed) {
+                index.rewrite = true;
+            }
+        }
+        Ok(index)
+    }
+
+    // One copy of each message. Failures of transcripts that have been deleted
+    // since are left out.
+    fn scan(self, files: &[PathBuf]) -> UsageScan {
+        let mut skipped_lines = 0;
+        let mut failures = vec![];
+        for path in files {
+            if let Some(state) = self.state.files.get(&path.display().to_string()) {
+                skipped_lines += state.skipped;
+                failures.extend(state.failures.iter().cloned());
+            }
+        }
+
+        // Owner of each uuid, listed in the order the uuids were first seen
+        let mut owners: HashMap<&str, usize> = HashMap::new();
+        let mut uuids = vec![];
+        for (position, indexed) in self.records.iter().enumerate() {
+            match owners.get_mut(indexed.record.uuid.as_str()) {
+                Some(owner) => {
+                    if precedes(indexed, &self.records[*owner]) {
+                        *owner = position;
+                    }
+                }
+                None => {
+                    owners.insert(&indexed.record.uuid, position);
+                    uuids.push(indexed.record.uuid.as_str());
+                }
+            }
+        }
+        let kept: Vec<usize> = uuids.iter().map(|uuid| owners[uuid]).collect();
+
+        let mut records: Vec<Option<IndexedRecord>> = self.records.into_iter().map(Some).collect();
+        UsageScan {
+            records: kept
+                .into_iter()
+                .filter_map(|position| records[position].take())
+                .map(|indexed| indexed.record)
+                .collect(),
+            skipped_lines,
+            failures,
+        }
+    }
+
+    // Add the new records and write them. Records first: if we stop in between,
+    // the files are read again and the repeated records replace the earlier
+    // copies when loading.
+    fn save(&mut self, added: Vec<IndexedRecord>) -> Result<(), AppError> {
+        let records_path = self.dir.join(RECORDS_FILE);
+
+        if self.rewrite {
+            for indexed in added {
+                self.insert(indexed);
+            }
+            write_atomic(&records_path, record_lines(&self.records)?)
+                .map_err(|e| AppError::io("Failed to write usage index", &records_path, e))?;
+        } else {
+            let mut content = record_lines(&added)?;
+            for indexed in added {
+                self.insert(indexed);
+            }
+
+            let mut file = OpenOptions::new()
+                .create(true)
+                .read(true)
+                .append(true)
+                .open(&records_path)
+                .map_err(|e| AppError::io("Failed to open usage index", &records_path, e))?;
+            // Don't glue the first record onto a half written line
+            let mut last = [b'\n'];
+            if file.seek(SeekFrom::End(-1)).is_ok() && file.read_exact(&mut last).is_ok() && last[0] != b'\n' {
+                content.insert(0, '\n');
+            }
+            file.write_all(content.as_bytes())
+                .map_err(|e| AppError::io("Failed to write usage index", &records_path, e))?;
+        }
+        self.rewrite = false;
+
+        let state_path = self.dir.join(STATE_FILE);
+        let json_content = serde_json::to_string(&self.state)
+            .map_err(|e| format!("Failed to serialize usage index: {}", e))?;
+        write_atomic(&state_path, json_content).map_err(|e| AppError::io("Failed to write usage index", &state_path, e))
+    }
+}
+
+fn record_lines(records: &[IndexedRecord]) -> Result<String, String> {
+    let mut content = String::new();
+    for indexed in records {
+        let line = serde_json::to_string(indexed).map_err(|e| format!("Failed to serialize usage record: {}", e))?;
+        content.push_str(&line);
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+fn file_state(path: &Path) -> Option<FileState> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    Some(FileState {
+        offset: 0,
+        lines: 0,
+        len: metadata.len(),
+        modified,
+        skipped: 0,
+        failures: vec![],
+    })
+}
+
+// Complete lines after an offset
+struct NewLines {
+    bytes: u64,
+    count: usize,
+    /// Non-empty lines with their 1-based line numbers counted from the offset
+    lines: Vec<(usize, String)>,
+}
+
+fn read_new_lines(path: &Path, offset: u64) -> Result<NewLines, AppError> {
+    let mut file = File::open(path).map_err(|e| AppError::io("Failed to read file", path, e))?;
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| AppError::io("Failed to read file", path, e))?;
+
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes)
+        .map_err(|e| AppError::io("Failed to read file", path, e))?;
+
+    let Some(end) = bytes.iter().rposition(|byte| *byte == b'\n') else {
+        return Ok(NewLines {
+            bytes: 0,
+            count: 0,
+            lines: vec![],
+        });
+    };
+    bytes.truncate(end + 1);
+
+    let content = String::from_utf8_lossy(&bytes);
+    let lines: Vec<(usize, String)> = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_index, line)| (line_index + 1, line.to_string()))
+        .collect();
+    Ok(NewLines {
+        bytes: bytes.len() as u64,
+        count: bytes.iter().filter(|byte| **byte == b'\n').count(),
+        lines,
+    })
+}
+
+// Recursively find all .jsonl files in the projects directory and subdirectories
+fn find_jsonl_files(dir: &Path) -> Result<Vec<PathBuf>, AppError> {
+    fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), AppError> {
+        let entries = std::fs::read_dir(dir).map_err(|e| AppError::io("Failed to read directory", dir, e))?;
+
+        for entry in entries {
+            let path = entry.map_err(|e| AppError::io("Failed to read directory entry", dir, e))?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "jsonl") {
+                files.push(path);
+            } else if path.is_dir() {
+                if let Err(e) = walk(&path, files) {
+                    eprintln!("Warning: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = vec![];
+    walk(dir, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+/// The usage record of a transcript line, if it is an assistant message that used tokens.
+fn parse_record(json_value: &Value, path: &Path) -> Option<ProjectUsageRecord> {
+    let uuid = json_value.get("uuid").and_then(Value::as_str).unwrap_or("");
+    let timestamp = json_value.get("timestamp").and_then(Value::as_str).unwrap_or("");
+    if uuid.is_empty() || timestamp.is_empty() {
+        return None;
+    }
+
+    // Both fields can be at the top level or nested in the message field
+    let message = json_value.get("message");
+    let model = json_value
+        .get("model")
+        .or_else(|| message.and_then(|message| message.get("model")))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let usage_obj = json_value
+        .get("usage")
+        .or_else(|| message.and_then(|message| message.get("usage")))?;
+
+    let usage = parse_usage(usage_obj);
+
+    // Only include if input_tokens + output_tokens > 0
+    if usage.input_tokens.unwrap_or(0) + usage.output_tokens.unwrap_or(0) == 0 {
+        return None;
+    }
+
+    Some(ProjectUsageRecord {
+        uuid: uuid.to_string(),
+        timestamp: timestamp.to_string(),
+        model,
+        usage: Some(usage),
+        project_path: project_path(json_value, path),
+        // Transcripts are named after their session
+        session_id: json_value
+            .get("sessionId")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .or_else(|| path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string)),
+        cost_usd: None,
+    })
+}
+
+// Every field of the usage object. A provider that sends a known field with an
+// unexpected type still gets the other fields read, that one lands in `extra`.
+fn parse_usage(usage_obj: &Value) -> UsageData {
+    if let Ok(usage) = serde_json::from_value::<UsageData>(usage_obj.clone()) {
+        return usage;
+    }
+
+    let mut extra = usage_obj.as_object().cloned().unwrap_or_default();
+    UsageData {
+        input_tokens: take_field(&mut extra, "input_tokens"),
+        cache_creation_input_tokens: take_field(&mut extra, "cache_creation_input_tokens"),
+        cache_read_input_tokens: take_field(&mut extra, "cache_read_input_tokens"),
+        output_tokens: take_field(&mut extra, "output_tokens"),
+        cache_creation: take_field(&mut extra, "cache_creation"),
+        service_tier: take_field(&mut extra, "service_tier"),
+        server_tool_use: take_field(&mut extra, "server_tool_use"),
+        extra,
+    }
+}
+
+// The field if it has the expected type, otherwise it stays where it is
+fn take_field<T: DeserializeOwned>(fields: &mut serde_json::Map<String, Value>, key: &str) -> Option<T> {
+    let value = serde_json::from_value(fields.get(key)?.clone()).ok()?;
+    fields.remove(key);
+    Some(value)
+}
+
+// The session's `cwd`, or for lines without one the project directory name
+// (`-Users-me-code-app`), which is the path with separators replaced
+fn project_path(json_value: &Value, path: &Path) -> Option<String> {
+    if let Some(cwd) = json_value.get("cwd").and_then(Value::as_str) {
+        return Some(cwd.to_string());
+    }
+    path.parent()?.file_name()?.to_str().map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Dirs {
+        root: PathBuf,
+        index: PathBuf,
+        projects: PathBuf,
+    }
+
+    impl Dirs {
+        fn new() -> Dirs {
+            let root = std::env::temp_dir().join(format!("ccmate-usage-{}", nanoid::nanoid!()));
+            let projects = root.join("projects");
+            std::fs::create_dir_all(projects.join("-code-app")).unwrap();
+            Dirs {
+                index: root.join("index"),
+                projects,
+                root,
+            }
+        }
+
+        fn transcript(&self, name: &str) -> PathBuf {
+            self.projects.join("-code-app").join(name)
+        }
+
+        fn refresh(&self) -> UsageScan {
+            refresh_at(&self.index, &self.projects).unwrap()
+        }
+
+        fn indexed_uuids(&self) -> Vec<String> {
+            std::fs::read_to_string(self.index.join(RECORDS_FILE))
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<IndexedRecord>(line).unwrap().record.uuid)
+                .collect()
+        }
+    }
+
+    impl Drop for Dirs {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn line(uuid: &str, input_tokens: u64) -> String {
+        line_at(uuid, input_tokens, "2025-01-31T12:00:00Z")
+    }
+
+    fn line_at(uuid: &str, input_tokens: u64, timestamp: &str) -> String {
+        format!(
+            r#"{{"uuid":"{}","timestamp":"{}","cwd":"/code/app","message":{{"model":"claude-sonnet-4","usage":{{"input_tokens":{},"output_tokens":1}}}}}}"#,
+            uuid, timestamp, input_tokens
+        )
+    }
+
+    fn append(path: &Path, content: &str) {
+        let mut file = OpenOptions::new().create(true).append(true).open(path).unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+    }
+
+    fn input_tokens(scan: &UsageScan) -> Vec<(String, u64)> {
+        scan.records
+            .iter()
+            .map(|record| (record.uuid.clone(), record.usage.as_ref().unwrap().input_tokens.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_only_what_was_appended() {
+        let dirs = Dirs::new();
+        let transcript = dirs.transcript("session.jsonl");
+        append(&transcript, &format!("{}\n", line("a", 10)));
+        assert_eq!(input_tokens(&dirs.refresh()), vec![("a".to_string(), 10)]);
+
+        append(&transcript, &format!("{}\n", line("b", 20)));
+        let scan = dirs.refresh();
+        assert_eq!(input_tokens(&scan), vec![("a".to_string(), 10), ("b".to_string(), 20)]);
+        assert_eq!(scan.records[0].session_id.as_deref(), Some("session"));
+        assert_eq!(dirs.indexed_uuids(), vec!["a", "b"]);
+
+        let state = Index::load(dirs.index.clone(), &dirs.projects).unwrap().state;
+        let file = &state.files[&transcript.display().to_string()];
+        assert_eq!(file.offset, std::fs::metadata(&transcript).unwrap().len());
+        assert_eq!(file.lines, 2);
+    }
+
+    #[test]
+    fn leaves_a_partial_line_for_the_next_scan() {
+        let dirs = Dirs::new();
+        let transcript = dirs.transcript("session.jsonl");
+        let complete = line("b", 20);
+        let (start, end) = complete.split_at(30);
+        append(&transcript, &format!("{}\n{}", line("a", 10), start));
+
+        let scan = dirs.refresh();
+        assert_eq!(input_tokens(&scan), vec![("a".to_string(), 10)]);
+        assert_eq!(scan.skipped_lines, 0);
+
+        append(&transcript, &format!("{}\n", end));
+        let scan = dirs.refresh();
+        assert_eq!(input_tokens(&scan), vec![("a".to_string(), 10), ("b".to_string(), 20)]);
+        assert_eq!(scan.skipped_lines, 0);
+    }
+
+    #[test]
+    fn rebuilds_when_a_transcript_shrinks() {
+        let dirs = Dirs::new();
+        let transcript = dirs.transcript("session.jsonl");
+        append(&transcript, &format!("{}\n{}\n", line("a", 10), line("b", 20)));
+        dirs.refresh();
+
+        std::fs::write(&transcript, format!("{}\n", line("c", 30))).unwrap();
+        assert_eq!(input_tokens(&dirs.refresh()), vec![("c".to_string(), 30)]);
+        assert_eq!(dirs.indexed_uuids(), vec!["c"]);
+    }
+
+    #[test]
+    fn replaces_the_records_of_another_claude_home() {
+        let dirs = Dirs::new();
+        append(&dirs.transcript("session.jsonl"), &format!("{}\n", line("a", 10)));
+        dirs.refresh();
+
+        let other = dirs.root.join("other-projects");
+        std::fs::create_dir_all(other.join("-code-web")).unwrap();
+        append(&other.join("-code-web").join("session.jsonl"), &format!("{}\n", line("b", 20)));
+        let scan = refresh_at(&dirs.index, &other).unwrap();
+        assert_eq!(input_tokens(&scan), vec![("b".to_string(), 20)]);
+        assert_eq!(dirs.indexed_uuids(), vec!["b"]);
+    }
+
+    #[test]
+    fn replaces_the_records_of_another_index_version() {
+        let dirs = Dirs::new();
+        append(&dirs.transcript("session.jsonl"), &format!("{}\n", line("a", 10)));
+        dirs.refresh();
+
+        let state_path = dirs.index.join(STATE_FILE);
+        let mut state: Value = serde_json::from_str(&std::fs::read_to_string(&state_path).unwrap()).unwrap();
+        state["version"] = Value::from(INDEX_VERSION - 1);
+        std::fs::write(&state_path, state.to_string()).unwrap();
+        let old = serde_json::json!({"uuid": "old", "timestamp": "", "model": null, "usage": null});
+        append(&dirs.index.join(RECORDS_FILE), &format!("{}\n", old));
+
+        assert_eq!(input_tokens(&dirs.refresh()), vec![("a".to_string(), 10)]);
+        assert_eq!(dirs.indexed_uuids(), vec!["a"]);
+    }
+
+    #[test]
+    fn keeps_the_same_copy_whatever_the_read_order() {
+        // A resumed session copies the message into its own transcript
+        for (first, second) in [("a.jsonl", "b.jsonl"), ("b.jsonl", "a.jsonl")] {
+            let dirs = Dirs::new();
+            let tokens = |name: &str| if name == "a.jsonl" { 10 } else { 20 };
+            append(&dirs.transcript(first), &format!("{}\n", line("m", tokens(first))));
+            dirs.refresh();
+            append(&dirs.transcript(second), &format!("{}\n", line("m", tokens(second))));
+
+            let scan = dirs.refresh();
+            assert_eq!(input_tokens(&scan), vec![("m".to_string(), 10)]);
+            assert_eq!(scan.records[0].session_id.as_deref(), Some("a"));
+        }
+    }
+
+    #[test]
+    fn keeps_the_earliest_copy() {
+        let dirs = Dirs::new();
+        append(&dirs.transcript("a.jsonl"), &format!("{}\n", line_at("m", 10, "2025-01-31T12:00:00Z")));
+        append(&dirs.transcript("b.jsonl"), &format!("{}\n", line_at("m", 20, "2025-01-31T11:59:59.5Z")));
+        assert_eq!(input_tokens(&dirs.refresh()), vec![("m".to_string(), 20)]);
+    }
+
+    #[test]
+    fn drops_the_records_of_deleted_transcripts() {
+        let dirs = Dirs::new();
+        append(&dirs.transcript("a.jsonl"), &format!("{}\n{}\n", line("m", 10), line("n", 30)));
+        append(&dirs.transcript("b.jsonl"), &format!("{}\n", line("m", 20)));
+        assert_eq!(input_tokens(&dirs.refresh()), vec![("m".to_string(), 10), ("n".to_string(), 30)]);
+
+        // The copy in the remaining transcript takes over
+        std::fs::remove_file(dirs.transcript("a.jsonl")).unwrap();
+        assert_eq!(input_tokens(&dirs.refresh()), vec![("m".to_string(), 20)]);
+        assert_eq!(dirs.indexed_uuids(), vec!["m"]);
+
+        let state = Index::load(dirs.index.clone(), &dirs.projects).unwrap().state;
+        assert_eq!(state.files.len(), 1);
+    }
+
+    #[test]
+    fn compacts_repeated_records() {
+        let dirs = Dirs::new();
+        append(&dirs.transcript("session.jsonl"), &format!("{}\n", line("a", 10)));
+        dirs.refresh();
+
+        // What an interrupted scan leaves behind: the record written twice and a torn line
+        let records_path = dirs.index.join(RECORDS_FILE);
+        let record = std::fs::read_to_string(&records_path).unwrap();
+        append(&records_path, &format!("{}{{\"file\":", record));
+        assert_eq!(input_tokens(&dirs.refresh()), vec![("a".to_string(), 10)]);
+        assert_eq!(dirs.indexed_uuids(), vec!["a"]);
+    }
+
+    #[test]
+    fn reports_bad_lines_by_line_number() {
+        let dirs = Dirs::new();
+        let transcript = dirs.transcript("session.jsonl");
+        append(&transcript, &format!("{}\nnot json\n\n", line("a", 10)));
+        dirs.refresh();
+
+        append(&transcript, "{bad\n");
+        let scan = dirs.refresh();
+        assert_eq!(scan.skipped_lines, 2);
+        let lines: Vec<usize> = scan.failures.iter().map(|failure| failure.line).collect();
+        assert_eq!(lines, vec![2, 4]);
+    }
+
+    #[test]
+    fn keeps_the_usage_fields_that_parse() {
+        let usage = parse_usage(&serde_json::json!({
+            "input_tokens": 10,
+            "output_tokens": "5",
+            "cache_creation": {"ephemeral_5m_input_tokens": 3, "ephemeral_1h_input_tokens": 4},
+            "service_tier": "standard",
+            "server_tool_use": {"web_search_requests": 2},
+        }));
+        assert_eq!(usage.input_tokens, Some(10));
+        assert_eq!(usage.output_tokens, None);
+        assert_eq!(usage.cache_creation.unwrap().ephemeral_1h_input_tokens, Some(4));
+        assert_eq!(usage.service_tier.as_deref(), Some("standard"));
+        assert_eq!(usage.server_tool_use.unwrap().web_search_requests, Some(2));
+        assert_eq!(usage.extra, serde_json::json!({"output_tokens": "5"}).as_object().cloned().unwrap());
+    }
+}
@@ -0,0 +1,422 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone};
+use std::collections::BTreeMap;
+
+use crate::commands::ProjectUsageRecord;
+use crate::error::AppError;
+use crate::usage_index::SkippedLine;
+
+/// What usage is grouped by. At most one of the time buckets, which are in local time.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageDimension {
+    Hour,
+    Day,
+    /// Weeks starting on Monday
+    Week,
+    Month,
+    Model,
+    Project,
+    Session,
+}
+
+impl UsageDimension {
+    fn is_time(self) -> bool {
+        matches!(
+            self,
+            UsageDimension::Hour | UsageDimension::Day | UsageDimension::Week | UsageDimension::Month
+        )
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct UsageQuery {
+    /// Inclusive, RFC 3339 or a local date (`2025-01-31`)
+    #[serde(default)]
+    pub from: Option<String>,
+    /// Exclusive, same formats as `from`
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub group_by: Vec<UsageDimension>,
+    /// Only these models, projects or sessions; empty means all
+    #[serde(default)]
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub projects: Vec<String>,
+    #[serde(default)]
+    pub sessions: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    pub web_search_requests: u64,
+    /// Of the requests that have a price
+    pub cost_usd: f64,
+    /// Requests left out of `cost_usd` because their model has no price
+    pub unpriced_requests: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, record: &ProjectUsageRecord) {
+        self.requests += 1;
+        if let Some(usage) = &record.usage {
+            self.input_tokens += usage.input_tokens.unwrap_or(0);
+            self.output_tokens += usage.output_tokens.unwrap_or(0);
+            self.cache_creation_input_tokens += usage.cache_creation_input_tokens.unwrap_or(0);
+            self.cache_read_input_tokens += usage.cache_read_input_tokens.unwrap_or(0);
+            self.web_search_requests += usage
+                .server_tool_use
+                .as_ref()
+                .and_then(|server_tool_use| server_tool_use.web_search_requests)
+                .unwrap_or(0);
+        }
+        match record.cost_usd {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced_requests += 1,
+        }
+    }
+
+    pub fn tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens
+    }
+}
+
+/// Totals for one combination of the `group_by` values; dimensions that
+/// weren't asked for are None.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct UsageGroup {
+    /// Start of the time bucket: `2025-01-31T14:00`, `2025-01-31` (day and
+    /// week) or `2025-01`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub totals: UsageTotals,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct UsageQueryResult {
+    pub total: UsageTotals,
+    /// Sorted by period, then model, project and session
+    pub groups: Vec<UsageGroup>,
+    /// Transcript lines left out because they aren't valid JSON, as in `UsageScan`
+    #[serde(default)]
+    pub skipped_lines: usize,
+    #[serde(default)]
+    pub failures: Vec<SkippedLine>,
+}
+
+type GroupKey = (Option<String>, Option<String>, Option<String>, Option<String>);
+
+/// Filter and aggregate usage records. Records without a parseable timestamp
+/// only count when no time range or time bucket is asked for.
+pub fn run(records: &[ProjectUsageRecord], query: &UsageQuery) -> Result<UsageQueryResult, AppError> {
+    let time_dimensions: Vec<UsageDimension> = query.group_by.iter().copied().filter(|dimension| dimension.is_time()).collect();
+    if time_dimensions.len() > 1 {
+        return Err(AppError::validation("Usage can only be grouped by one time period at a time", vec![]));
+    }
+    let time_dimension = time_dimensions.first().copied();
+
+    let from = query.from.as_deref().map(parse_bound).transpose()?;
+    let to = query.to.as_deref().map(parse_bound).transpose()?;
+    let needs_time = from.is_some() || to.is_some() || time_dimension.is_some();
+
+    let mut total = UsageTotals::default();
+    let mut groups: BTreeMap<GroupKey, UsageTotals> = BTreeMap::new();
+
+    for record in records {
+        if !matches_filter(&query.models, record.model.as_deref())
+            || !matches_filter(&query.projects, record.project_path.as_deref())
+            || !matches_filter(&query.sessions, record.session_id.as_deref())
+        {
+            continue;
+        }
+
+        let timestamp = DateTime::parse_from_rfc3339(&record.timestamp)
+            .ok()
+            .map(|timestamp| timestamp.with_timezone(&Local));
+        if needs_time {
+            let Some(timestamp) = timestamp else {
+                continue;
+            };
+            if from.is_some_and(|from| timestamp < from) || to.is_some_and(|to| timestamp >= to) {
+                continue;
+            }
+        }
+
+        let grouped = |dimension: UsageDimension, value: Option<&String>| {
+            query
+                .group_by
+                .contains(&dimension)
+                .then(|| value.cloned().unwrap_or_else(|| "unknown".to_string()))
+        };
+        let key = (
+            time_dimension.zip(timestamp).map(|(dimension, timestamp)| period(dimension, timestamp)),
+            grouped(UsageDimension::Model, record.model.as_ref()),
+            grouped(UsageDimension::Project, record.project_path.as_ref()),
+            grouped(UsageDimension::Session, record.session_id.as_ref()),
+        );
+
+        total.add(record);
+        groups.entry(key).or_default().add(record);
+    }
+
+    Ok(UsageQueryResult {
+        total,
+        groups: groups
+            .into_iter()
+            .map(|((period, model, project_path, session_id), totals)| UsageGroup {
+                period,
+                model,
+                project_path,
+                session_id,
+                totals,
+            })
+            .collect(),
+        skipped_lines: 0,
+        failures: vec![],
+    })
+}
+
+/// Start of today in local time, for "today so far" totals.
+pub fn start_of_today() -> String {
+    Local::now().format("%Y-%m-%d").to_string()
+}
+
+fn matches_filter(filter: &[String], value: Option<&str>) -> bool {
+    filter.is_empty() || value.is_some_and(|value| filter.iter().any(|allowed| allowed == value))
+}
+
+fn parse_bound(value: &str) -> Result<DateTime<Local>, AppError> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Local));
+    }
+
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+        .ok_or_else(|| {
+            AppError::validation(
+                format!("Invalid time '{}', use a date like 2025-01-31 or an RFC 3339 timestamp", value),
+                vec![],
+            )
+        })
+}
+
+fn period(dimension: UsageDimension, timestamp: DateTime<Local>) -> String {
+    match dimension {
+        UsageDimension::Hour => timestamp.format("%Y-%m-%dT%H:00").to_string(),
+        UsageDimension::Week => {
+            let monday = timestamp.date_naive() - Duration::days(timestamp.weekday().num_days_from_monday() as i64);
+            monday.format("%Y-%m-%d").to_string()
+        }
+        UsageDimension::Month => timestamp.format("%Y-%m").to_string(),
+        _ => timestamp.format("%Y-%m-%d").to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::UsageData;
+
+    // Timestamps are given in local time, so buckets don't depend on the machine's time zone
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> String {
+        Local
+            .with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+            .to_rfc3339()
+    }
+
+    fn record(timestamp: &str, model: &str, project: Option<&str>, session: &str) -> ProjectUsageRecord {
+        ProjectUsageRecord {
+            uuid: nanoid::nanoid!(),
+            timestamp: timestamp.to_string(),
+            model: Some(model.to_string()),
+            usage: Some(UsageData {
+                input_tokens: Some(10),
+                output_tokens: Some(1),
+                ..Default::default()
+            }),
+            project_path: project.map(str::to_string),
+            session_id: Some(session.to_string()),
+            cost_usd: None,
+        }
+    }
+
+    fn query(group_by: &[UsageDimension]) -> UsageQuery {
+        UsageQuery {
+            group_by: group_by.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn periods(result: &UsageQueryResult) -> Vec<(String, u64)> {
+        result
+            .groups
+            .iter()
+            .map(|group| (group.period.clone().unwrap(), group.totals.requests))
+            .collect()
+    }
+
+    #[test]
+    fn filters_by_model_project_and_session() {
+        let timestamp = at(2025, 1, 27, 12, 0);
+        let records = [
+            record(&timestamp, "sonnet", Some("/code/app"), "s1"),
+            record(&timestamp, "opus", Some("/code/app"), "s1"),
+            record(&timestamp, "sonnet", Some("/code/web"), "s2"),
+            record(&timestamp, "sonnet", None, "s3"),
+        ];
+
+        let models = UsageQuery {
+            models: vec!["sonnet".to_string()],
+            ..query(&[UsageDimension::Project])
+        };
+        let result = run(&records, &models).unwrap();
+        assert_eq!(result.total.requests, 3);
+        let projects: Vec<(Option<String>, u64)> = result
+            .groups
+            .iter()
+            .map(|group| (group.project_path.clone(), group.totals.requests))
+            .collect();
+        assert_eq!(
+            projects,
+            vec![
+                (Some("/code/app".to_string()), 1),
+                (Some("/code/web".to_string()), 1),
+                (Some("unknown".to_string()), 1),
+            ]
+        );
+
+        // A record without a project never matches a project filter
+        let projects = UsageQuery {
+            projects: vec!["/code/app".to_string(), "unknown".to_string()],
+            ..query(&[])
+        };
+        assert_eq!(run(&records, &projects).unwrap().total.requests, 2);
+
+        let sessions = UsageQuery {
+            models: vec!["sonnet".to_string()],
+            sessions: vec!["s1".to_string(), "s2".to_string()],
+            ..query(&[])
+        };
+        let result = run(&records, &sessions).unwrap();
+        assert_eq!(result.total.requests, 2);
+        assert_eq!(result.total.input_tokens, 20);
+        assert_eq!(result.total.unpriced_requests, 2);
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        let records = [
+            // Sunday night and Monday morning
+            record(&at(2025, 1, 26, 23, 30), "sonnet", None, "s"),
+            record(&at(2025, 1, 27, 0, 0), "sonnet", None, "s"),
+            record(&at(2025, 2, 2, 23, 59), "sonnet", None, "s"),
+        ];
+        let result = run(&records, &query(&[UsageDimension::Week])).unwrap();
+        assert_eq!(periods(&result), vec![("2025-01-20".to_string(), 1), ("2025-01-27".to_string(), 2)]);
+    }
+
+    #[test]
+    fn buckets_are_in_local_time() {
+        let records = [
+            record(&at(2025, 1, 27, 14, 59), "sonnet", None, "s"),
+            record(&at(2025, 1, 27, 15, 0), "sonnet", None, "s"),
+            record(&at(2025, 1, 31, 23, 59), "sonnet", None, "s"),
+            record(&at(2025, 2, 1, 0, 0), "sonnet", None, "s"),
+        ];
+
+        let hours = run(&records, &query(&[UsageDimension::Hour])).unwrap();
+        assert_eq!(
+            periods(&hours),
+            vec![
+                ("2025-01-27T14:00".to_string(), 1),
+                ("2025-01-27T15:00".to_string(), 1),
+                ("2025-01-31T23:00".to_string(), 1),
+                ("2025-02-01T00:00".to_string(), 1),
+            ]
+        );
+
+        let days = run(&records, &query(&[UsageDimension::Day])).unwrap();
+        assert_eq!(
+            periods(&days),
+            vec![
+                ("2025-01-27".to_string(), 2),
+                ("2025-01-31".to_string(), 1),
+                ("2025-02-01".to_string(), 1),
+            ]
+        );
+
+        let months = run(&records, &query(&[UsageDimension::Month])).unwrap();
+        assert_eq!(periods(&months), vec![("2025-01".to_string(), 3), ("2025-02".to_string(), 1)]);
+    }
+
+    #[test]
+    fn from_is_inclusive_and_to_exclusive() {
+        let records = [
+            record(&at(2025, 1, 26, 23, 59), "sonnet", None, "s"),
+            record(&at(2025, 1, 27, 0, 0), "sonnet", None, "s"),
+            record(&at(2025, 1, 27, 23, 59), "sonnet", None, "s"),
+            record(&at(2025, 1, 28, 0, 0), "sonnet", None, "s"),
+        ];
+
+        let dates = UsageQuery {
+            from: Some("2025-01-27".to_string()),
+            to: Some("2025-01-28".to_string()),
+            ..query(&[])
+        };
+        assert_eq!(run(&records, &dates).unwrap().total.requests, 2);
+
+        let timestamps = UsageQuery {
+            from: Some(at(2025, 1, 26, 23, 59)),
+            to: Some(at(2025, 1, 27, 23, 59)),
+            ..query(&[])
+        };
+        assert_eq!(run(&records, &timestamps).unwrap().total.requests, 2);
+    }
+
+    #[test]
+    fn unparseable_timestamps_only_count_without_time() {
+        let records = [
+            record(&at(2025, 1, 27, 12, 0), "sonnet", None, "s"),
+            record("yesterday", "sonnet", None, "s"),
+            record("", "opus", None, "s"),
+        ];
+
+        let result = run(&records, &query(&[UsageDimension::Model])).unwrap();
+        assert_eq!(result.total.requests, 3);
+
+        let result = run(&records, &query(&[UsageDimension::Day])).unwrap();
+        assert_eq!(periods(&result), vec![("2025-01-27".to_string(), 1)]);
+        assert_eq!(result.total.requests, 1);
+
+        let since = UsageQuery {
+            from: Some("2025-01-01".to_string()),
+            ..query(&[])
+        };
+        assert_eq!(run(&records, &since).unwrap().total.requests, 1);
+    }
+
+    #[test]
+    fn rejects_two_time_buckets_and_bad_bounds() {
+        assert!(run(&[], &query(&[UsageDimension::Day, UsageDimension::Week])).is_err());
+
+        let bad = UsageQuery {
+            to: Some("31/01/2025".to_string()),
+            ..query(&[])
+        };
+        assert!(run(&[], &bad).is_err());
+    }
+}
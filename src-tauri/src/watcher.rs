@@ -0,0 +1,216 @@
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::paths;
+
+// Editors and Claude Code tend to write a file several times in a row
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+// The running watcher; replaced when the watched paths change (e.g. another Claude home)
+static WATCHER: Mutex<Option<Debouncer<RecommendedWatcher>>> = Mutex::new(None);
+
+/// Something on disk that the UI shows has changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigEvent {
+    Settings,
+    Mcp,
+    Memory,
+    Commands,
+    Agents,
+    Stores,
+    Undo,
+}
+
+impl ConfigEvent {
+    /// Name of the Tauri event emitted to the frontend.
+    pub fn name(self) -> &'static str {
+        match self {
+            ConfigEvent::Settings => "settings-changed",
+            ConfigEvent::Mcp => "mcp-changed",
+            ConfigEvent::Memory => "memory-changed",
+            ConfigEvent::Commands => "commands-changed",
+            ConfigEvent::Agents => "agents-changed",
+            ConfigEvent::Stores => "stores-changed",
+            ConfigEvent::Undo => "undo-changed",
+        }
+    }
+
+    // What the tray menu shows: profiles with their checkmarks and the undo item
+    fn affects_tray(self) -> bool {
+        matches!(self, ConfigEvent::Settings | ConfigEvent::Stores | ConfigEvent::Undo)
+    }
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ChangePayload {
+    pub paths: Vec<String>,
+}
+
+/// Paths resolved when the watcher starts.
+struct Watched {
+    claude_dir: PathBuf,
+    claude_json: PathBuf,
+    stores_file: PathBuf,
+    undo_file: PathBuf,
+    // Settings files of profiles pinned to a project
+    project_settings: Vec<PathBuf>,
+}
+
+impl Watched {
+    fn resolve() -> Result<Watched, String> {
+        let app_config_dir = paths::app_config_dir()?;
+        Ok(Watched {
+            claude_dir: paths::claude_dir()?,
+            claude_json: paths::claude_json_path()?,
+            stores_file: paths::stores_file()?,
+            undo_file: app_config_dir.join("undo.json"),
+            project_settings: project_settings(),
+        })
+    }
+
+    fn classify(&self, path: &Path) -> Option<ConfigEvent> {
+        let file_name = path.file_name()?.to_str()?;
+        // Temp files of atomic writes, the rename that follows is what counts
+        if file_name.starts_with('.') && file_name.ends_with(".tmp") {
+            return None;
+        }
+
+        if path == self.claude_json {
+            return Some(ConfigEvent::Mcp);
+        }
+        if path == self.stores_file {
+            return Some(ConfigEvent::Stores);
+        }
+        if path == self.undo_file {
+            return Some(ConfigEvent::Undo);
+        }
+        if self.project_settings.iter().any(|settings| settings == path) {
+            return Some(ConfigEvent::Settings);
+        }
+        if path.starts_with(self.claude_dir.join("commands")) {
+            return Some(ConfigEvent::Commands);
+        }
+        if path.starts_with(self.claude_dir.join("agents")) {
+            return Some(ConfigEvent::Agents);
+        }
+        if path.parent() == Some(self.claude_dir.as_path()) {
+            return match file_name {
+                "settings.json" | "settings.local.json" => Some(ConfigEvent::Settings),
+                "CLAUDE.md" => Some(ConfigEvent::Memory),
+                _ => None,
+            };
+        }
+        None
+    }
+
+    // Directories to watch. Files are watched through their directory so
+    // atomic replaces (write temp file, rename) are seen.
+    fn targets(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        let mut targets = vec![(self.claude_dir.clone(), RecursiveMode::NonRecursive)];
+        for dir in ["commands", "agents"] {
+            targets.push((self.claude_dir.join(dir), RecursiveMode::Recursive));
+        }
+        for file in [&self.claude_json, &self.stores_file].into_iter().chain(&self.project_settings) {
+            if let Some(parent) = file.parent() {
+                targets.push((parent.to_path_buf(), RecursiveMode::NonRecursive));
+            }
+        }
+        targets.sort();
+        targets.dedup_by(|a, b| a.0 == b.0);
+        targets
+    }
+}
+
+// Broken stores only cost the project settings their watch
+fn project_settings() -> Vec<PathBuf> {
+    crate::commands::project_settings_paths().unwrap_or_else(|e| {
+        eprintln!("Failed to find project settings to watch: {}", e);
+        vec![]
+    })
+}
+
+/// Start watching everything CC Mate manages, replacing a running watcher.
+/// Changes are emitted as `settings-changed`, `mcp-changed`, ... events and
+/// rebuild the tray menu when they affect it.
+pub fn start<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    let watched = Watched::resolve()?;
+    let targets = watched.targets();
+    let app_handle = app.clone();
+
+    let mut debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| match result {
+        Ok(events) => handle_events(&app_handle, &watched, events.iter().map(|event| event.path.as_path())),
+        Err(e) => eprintln!("File watcher error: {}", e),
+    })
+    .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+    for (path, mode) in &targets {
+        // Missing directories are picked up once they are created, see handle_events
+        if !path.is_dir() {
+            continue;
+        }
+        if let Err(e) = debouncer.watcher().watch(path, *mode) {
+            eprintln!("Failed to watch {}: {}", path.display(), e);
+        }
+    }
+
+    *WATCHER
+        .lock()
+        .map_err(|e| format!("Failed to access file watcher: {}", e))? = Some(debouncer);
+    println!("👀 Watching {} directories for changes", targets.len());
+    Ok(())
+}
+
+fn handle_events<'a, R: Runtime>(app: &AppHandle<R>, watched: &Watched, paths: impl Iterator<Item = &'a Path>) {
+    let mut changed: BTreeMap<ConfigEvent, Vec<String>> = BTreeMap::new();
+    let mut restart = false;
+
+    for path in paths {
+        // A commands or agents directory that didn't exist yet needs a watch of its own
+        if path.parent() == Some(watched.claude_dir.as_path())
+            && (path.ends_with("commands") || path.ends_with("agents"))
+            && path.is_dir()
+        {
+            restart = true;
+        }
+
+        if let Some(event) = watched.classify(path) {
+            changed.entry(event).or_default().push(path.display().to_string());
+        }
+    }
+
+    // A profile was pinned to another project, or activating one created its .claude directory
+    if changed.contains_key(&ConfigEvent::Stores) && project_settings() != watched.project_settings {
+        restart = true;
+    }
+
+    let rebuild_tray = changed.keys().any(|event| event.affects_tray());
+    for (event, paths) in changed {
+        if let Err(e) = app.emit(event.name(), ChangePayload { paths }) {
+            eprintln!("Failed to emit {}: {}", event.name(), e);
+        }
+    }
+
+    if rebuild_tray {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = crate::tray::rebuild_tray_menu(app).await {
+                eprintln!("Failed to rebuild tray menu: {}", e);
+            }
+        });
+    }
+
+    if restart {
+        // Not from inside the watcher's own callback
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = start(&app) {
+                eprintln!("Failed to restart file watcher: {}", e);
+            }
+        });
+    }
+}
@@ -1,7 +1,7 @@
 import dayjs from 'dayjs';
 import { useMemo } from "react";
 import { useTranslation } from "react-i18next";
-import { type ProjectUsageRecord } from "@/lib/query";
+import { type UsageGroup } from "@/lib/query";
 import {
 	Tooltip,
 	TooltipContent,
@@ -10,8 +10,8 @@ import {
 } from "@/components/ui/tooltip";
 
 interface ActivityGridProps {
-	data: ProjectUsageRecord[];
-	onFilteredDataChange?: (data: ProjectUsageRecord[]) => void;
+	// Usage grouped by day
+	data: UsageGroup[];
 }
 
 interface DayData {
@@ -19,7 +19,6 @@ interface DayData {
 	dateKey: string;
 	totalTokens: number;
 	count: number;
-	records: ProjectUsageRecord[];
 }
 
 interface ActivityLevel {
@@ -49,31 +48,20 @@ export function ActivityGrid({ data }: ActivityGridProps) {
 				date,
 				dateKey,
 				totalTokens: 0,
-				count: 0,
-				records: []
+				count: 0
 			});
 
 			currentDate = currentDate.add(1, 'day');
 		}
 
 		// Process usage data
-		data.forEach(record => {
-			try {
-				const recordDate = dayjs(record.timestamp);
-				const dateKey = recordDate.format('YYYY-MM-DD');
-
-				if (dateMap.has(dateKey)) {
-					const existing = dateMap.get(dateKey)!;
-					const tokens = (record.usage?.input_tokens || 0) +
-											 (record.usage?.output_tokens || 0) +
-											 (record.usage?.cache_read_input_tokens || 0);
-
-					existing.totalTokens += tokens;
-					existing.count += 1;
-					existing.records.push(record);
-				}
-			} catch (error) {
-				console.warn('Invalid date in record:', record.timestamp);
+		data.forEach(group => {
+			const existing = group.period && dateMap.get(group.period);
+			if (existing) {
+				existing.totalTokens += group.totals.input_tokens +
+					group.totals.output_tokens +
+					group.totals.cache_read_input_tokens;
+				existing.count += group.totals.requests;
 			}
 		});
 
@@ -127,7 +115,6 @@ export function ActivityGrid({ data }: ActivityGridProps) {
 					date: currentDate.toDate(),
 					totalTokens: 0,
 					count: 0,
-					records: [],
 					dateKey
 				});
 
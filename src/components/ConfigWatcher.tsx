@@ -0,0 +1,70 @@
+/**
+ * Refreshes cached queries when the backend's file watcher reports that a
+ * config file was changed outside the app (Claude Code, an editor, git).
+ */
+
+import { useQueryClient } from "@tanstack/react-query";
+import { useEffect } from "react";
+
+// Backend event name -> queries showing that file
+const EVENT_QUERIES: Record<string, string[][]> = {
+	"settings-changed": [
+		["config-file"],
+		["config-files"],
+		["current-store"],
+		["validate-settings"],
+		["profile-drift"],
+		["config-diff"],
+	],
+	"mcp-changed": [["global-mcp-servers"], ["claude-config-file"]],
+	"memory-changed": [["claude-memory"]],
+	"commands-changed": [["claude-commands"]],
+	"agents-changed": [["claude-agents"]],
+	"stores-changed": [
+		["stores"],
+		["store"],
+		["current-store"],
+		["profile-drift"],
+		["config-diff"],
+	],
+	"undo-changed": [["undo"]],
+};
+
+export function ConfigWatcher({ children }: { children: React.ReactNode }) {
+	const queryClient = useQueryClient();
+
+	useEffect(() => {
+		// Only the Tauri build has a watcher
+		if (!("__TAURI_INTERNALS__" in window)) {
+			return;
+		}
+
+		let unlisteners: (() => void)[] = [];
+		let cancelled = false;
+
+		import("@tauri-apps/api/event").then(({ listen }) =>
+			Promise.all(
+				Object.entries(EVENT_QUERIES).map(([event, queryKeys]) =>
+					listen(event, () => {
+						for (const queryKey of queryKeys) {
+							queryClient.invalidateQueries({ queryKey });
+						}
+					}),
+				),
+			).then((fns) => {
+				if (cancelled) {
+					fns.forEach((unlisten) => unlisten());
+				} else {
+					unlisteners = fns;
+				}
+			}),
+		);
+
+		return () => {
+			cancelled = true;
+			unlisteners.forEach((unlisten) => unlisten());
+		};
+	}, [queryClient]);
+
+	return <>{children}</>;
+}
@@ -2,7 +2,7 @@ import { CircleQuestionMarkIcon } from "lucide-react";
 import { useState } from "react";
 import { useTranslation } from "react-i18next";
 import { cn } from "@/lib/utils";
-import { GLMDialog } from "./GLMDialog";
+import { ProviderPresetDialog } from "./ProviderPresetDialog";
 import { Button } from "./ui/button";
 import {
 	Tooltip,
@@ -11,8 +11,6 @@ import {
 	TooltipTrigger,
 } from "./ui/tooltip";
 
-export { GLMDialog } from "./GLMDialog";
-
 export function GLMBanner(props: {
 	className?: string;
 	hideCloseButton?: boolean;
@@ -53,7 +51,8 @@ export function GLMBanner(props: {
 				</TooltipProvider>
 			</h3>
 			<div className="flex items-center gap-1">
-				<GLMDialog
+				<ProviderPresetDialog
+					presetIds={["zhipu-glm", "zai-glm"]}
 					trigger={
 						<Button size="sm" variant="outline" className="text-sm">
 							{t("glm.startConfig")}
@@ -0,0 +1,197 @@
+import { openUrl } from "@/lib/utools-dialog";
+import { ExternalLinkIcon } from "lucide-react";
+import { useState } from "react";
+import { useTranslation } from "react-i18next";
+import {
+	useCreateConfigFromPreset,
+	useProviderPresets,
+	useSetCurrentConfig,
+} from "@/lib/query";
+import { Button } from "./ui/button";
+import {
+	Dialog,
+	DialogContent,
+	DialogDescription,
+	DialogHeader,
+	DialogTitle,
+	DialogTrigger,
+} from "./ui/dialog";
+import { Input } from "./ui/input";
+import { Label } from "./ui/label";
+import { NativeSelect, NativeSelectOption } from "./ui/native-select";
+
+// Creates a config from one of the provider presets (built-in or the user's
+// own) and switches to it. Providers are data, so adding one needs no UI code.
+export function ProviderPresetDialog(props: {
+	trigger: React.ReactNode;
+	// Only offer these presets, e.g. the GLM ones
+	presetIds?: string[];
+	onSuccess?: () => void;
+}) {
+	const { t } = useTranslation();
+	const [isOpen, setIsOpen] = useState(false);
+	const [selectedId, setSelectedId] = useState<string>();
+	const [title, setTitle] = useState("");
+	const [secrets, setSecrets] = useState<Record<string, string>>({});
+	const { data: allPresets = [], isError } = useProviderPresets();
+	const createConfigMutation = useCreateConfigFromPreset();
+	const setCurrentConfigMutation = useSetCurrentConfig();
+
+	const presets = props.presetIds
+		? allPresets.filter((preset) => props.presetIds?.includes(preset.id))
+		: allPresets;
+	const preset =
+		presets.find((option) => option.id === selectedId) ?? presets[0];
+	const isComplete =
+		!!preset &&
+		preset.secrets.every((field) => secrets[field.env]?.trim());
+
+	const reset = () => {
+		setSelectedId(undefined);
+		setTitle("");
+		setSecrets({});
+	};
+
+	const handleCreateConfig = async () => {
+		if (!preset || !isComplete) {
+			return;
+		}
+
+		try {
+			const store = await createConfigMutation.mutateAsync({
+				presetId: preset.id,
+				title: title.trim() || undefined,
+				secrets,
+			});
+
+			// Set the newly created config as the current/active config
+			await setCurrentConfigMutation.mutateAsync(store.id);
+
+			setIsOpen(false);
+			reset();
+
+			props.onSuccess?.();
+		} catch (error) {
+			console.error(`Failed to create ${preset.name} config:`, error);
+		}
+	};
+
+	return (
+		<Dialog
+			open={isOpen}
+			onOpenChange={(open) => {
+				setIsOpen(open);
+				if (!open) {
+					reset();
+				}
+			}}
+		>
+			<DialogTrigger asChild>{props.trigger}</DialogTrigger>
+			<DialogContent>
+				<DialogHeader>
+					<DialogTitle>{t("providerPreset.title")}</DialogTitle>
+					<DialogDescription>
+						{t("providerPreset.description")}
+					</DialogDescription>
+				</DialogHeader>
+				{isError && (
+					<p className="text-sm text-destructive">
+						{t("providerPreset.loadError")}
+					</p>
+				)}
+				{preset && (
+					<div className="mt-2 space-y-4">
+						<div className="space-y-2">
+							<Label>{t("providerPreset.provider")}</Label>
+							<NativeSelect
+								value={preset.id}
+								onChange={(e) => {
+									setSelectedId(e.target.value);
+									setSecrets({});
+								}}
+								className="w-full"
+							>
+								{presets.map((option) => (
+									<NativeSelectOption key={option.id} value={option.id}>
+										{option.name}
+									</NativeSelectOption>
+								))}
+							</NativeSelect>
+							{preset.description && (
+								<p className="text-muted-foreground text-sm">
+									{preset.description}
+								</p>
+							)}
+							{(preset.docsUrl || preset.apiKeyUrl) && (
+								<div className="flex items-center gap-2">
+									{preset.docsUrl && (
+										<Button
+											onClick={() => preset.docsUrl && openUrl(preset.docsUrl)}
+											size="sm"
+											variant="outline"
+											className="text-sm"
+										>
+											<ExternalLinkIcon />
+											{t("providerPreset.openDocs")}
+										</Button>
+									)}
+									{preset.apiKeyUrl && (
+										<Button
+											onClick={() => preset.apiKeyUrl && openUrl(preset.apiKeyUrl)}
+											size="sm"
+											variant="outline"
+											className="text-sm"
+										>
+											<ExternalLinkIcon />
+											{t("providerPreset.getApiKey")}
+										</Button>
+									)}
+								</div>
+							)}
+						</div>
+
+						{preset.secrets.map((field) => (
+							<div key={field.env} className="space-y-2">
+								<Label htmlFor={`preset-secret-${field.env}`}>
+									{field.label}
+								</Label>
+								<Input
+									id={`preset-secret-${field.env}`}
+									type="password"
+									value={secrets[field.env] ?? ""}
+									onChange={(e) =>
+										setSecrets({ ...secrets, [field.env]: e.target.value })
+									}
+									placeholder={field.placeholder ?? field.env}
+								/>
+							</div>
+						))}
+
+						<div className="space-y-2">
+							<Label htmlFor="preset-title">
+								{t("providerPreset.configTitle")}
+							</Label>
+							<Input
+								id="preset-title"
+								value={title}
+								onChange={(e) => setTitle(e.target.value)}
+								placeholder={preset.name}
+							/>
+						</div>
+
+						<div className="flex justify-end">
+							<Button
+								onClick={handleCreateConfig}
+								disabled={!isComplete || createConfigMutation.isPending}
+							>
+								{createConfigMutation.isPending
+									? t("providerPreset.creating")
+									: t("providerPreset.createConfig")}
+							</Button>
+						</div>
+					</div>
+				)}
+			</DialogContent>
+		</Dialog>
+	);
+}
@@ -1,5 +1,5 @@
 import dayjs from 'dayjs';
-import { useEffect, useMemo, useState } from "react";
+import { useState } from "react";
 import { useTranslation } from "react-i18next";
 import { AreaChart } from "@/components/ui/area-chart";
 import {
@@ -9,99 +9,66 @@ import {
 	SelectTrigger,
 	SelectValue,
 } from "@/components/ui/select";
-import type { ProjectUsageRecord } from "@/lib/query";
+import type { UsageGroup, UsageQuery } from "@/lib/query";
 import { formatLargeNumber } from "@/lib/utils";
 
+export type TimeRange = "5h" | "today" | "7d" | "week" | "month" | "all";
+
+// The usage query behind a time range: where it starts and the bucket size
+export function timeRangeQuery(
+	timeRange: TimeRange,
+	now: dayjs.Dayjs,
+): Pick<UsageQuery, "from" | "group_by"> {
+	switch (timeRange) {
+		case "5h":
+			return { from: now.subtract(5, 'hour').format(), group_by: ["hour"] };
+		case "today":
+			return { from: now.format("YYYY-MM-DD"), group_by: ["hour"] };
+		case "7d":
+			return {
+				from: now.subtract(6, 'day').format("YYYY-MM-DD"),
+				group_by: ["day"],
+			};
+		case "week":
+			// Sunday (start of week)
+			return { from: now.day(0).format("YYYY-MM-DD"), group_by: ["day"] };
+		case "month":
+			return { from: now.startOf('month').format("YYYY-MM-DD"), group_by: ["day"] };
+		case "all":
+			// Weeks start on Monday
+			return { group_by: ["week"] };
+	}
+}
+
 interface TokenUsageChartProps {
-	data: ProjectUsageRecord[];
-	onFilteredDataChange?: (filteredData: ProjectUsageRecord[]) => void;
+	// Usage grouped by the bucket of timeRangeQuery
+	groups: UsageGroup[];
+	models: string[];
+	selectedModel: string;
+	onModelChange: (model: string) => void;
+	timeRange: TimeRange;
+	onTimeRangeChange: (timeRange: TimeRange) => void;
+	now: dayjs.Dayjs;
 }
 
-type TimeRange = "5h" | "today" | "7d" | "week" | "month" | "all";
+const HOUR_FORMAT = "YYYY-MM-DDTHH:00";
+const DAY_FORMAT = "YYYY-MM-DD";
 
 export function TokenUsageChart({
-	data,
-	onFilteredDataChange,
+	groups,
+	models,
+	selectedModel,
+	onModelChange,
+	timeRange,
+	onTimeRangeChange,
+	now,
 }: TokenUsageChartProps) {
 	const { t } = useTranslation();
-	const [selectedModel, setSelectedModel] = useState<string>("all");
-	const [timeRange, setTimeRange] = useState<TimeRange>("5h");
 	const [activeCategories, setActiveCategories] = useState<string[]>([
 		"Input Tokens",
 		"Output Tokens",
 	]);
 
-	// Get unique models from data
-	const availableModels = useMemo(() => {
-		const models = new Set<string>();
-		data.forEach((record) => {
-			if (record.model) {
-				models.add(record.model);
-			}
-		});
-		return Array.from(models).sort();
-	}, [data]);
-
-	// Filter data based on selected model and time range
-	const filteredData = useMemo(() => {
-		let filtered = data;
-
-		// Filter by model
-		if (selectedModel !== "all") {
-			filtered = filtered.filter((record) => record.model === selectedModel);
-		}
-
-		// Filter by time range
-		const now = dayjs();
-		let startTime: dayjs.Dayjs;
-
-		switch (timeRange) {
-			case "5h":
-				startTime = now.subtract(5, 'hour');
-				break;
-			case "today":
-				startTime = now.startOf('day');
-				break;
-			case "7d":
-				startTime = now.subtract(6, 'day');
-				break;
-			case "week":
-				startTime = now.day(0); // Sunday (start of week)
-				break;
-			case "month":
-				startTime = now.startOf('month');
-				break;
-			case "all":
-				// For "all time", find the earliest timestamp in the data
-				if (filtered.length > 0) {
-					const earliestTime = dayjs(
-						Math.min(
-							...filtered.map((record) => dayjs(record.timestamp).valueOf()),
-						),
-					);
-					startTime = earliestTime;
-				} else {
-					startTime = dayjs(0);
-				}
-				break;
-			default:
-				startTime = now.subtract(5, 'hour');
-		}
-
-		filtered = filtered.filter(
-			(record) => dayjs(record.timestamp).isAfter(startTime.subtract(1, 'millisecond')),
-		);
-
-		return filtered;
-	}, [data, selectedModel, timeRange]);
-
-	// Notify parent component when filtered data changes
-	useEffect(() => {
-		if (onFilteredDataChange) {
-			onFilteredDataChange(filteredData);
-		}
-	}, [filteredData, onFilteredDataChange]);
-
 	// Handle category toggling
 	const handleCategoryToggle = (value: any) => {
 		if (value && value.categoryClicked) {
@@ -119,175 +86,77 @@ export function TokenUsageChart({
 	const toggleCategory = (category: string) => {
 		handleCategoryToggle({ categoryClicked: category });
 	};
-	// Group data based on time range
-	const groupDataByInterval = (records: ProjectUsageRecord[]) => {
-		const intervals: {
-			[key: string]: { input: number; output: number; cache: number };
-		} = {};
-		const now = dayjs();
-
-		if (timeRange === "all") {
-			// For all time, group by week
-			const earliestTime =
-				records.length > 0
-					? dayjs(
-							Math.min(
-								...records.map((record) =>
-									dayjs(record.timestamp).valueOf(),
-								),
-							),
-						)
-					: dayjs();
 
-			// Get start of the week for earliest time
-			let currentWeekStart = earliestTime.day(0); // Sunday
-			const nowWeekStart = now.day(0); // Sunday
+	// Every bucket of the time range, so quiet periods show as zero
+	const bucketStarts = () => {
+		const starts: dayjs.Dayjs[] = [];
 
-			// Generate weekly intervals from earliest week to current week
-			while (currentWeekStart.isBefore(nowWeekStart) || currentWeekStart.isSame(nowWeekStart)) {
-				intervals[currentWeekStart.valueOf()] = {
-					input: 0,
-					output: 0,
-					cache: 0,
-				};
-				// Move to next week (7 days)
+		if (timeRange === "all") {
+			// Monday of the week, as the backend groups them
+			const weekStart = (date: dayjs.Dayjs) =>
+				date.startOf('day').subtract((date.day() + 6) % 7, 'day');
+			const periods = groups
+				.map((group) => group.period)
+				.filter((period): period is string => !!period);
+			let currentWeekStart = weekStart(
+				periods.length > 0 ? dayjs(periods[0]) : now,
+			);
+			const nowWeekStart = weekStart(now);
+			while (!currentWeekStart.isAfter(nowWeekStart)) {
+				starts.push(currentWeekStart);
 				currentWeekStart = currentWeekStart.add(1, 'week');
 			}
-
-			// Group records into weekly intervals
-			records.forEach((record) => {
-				const recordTime = dayjs(record.timestamp);
-				const weekStart = recordTime.day(0); // Sunday
-				const weekKey = weekStart.valueOf();
-
-				if (intervals[weekKey]) {
-					intervals[weekKey].input += record.usage?.input_tokens || 0;
-					intervals[weekKey].output += record.usage?.output_tokens || 0;
-					intervals[weekKey].cache +=
-						record.usage?.cache_read_input_tokens || 0;
-				}
-			});
-		} else if (timeRange === "5h") {
-			// Group by 30-minute intervals for 5h time range
-			const intervalMs = 30 * 60 * 1000; // 30 minutes in milliseconds
-
-			// Round current time down to nearest 30-minute boundary (epoch-based)
-			const currentIntervalKey =
-				Math.floor(now.valueOf() / intervalMs) * intervalMs;
-
-			// Generate intervals (10 intervals for 5 hours)
-			for (let i = 0; i < 10; i++) {
-				const intervalKey = currentIntervalKey - i * intervalMs;
-				intervals[intervalKey] = { input: 0, output: 0, cache: 0 };
-			}
-
-			// Group records into 30-minute intervals
-			records.forEach((record) => {
-				const recordTime = dayjs(record.timestamp);
-				const recordIntervalKey =
-					Math.floor(recordTime.valueOf() / intervalMs) * intervalMs;
-
-				if (intervals[recordIntervalKey]) {
-					intervals[recordIntervalKey].input += record.usage?.input_tokens || 0;
-					intervals[recordIntervalKey].output +=
-						record.usage?.output_tokens || 0;
-					intervals[recordIntervalKey].cache +=
-						record.usage?.cache_read_input_tokens || 0;
-				}
-			});
-		} else if (timeRange === "today") {
-			// Group by hour for today
-			const startOfToday = now.startOf('day');
-			const currentHour = now.hour();
-
-			for (let i = 0; i <= currentHour; i++) {
-				const hourTime = startOfToday.add(i, 'hour');
-				intervals[hourTime.valueOf()] = { input: 0, output: 0, cache: 0 };
+		} else if (timeRange === "5h" || timeRange === "today") {
+			let hour =
+				timeRange === "5h"
+					? now.subtract(5, 'hour').startOf('hour')
+					: now.startOf('day');
+			while (!hour.isAfter(now)) {
+				starts.push(hour);
+				hour = hour.add(1, 'hour');
 			}
-
-			records.forEach((record) => {
-				const recordTime = dayjs(record.timestamp);
-				const hourStart = recordTime.startOf('hour');
-				const hourKey = hourStart.valueOf();
-
-				if (intervals[hourKey]) {
-					intervals[hourKey].input += record.usage?.input_tokens || 0;
-					intervals[hourKey].output += record.usage?.output_tokens || 0;
-					intervals[hourKey].cache +=
-						record.usage?.cache_read_input_tokens || 0;
-				}
-			});
 		} else {
 			// Group by day for longer periods (7d, week, month)
-			let startDate: dayjs.Dayjs;
-			let days: number;
-
-			if (timeRange === "week") {
-				startDate = now.day(0); // Sunday
-				// Calculate actual days in the current week so far (from start of week to today)
-				const todayStart = now.startOf('day');
-				days = todayStart.diff(startDate, 'day') + 1;
-			} else if (timeRange === "month") {
-				startDate = now.startOf('month');
-				// Calculate actual days in the current month so far (from start of month to today)
-				const todayStart = now.startOf('day');
-				days = todayStart.diff(startDate, 'day') + 1;
-			} else {
-				// For 7d, start from (days-1) days ago to include today
-				days = 7;
-				startDate = now.subtract(days - 1, 'day').startOf('day');
-			}
-
-			for (let i = 0; i < days; i++) {
-				const dayTime = startDate.add(i, 'day');
-				intervals[dayTime.valueOf()] = { input: 0, output: 0, cache: 0 };
+			let day = dayjs(timeRangeQuery(timeRange, now).from);
+			while (!day.isAfter(now)) {
+				starts.push(day);
+				day = day.add(1, 'day');
 			}
-
-			records.forEach((record) => {
-				const recordTime = dayjs(record.timestamp);
-				const dayStart = recordTime.startOf('day');
-				const dayKey = dayStart.valueOf();
-
-				if (intervals[dayKey]) {
-					intervals[dayKey].input += record.usage?.input_tokens || 0;
-					intervals[dayKey].output += record.usage?.output_tokens || 0;
-					intervals[dayKey].cache += record.usage?.cache_read_input_tokens || 0;
-				}
-			});
 		}
 
-		return intervals;
+		return starts;
 	};
 
-	const groupedData = groupDataByInterval(filteredData);
+	const periodFormat =
+		timeRange === "5h" || timeRange === "today" ? HOUR_FORMAT : DAY_FORMAT;
+	const totalsByPeriod = new Map(
+		groups.map((group) => [group.period, group.totals] as const),
+	);
 
 	// Prepare chart data for Recharts
-	const chartData = Object.keys(groupedData)
-		.map(Number)
-		.sort((a, b) => a - b)
-		.map((timestamp) => {
-			const date = dayjs(timestamp);
-			let label: string;
-			if (timeRange === "all") {
-				label = date.format("MMM DD, YYYY");
-			} else if (timeRange === "today") {
-				label = date.format("HH:mm");
-			} else if (timeRange === "5h") {
-				label = date.format("HH:mm");
-			} else {
-				label = date.format("MMM DD");
-			}
-
-			return {
-				time: label,
-				timestamp,
-				"Input Tokens": groupedData[timestamp].input,
-				"Output Tokens": groupedData[timestamp].output,
-				"Cache Read Tokens": groupedData[timestamp].cache,
-			};
-		});
+	const chartData = bucketStarts().map((date) => {
+		let label: string;
+		if (timeRange === "all") {
+			label = date.format("MMM DD, YYYY");
+		} else if (timeRange === "today") {
+			label = date.format("HH:mm");
+		} else if (timeRange === "5h") {
+			label = date.format("HH:mm");
+		} else {
+			label = date.format("MMM DD");
+		}
 
-	if (!data || data.length === 0) {
+		const totals = totalsByPeriod.get(date.format(periodFormat));
+		return {
+			time: label,
+			timestamp: date.valueOf(),
+			"Input Tokens": totals?.input_tokens ?? 0,
+			"Output Tokens": totals?.output_tokens ?? 0,
+			"Cache Read Tokens": totals?.cache_read_input_tokens ?? 0,
+		};
+	});
+
+	if (models.length === 0) {
 		return (
 			<div className="h-96 flex items-center justify-center border rounded-lg bg-muted/20">
 				<p className="text-muted-foreground">{t("usageChart.noData")}</p>
@@ -303,13 +172,13 @@ export function TokenUsageChart({
 					<label htmlFor="model-filter" className="text-sm font-medium">
 						{t("usageChart.modelFilter")}
 					</label>
-					<Select value={selectedModel} onValueChange={setSelectedModel}>
+					<Select value={selectedModel} onValueChange={onModelChange}>
 						<SelectTrigger id="model-filter" className="w-48">
 							<SelectValue placeholder={t("usageChart.allModels")} />
 						</SelectTrigger>
 						<SelectContent>
 							<SelectItem value="all">{t("usageChart.allModels")}</SelectItem>
-							{availableModels.map((model) => (
+							{models.map((model) => (
 								<SelectItem key={model} value={model}>
 									{model}
 								</SelectItem>
@@ -324,7 +193,7 @@ export function TokenUsageChart({
 					</label>
 					<Select
 						value={timeRange}
-						onValueChange={(value: TimeRange) => setTimeRange(value)}
+						onValueChange={(value: TimeRange) => onTimeRangeChange(value)}
 					>
 						<SelectTrigger id="time-range" className="w-48">
 							<SelectValue placeholder={t("usageChart.selectTimeRange")} />
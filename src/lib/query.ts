@@ -1,10 +1,14 @@
 import {
+	keepPreviousData,
+	type QueryClient,
+	useIsFetching,
 	useMutation,
 	useQuery,
 	useQueryClient,
 	useSuspenseQuery,
 } from "@tanstack/react-query";
 import { invoke } from "@/lib/utools-adapter";
+import { getErrorMessage } from "@/lib/utils";
 import { nanoid } from "nanoid";
 import { toast } from "sonner";
 import i18n from "../i18n";
@@ -37,6 +41,9 @@ export interface ConfigStore {
 	createdAt: number;
 	settings: ClaudeSettings;
 	using: boolean;
+	extends?: string[];
+	arrayStrategy?: "replace" | "append" | "union";
+	project?: { path: string; local: boolean };
 }
 
 export interface McpServer {
@@ -69,6 +76,22 @@ export const useConfigFile = (configType: ConfigType) => {
 	});
 };
 
+export interface SettingsDiagnostic {
+	path: string;
+	severity: "error" | "warning";
+	message: string;
+	suggestion?: string;
+}
+
+export const useValidateSettings = (settings: unknown) => {
+	return useQuery({
+		queryKey: ["validate-settings", settings],
+		queryFn: () =>
+			invoke<SettingsDiagnostic[]>("validate_settings", { settings }),
+		enabled: settings !== undefined,
+	});
+};
+
 export const useWriteConfigFile = () => {
 	const queryClient = useQueryClient();
 
@@ -90,27 +113,350 @@ export const useWriteConfigFile = () => {
 			queryClient.invalidateQueries({ queryKey: ["config-files"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(i18n.t("toast.configSaveFailed", { error: errorMessage }));
 		},
 	});
 };
 
+export interface BackupManifest {
+	id: string;
+	createdAt: string;
+	reason: string;
+	claudeDir: string;
+	claudeJsonPath: string;
+	entries: { path: string; size: number; sha256: string }[];
+	archiveSha256?: string;
+}
+
+export interface BackupVerification {
+	id: string;
+	ok: boolean;
+	archiveOk: boolean;
+	corrupted: string[];
+	missing: string[];
+}
+
 export const useBackupClaudeConfigs = () => {
+	const queryClient = useQueryClient();
+
 	return useMutation({
-		mutationFn: () => invoke<void>("backup_claude_configs"),
+		mutationFn: () => invoke<BackupManifest>("backup_claude_configs"),
 		onSuccess: () => {
 			toast.success(i18n.t("toast.backupSuccess"));
+			queryClient.invalidateQueries({ queryKey: ["backups"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(i18n.t("toast.backupFailed", { error: errorMessage }));
 		},
 	});
 };
 
+export const useBackups = () => {
+	return useQuery({
+		queryKey: ["backups"],
+		queryFn: () => invoke<BackupManifest[]>("list_backups"),
+	});
+};
+
+export const useVerifyBackup = () => {
+	return useMutation({
+		mutationFn: (backupId: string) =>
+			invoke<BackupVerification>("verify_backup", { backupId }),
+	});
+};
+
+export const useRestoreBackup = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: ({
+			backupId,
+			entries,
+		}: {
+			backupId: string;
+			entries?: string[];
+		}) => invoke<string[]>("restore_backup", { backupId, entries }),
+		onSuccess: () => {
+			// Restored files can be anything under the Claude directory
+			queryClient.invalidateQueries();
+		},
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
+	});
+};
+
+export const useDeleteBackup = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: (backupId: string) => invoke<void>("delete_backup", { backupId }),
+		onSuccess: () => {
+			queryClient.invalidateQueries({ queryKey: ["backups"] });
+		},
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
+	});
+};
+
+// Drift between active profiles and the live settings files
+
+export interface ProfileDrift {
+	storeId: string;
+	title: string;
+	settingsPath: string;
+	entries: DiffEntry[];
+}
+
+export const useProfileDrift = () => {
+	return useQuery({
+		queryKey: ["profile-drift"],
+		queryFn: () => invoke<ProfileDrift[]>("check_profile_drift"),
+	});
+};
+
+export const useReapplyProfile = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: (storeId: string) => invoke<void>("reapply_profile", { storeId }),
+		onSuccess: () => {
+			queryClient.invalidateQueries({ queryKey: ["profile-drift"] });
+			queryClient.invalidateQueries({ queryKey: ["config-file"] });
+		},
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
+	});
+};
+
+export const useAdoptProfileDrift = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: (storeId: string) =>
+			invoke<ConfigStore>("adopt_profile_drift", { storeId }),
+		onSuccess: () => {
+			queryClient.invalidateQueries({ queryKey: ["profile-drift"] });
+			queryClient.invalidateQueries({ queryKey: ["stores"] });
+			queryClient.invalidateQueries({ queryKey: ["store"] });
+			queryClient.invalidateQueries({ queryKey: ["current-store"] });
+		},
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
+	});
+};
+
+// Profile bundles, for moving profiles between machines
+
+export type SecretMode = "redact" | "include" | "encrypt";
+export type ConflictStrategy = "rename" | "overwrite" | "skip";
+
+export interface BundlePreview {
+	version: number;
+	exportedAt: number;
+	secrets: SecretMode;
+	profiles: {
+		id: string;
+		title: string;
+		conflict?: "id" | "title";
+		existingId?: string;
+		redacted?: string[];
+	}[];
+}
+
+export interface ImportReport {
+	imported: {
+		id: string;
+		title: string;
+		sourceId: string;
+		overwritten: boolean;
+		redacted?: string[];
+	}[];
+	skipped: { id: string; title: string; reason: string }[];
+}
+
+export const useExportProfiles = () => {
+	return useMutation({
+		mutationFn: (body: {
+			storeIds?: string[];
+			secretMode?: SecretMode;
+			passphrase?: string;
+		}) =>
+			invoke<string>("export_profiles", {
+				storeIds: body.storeIds,
+				secretMode: body.secretMode,
+				passphrase: body.passphrase,
+			}),
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
+	});
+};
+
+export const usePreviewProfileBundle = () => {
+	return useMutation({
+		mutationFn: (content: string) =>
+			invoke<BundlePreview>("preview_profile_bundle", { content }),
+	});
+};
+
+export const useImportProfiles = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: (body: {
+			content: string;
+			passphrase?: string;
+			conflict?: ConflictStrategy;
+			resolutions?: Record<string, ConflictStrategy>;
+		}) =>
+			invoke<ImportReport>("import_profiles", {
+				content: body.content,
+				passphrase: body.passphrase,
+				conflict: body.conflict,
+				resolutions: body.resolutions,
+			}),
+		onSuccess: () => {
+			queryClient.invalidateQueries({ queryKey: ["stores"] });
+			queryClient.invalidateQueries({ queryKey: ["current-store"] });
+		},
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
+	});
+};
+
+// Team sync from a shared directory or git checkout
+
+export type SyncItemKind = "profile" | "mcp" | "command" | "agent";
+
+export interface SyncItem {
+	kind: SyncItemKind;
+	name: string;
+}
+
+export interface SyncConflict extends SyncItem {
+	reason: "unmanaged" | "modifiedLocally" | "deletedLocally" | "removedUpstream";
+	sourceChanged: boolean;
+}
+
+export interface SyncStatus {
+	source?: string;
+	pull: boolean;
+	lastSync?: number;
+	managed: (SyncItem & { localHash: string; sourceHash: string })[];
+	detached: SyncItem[];
+	conflicts: SyncConflict[];
+}
+
+export interface SyncReport {
+	pulled: boolean;
+	added: SyncItem[];
+	updated: SyncItem[];
+	removed: SyncItem[];
+	conflicts: SyncConflict[];
+	errors: (SyncItem & { message: string })[];
+}
+
+export const useSyncStatus = () => {
+	return useQuery({
+		queryKey: ["sync-status"],
+		queryFn: () => invoke<SyncStatus>("get_sync_status"),
+	});
+};
+
+export const useSetSyncSource = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: (body: { source?: string; pull: boolean }) =>
+			invoke<SyncStatus>("set_sync_source", {
+				source: body.source,
+				pull: body.pull,
+			}),
+		onSuccess: () => {
+			queryClient.invalidateQueries({ queryKey: ["sync-status"] });
+		},
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
+	});
+};
+
+export const useRunSync = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: () => invoke<SyncReport>("run_sync"),
+		onSuccess: () => {
+			// Sync can touch profiles, MCP servers, commands and agents
+			queryClient.invalidateQueries();
+		},
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
+	});
+};
+
+export const useResolveSyncConflict = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: (body: { item: SyncItem; resolution: "local" | "team" }) =>
+			invoke<SyncStatus>("resolve_sync_conflict", {
+				item: body.item,
+				resolution: body.resolution,
+			}),
+		onSuccess: () => {
+			queryClient.invalidateQueries();
+		},
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
+	});
+};
+
+// Undo journal of destructive changes (deletes, reset)
+
+export interface UndoEntry {
+	id: string;
+	timestamp: number;
+	action: string;
+}
+
+// The tray menu shows an "Undo" item for the latest entry, so it has to be rebuilt too
+const refreshUndo = (queryClient: QueryClient) => {
+	queryClient.invalidateQueries({ queryKey: ["undo"] });
+	invoke<void>("rebuild_tray_menu_command").catch(() => {});
+};
+
+export const useLastUndoableChange = () => {
+	return useQuery({
+		queryKey: ["undo"],
+		queryFn: () => invoke<UndoEntry | null>("get_last_undoable_change"),
+	});
+};
+
+export const useUndoLastChange = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: () => invoke<UndoEntry | null>("undo_last_change"),
+		onSuccess: () => {
+			// An undo can restore stores, settings, MCP servers, commands or agents
+			queryClient.invalidateQueries();
+		},
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
+	});
+};
+
 // Store management hooks
 
 export const useStores = (options?: { storeId?: string }) => {
@@ -154,13 +500,97 @@ export const useCreateConfig = () => {
 			queryClient.invalidateQueries({ queryKey: ["current-store"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
+			toast.error(i18n.t("toast.storeCreateFailed", { error: errorMessage }));
+		},
+	});
+};
+
+export interface ProviderPreset {
+	id: string;
+	name: string;
+	description?: string;
+	baseUrl: string;
+	models: {
+		default?: string;
+		opus?: string;
+		sonnet?: string;
+		haiku?: string;
+		smallFast?: string;
+	};
+	env?: Record<string, unknown>;
+	secrets: { env: string; label: string; placeholder?: string }[];
+	docsUrl?: string;
+	apiKeyUrl?: string;
+	source: "builtin" | "user";
+}
+
+export const useProviderPresets = () => {
+	return useQuery({
+		queryKey: ["provider-presets"],
+		queryFn: () => invoke<ProviderPreset[]>("list_provider_presets"),
+	});
+};
+
+export const useCreateConfigFromPreset = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: ({
+			presetId,
+			title,
+			secrets,
+		}: {
+			presetId: string;
+			title?: string;
+			secrets: Record<string, string>;
+		}) =>
+			invoke<ConfigStore>("create_config_from_preset", {
+				presetId,
+				title,
+				secrets,
+			}),
+		onSuccess: async () => {
+			toast.success(i18n.t("toast.storeCreated"));
+			queryClient.invalidateQueries({ queryKey: ["stores"] });
+			queryClient.invalidateQueries({ queryKey: ["current-store"] });
+		},
+		onError: (error) => {
+			const errorMessage = getErrorMessage(error);
 			toast.error(i18n.t("toast.storeCreateFailed", { error: errorMessage }));
 		},
 	});
 };
 
+export interface ConnectionReport {
+	status:
+		| "ok"
+		| "missingCredentials"
+		| "authFailed"
+		| "unknownModel"
+		| "endpointNotFound"
+		| "rateLimited"
+		| "serverError"
+		| "unexpectedResponse"
+		| "invalidUrl"
+		| "dns"
+		| "tls"
+		| "connectionFailed"
+		| "timeout";
+	baseUrl: string;
+	model: string;
+	httpStatus?: number;
+	latencyMs?: number;
+	message?: string;
+}
+
+export const useTestConfigConnection = () => {
+	return useMutation({
+		mutationFn: (storeId: string) =>
+			invoke<ConnectionReport>("test_config_connection", { storeId }),
+	});
+};
+
 export const useDeleteConfig = () => {
 	const queryClient = useQueryClient();
 
@@ -172,11 +602,11 @@ export const useDeleteConfig = () => {
 		onSuccess: async () => {
 			toast.success(i18n.t("toast.storeDeleted"));
 			queryClient.invalidateQueries({ queryKey: ["stores"] });
+			refreshUndo(queryClient);
 			queryClient.invalidateQueries({ queryKey: ["current-store"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(i18n.t("toast.storeDeleteFailed", { error: errorMessage }));
 		},
 	});
@@ -202,8 +632,7 @@ export const useSetUsingConfig = () => {
 			queryClient.invalidateQueries({ queryKey: ["config-file", "user"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			if (window.utools) {
 				window.utools.showNotification(i18n.t("configQuickSwitch.switchError"));
 			} else {
@@ -231,8 +660,7 @@ export const useSetCurrentConfig = () => {
 			queryClient.invalidateQueries({ queryKey: ["config-file", "user"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			if (window.utools) {
 				window.utools.showNotification(i18n.t("configQuickSwitch.switchError"));
 			} else {
@@ -251,6 +679,7 @@ export const useResetToOriginalConfig = () => {
 			queryClient.invalidateQueries({ queryKey: ["stores"] });
 			queryClient.invalidateQueries({ queryKey: ["current-store"] });
 			queryClient.invalidateQueries({ queryKey: ["config-file", "user"] });
+			refreshUndo(queryClient);
 		},
 	});
 };
@@ -284,13 +713,157 @@ export const useUpdateConfig = () => {
 			}
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
+			toast.error(i18n.t("toast.storeSaveFailed", { error: errorMessage }));
+		},
+	});
+};
+
+export interface ConfigRevision {
+	id: string;
+	timestamp: number;
+	title: string;
+	settings: unknown;
+	extends?: string[];
+	arrayStrategy?: "replace" | "append" | "union";
+	trigger: "created" | "updated" | "restored" | "deleted";
+}
+
+export interface DiffEntry {
+	path: string;
+	kind: "added" | "removed" | "modified";
+	old?: unknown;
+	new?: unknown;
+}
+
+export const useConfigRevisions = (storeId: string) => {
+	return useQuery({
+		queryKey: ["config-revisions", storeId],
+		queryFn: () =>
+			invoke<ConfigRevision[]>("list_config_revisions", { storeId }),
+		enabled: !!storeId,
+	});
+};
+
+export const useConfigRevisionDiff = (
+	storeId: string,
+	fromRevision: string,
+	toRevision?: string,
+) => {
+	return useQuery({
+		queryKey: ["config-revision-diff", storeId, fromRevision, toRevision],
+		queryFn: () =>
+			invoke<DiffEntry[]>("diff_config_revisions", {
+				storeId,
+				fromRevision,
+				toRevision,
+			}),
+		enabled: !!storeId && !!fromRevision,
+	});
+};
+
+// Without storeB the profile is compared with the settings file it applies to
+export const useDiffConfigs = (storeA: string, storeB?: string) => {
+	return useQuery({
+		queryKey: ["config-diff", storeA, storeB],
+		queryFn: () => invoke<DiffEntry[]>("diff_configs", { storeA, storeB }),
+		enabled: !!storeA,
+	});
+};
+
+export const useRestoreConfigRevision = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: ({
+			storeId,
+			revisionId,
+		}: {
+			storeId: string;
+			revisionId: string;
+		}) =>
+			invoke<ConfigStore>("restore_config_revision", { storeId, revisionId }),
+		onSuccess: (data) => {
+			toast.success(i18n.t("toast.storeSaved", { title: data.title }));
+			queryClient.invalidateQueries({ queryKey: ["stores"] });
+			queryClient.invalidateQueries({ queryKey: ["store", data.id] });
+			queryClient.invalidateQueries({ queryKey: ["current-store"] });
+			queryClient.invalidateQueries({ queryKey: ["config-revisions", data.id] });
+			if (data.using) {
+				queryClient.invalidateQueries({ queryKey: ["config-file", "user"] });
+			}
+		},
+		onError: (error) => {
+			const errorMessage = getErrorMessage(error);
 			toast.error(i18n.t("toast.storeSaveFailed", { error: errorMessage }));
 		},
 	});
 };
 
+export interface ClaudeHome {
+	name: string;
+	path: string;
+}
+
+export interface ClaudeHomes {
+	homes: ClaudeHome[];
+	active?: string;
+}
+
+export const useClaudeHomes = () => {
+	return useQuery({
+		queryKey: ["claude-homes"],
+		queryFn: () => invoke<ClaudeHomes>("list_claude_homes"),
+	});
+};
+
+export const useAddClaudeHome = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: ({ name, path }: { name: string; path: string }) =>
+			invoke<ClaudeHomes>("add_claude_home", { name, path }),
+		onSuccess: () => {
+			queryClient.invalidateQueries({ queryKey: ["claude-homes"] });
+		},
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
+	});
+};
+
+export const useRemoveClaudeHome = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: (name: string) =>
+			invoke<ClaudeHomes>("remove_claude_home", { name }),
+		onSuccess: () => {
+			// Removing the active home also releases its active profile
+			queryClient.invalidateQueries();
+		},
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
+	});
+};
+
+export const useSetActiveClaudeHome = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: (name?: string) =>
+			invoke<ClaudeHomes>("set_active_claude_home", { name }),
+		onSuccess: () => {
+			// Every Claude file now comes from another directory
+			queryClient.invalidateQueries();
+		},
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
+	});
+};
+
 export interface UpdateInfo {
 	available: boolean;
 	version?: string;
@@ -322,8 +895,7 @@ export const useInstallAndRestart = () => {
 		},
 		onError: (error) => {
 			console.log("❌ Frontend: Update installation failed", error);
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(i18n.t("toast.updateInstallFailed", { error: errorMessage }));
 		},
 	});
@@ -355,8 +927,7 @@ export const useUpdateGlobalMcpServer = () => {
 			queryClient.invalidateQueries({ queryKey: ["global-mcp-servers"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(`Failed to update MCP server: ${errorMessage}`);
 		},
 	});
@@ -379,8 +950,7 @@ export const useAddGlobalMcpServer = () => {
 			queryClient.invalidateQueries({ queryKey: ["global-mcp-servers"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(`Failed to add MCP server: ${errorMessage}`);
 		},
 	});
@@ -406,10 +976,10 @@ export const useDeleteGlobalMcpServer = () => {
 		onSuccess: () => {
 			toast.success("MCP server deleted successfully");
 			queryClient.invalidateQueries({ queryKey: ["global-mcp-servers"] });
+			refreshUndo(queryClient);
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(`Failed to delete MCP server: ${errorMessage}`);
 		},
 	});
@@ -417,8 +987,22 @@ export const useDeleteGlobalMcpServer = () => {
 
 export interface UsageData {
 	input_tokens?: number;
+	cache_creation_input_tokens?: number;
 	cache_read_input_tokens?: number;
 	output_tokens?: number;
+	cache_creation?: {
+		ephemeral_5m_input_tokens?: number;
+		ephemeral_1h_input_tokens?: number;
+		[key: string]: unknown;
+	};
+	service_tier?: string;
+	server_tool_use?: {
+		web_search_requests?: number;
+		web_fetch_requests?: number;
+		[key: string]: unknown;
+	};
+	// Fields added by newer Claude Code versions are passed through as they are
+	[key: string]: unknown;
 }
 
 export interface ProjectUsageRecord {
@@ -426,12 +1010,146 @@ export interface ProjectUsageRecord {
 	timestamp: string;
 	model?: string;
 	usage?: UsageData;
+	project_path?: string;
+	session_id?: string;
+	// Missing when the model has no price
+	cost_usd?: number;
+}
+
+export interface SkippedLine {
+	path: string;
+	line: number;
+	message: string;
+}
+
+// failures is capped per file, skipped_lines counts every malformed line
+export interface UsageScan {
+	records: ProjectUsageRecord[];
+	skipped_lines: number;
+	failures: SkippedLine[];
+}
+
+export interface UsageCosts {
+	total_usd: number;
+	by_day: Record<string, number>;
+	by_model: Record<string, number>;
+	by_project: Record<string, number>;
+	unpriced_models: string[];
 }
 
-export const useProjectUsageFiles = () => {
+export const useUsageCosts = () => {
 	return useQuery({
-		queryKey: ["project-usage-files"],
-		queryFn: () => invoke<ProjectUsageRecord[]>("read_project_usage_files"),
+		queryKey: ["usage-costs"],
+		queryFn: () => invoke<UsageCosts>("get_usage_costs"),
+	});
+};
+
+export type UsageDimension =
+	| "hour"
+	| "day"
+	| "week"
+	| "month"
+	| "model"
+	| "project"
+	| "session";
+
+// from/to take a local date (2025-01-31) or an RFC 3339 timestamp, to is exclusive
+export interface UsageQuery {
+	from?: string;
+	to?: string;
+	group_by?: UsageDimension[];
+	models?: string[];
+	projects?: string[];
+	sessions?: string[];
+}
+
+export interface UsageTotals {
+	requests: number;
+	input_tokens: number;
+	output_tokens: number;
+	cache_creation_input_tokens: number;
+	cache_read_input_tokens: number;
+	web_search_requests: number;
+	cost_usd: number;
+	unpriced_requests: number;
+}
+
+export interface UsageGroup {
+	period?: string;
+	model?: string;
+	project_path?: string;
+	session_id?: string;
+	totals: UsageTotals;
+}
+
+export interface UsageQueryResult {
+	total: UsageTotals;
+	groups: UsageGroup[];
+	// Transcript lines left out, as in UsageScan
+	skipped_lines: number;
+	failures: SkippedLine[];
+}
+
+export const useQueryUsage = (query: UsageQuery) => {
+	return useQuery({
+		queryKey: ["usage-query", query],
+		queryFn: async () => {
+			const result = await invoke<UsageQueryResult>("query_usage", { query });
+			// The usage index was just refreshed, and with it the tray's figure for today
+			invoke<void>("rebuild_tray_menu_command").catch(() => {});
+			return result;
+		},
+		// Keep showing the previous range while another one loads
+		placeholderData: keepPreviousData,
+	});
+};
+
+// Re-reads the transcripts behind every usage query and the costs
+export const useRefreshUsage = () => {
+	const queryClient = useQueryClient();
+	const isRefreshing =
+		useIsFetching({ queryKey: ["usage-query"] }) +
+			useIsFetching({ queryKey: ["usage-costs"] }) >
+		0;
+
+	const refresh = () => {
+		queryClient.invalidateQueries({ queryKey: ["usage-query"] });
+		queryClient.invalidateQueries({ queryKey: ["usage-costs"] });
+	};
+
+	return { refresh, isRefreshing };
+};
+
+// USD per million tokens, `*` in the pattern matches anything
+export interface ModelPrice {
+	pattern: string;
+	input: number;
+	output: number;
+	cacheWrite: number;
+	cacheRead: number;
+}
+
+export const useModelPrices = () => {
+	return useQuery({
+		queryKey: ["model-prices"],
+		queryFn: () => invoke<ModelPrice[]>("get_model_prices"),
+	});
+};
+
+export const useSetModelPrices = () => {
+	const queryClient = useQueryClient();
+
+	return useMutation({
+		mutationFn: (prices: ModelPrice[]) =>
+			invoke<void>("set_model_prices", { prices }),
+		onSuccess: () => {
+			queryClient.invalidateQueries({ queryKey: ["model-prices"] });
+			queryClient.invalidateQueries({ queryKey: ["usage-costs"] });
+			queryClient.invalidateQueries({ queryKey: ["usage-query"] });
+		},
+		onError: (error) => {
+			toast.error(getErrorMessage(error));
+		},
 	});
 };
 
@@ -461,8 +1179,7 @@ export const useWriteClaudeMemory = () => {
 			queryClient.invalidateQueries({ queryKey: ["claude-memory"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(`Failed to save memory: ${errorMessage}`);
 		},
 	});
@@ -499,16 +1216,15 @@ export const useWriteClaudeConfigFile = () => {
 	const queryClient = useQueryClient();
 
 	return useMutation({
-		mutationFn: (content: unknown) =>
-			invoke<void>("write_claude_config_file", { content }),
+		mutationFn: ({ content, base }: { content: unknown; base?: unknown }) =>
+			invoke<void>("write_claude_config_file", { content, base }),
 		onSuccess: () => {
 			toast.success("Claude configuration saved successfully");
 			queryClient.invalidateQueries({ queryKey: ["claude-config-file"] });
 			queryClient.invalidateQueries({ queryKey: ["claude-projects"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(`Failed to save Claude configuration: ${errorMessage}`);
 		},
 	});
@@ -536,8 +1252,7 @@ export const useUpdateNotificationSettings = () => {
 			queryClient.invalidateQueries({ queryKey: ["notification-settings"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(`Failed to update notification settings: ${errorMessage}`);
 		},
 	});
@@ -566,8 +1281,7 @@ export const useWriteClaudeCommand = () => {
 			queryClient.invalidateQueries({ queryKey: ["claude-commands"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(i18n.t("toast.commandSaveFailed", { error: errorMessage }));
 		},
 	});
@@ -582,10 +1296,10 @@ export const useDeleteClaudeCommand = () => {
 		onSuccess: () => {
 			toast.success(i18n.t("toast.commandDeleted"));
 			queryClient.invalidateQueries({ queryKey: ["claude-commands"] });
+			refreshUndo(queryClient);
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(i18n.t("toast.commandDeleteFailed", { error: errorMessage }));
 		},
 	});
@@ -614,8 +1328,7 @@ export const useWriteClaudeAgent = () => {
 			queryClient.invalidateQueries({ queryKey: ["claude-agents"] });
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(`Failed to save agent: ${errorMessage}`);
 		},
 	});
@@ -630,10 +1343,10 @@ export const useDeleteClaudeAgent = () => {
 		onSuccess: () => {
 			toast.success("Agent deleted successfully");
 			queryClient.invalidateQueries({ queryKey: ["claude-agents"] });
+			refreshUndo(queryClient);
 		},
 		onError: (error) => {
-			const errorMessage =
-				error instanceof Error ? error.message : String(error);
+			const errorMessage = getErrorMessage(error);
 			toast.error(`Failed to delete agent: ${errorMessage}`);
 		},
 	});
@@ -24,3 +24,43 @@ export function formatLargeNumber(num: number): string {
 	}
 	return num.toString();
 }
+
+// Shape of the errors returned by the Tauri commands
+export interface AppError {
+	kind:
+		| "notFound"
+		| "permissionDenied"
+		| "io"
+		| "parse"
+		| "validation"
+		| "conflict"
+		| "network"
+		| "locked"
+		| "other";
+	message: string;
+	path?: string;
+	line?: number;
+	column?: number;
+	diagnostics?: {
+		path: string;
+		severity: "error" | "warning";
+		message: string;
+		suggestion?: string;
+	}[];
+}
+
+export function isAppError(error: unknown): error is AppError {
+	return (
+		typeof error === "object" &&
+		error !== null &&
+		"kind" in error &&
+		"message" in error
+	);
+}
+
+export function getErrorMessage(error: unknown): string {
+	if (error instanceof Error || isAppError(error)) {
+		return error.message;
+	}
+	return String(error);
+}
@@ -163,6 +163,7 @@ export type {
   CommandFile,
   AgentFile,
   ProjectUsageRecord,
+  UsageScan,
   MemoryFile,
   ProjectConfig,
   NotificationSettings
@@ -1,6 +1,7 @@
 import { QueryClient, QueryClientProvider } from "@tanstack/react-query";
 import React from "react";
 import ReactDOM from "react-dom/client";
+import { ConfigWatcher } from "./components/ConfigWatcher";
 import { ErrorBoundary } from "./components/ErrorBoundary";
 import { ThemeProvider } from "./components/theme-provider";
 import { UtoolsLifecycle } from "./components/UtoolsLifecycle";
@@ -31,7 +32,9 @@ ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
 					disableTransitionOnChange
 				>
 					<UtoolsLifecycle>
-						<Router />
+						<ConfigWatcher>
+							<Router />
+						</ConfigWatcher>
 					</UtoolsLifecycle>
 					<Toaster />
 				</ThemeProvider>
@@ -32,7 +32,14 @@ import { useDeleteConfig, useStore, useUpdateConfig } from "../lib/query";
 type FieldConfig = {
 	label: string;
 	name: string;
-	type: "text" | "number" | "boolean" | "textarea" | "tags" | "select";
+	type:
+		| "text"
+		| "number"
+		| "boolean"
+		| "textarea"
+		| "tags"
+		| "select"
+		| "secret";
 	description?: string;
 	placeholder?: string;
 	options?: string[];
@@ -43,6 +50,26 @@ type SectionConfig = {
 	fields: FieldConfig[];
 };
 
+// A token kept in the secret vault: {"$secret": "<name>"}
+function isSecretRef(value: any): boolean {
+	return (
+		isPlainObject(value) &&
+		Object.keys(value).length === 1 &&
+		typeof value.$secret === "string"
+	);
+}
+
+// Paths of the vault references in settings, e.g. env.ANTHROPIC_AUTH_TOKEN
+function secretRefPaths(obj: any, prefix = ""): string[] {
+	if (!isPlainObject(obj)) {
+		return [];
+	}
+	return Object.entries(obj).flatMap(([key, value]) => {
+		const path = prefix ? `${prefix}.${key}` : key;
+		return isSecretRef(value) ? [path] : secretRefPaths(value, path);
+	});
+}
+
 // Helper function to check if a leaf value should be included
 function isValidValue(value: any): boolean {
 	// Exclude undefined, null, NaN
@@ -120,7 +147,7 @@ const createFields = (t: (key: string) => string): SectionConfig[] => [
 			{
 				label: "ANTHROPIC_AUTH_TOKEN",
 				name: "env.ANTHROPIC_AUTH_TOKEN",
-				type: "text",
+				type: "secret",
 				description: "This value will be sent as the Authorization header.",
 			},
 			{
@@ -157,7 +184,7 @@ const createFields = (t: (key: string) => string): SectionConfig[] => [
 			{
 				label: "ANTHROPIC_API_KEY",
 				name: "env.ANTHROPIC_API_KEY",
-				type: "text",
+				type: "secret",
 				description:
 					"API key sent as X-Api-Key header, typically for the Claude SDK",
 			},
@@ -623,7 +650,8 @@ export function ConfigEditorPage() {
 	fields.forEach((section) => {
 		section.fields.forEach((field) => {
 			const value = get(storeData.settings, field.name);
-			if (value !== undefined) {
+			// Tokens in the vault stay out of the form, an empty field keeps them
+			if (value !== undefined && !isSecretRef(value)) {
 				defaultValues[field.name] = value;
 			}
 		});
@@ -651,7 +679,10 @@ export function ConfigEditorPage() {
 
 		for (const key in obj) {
 			const path = prefix ? `${prefix}.${key}` : key;
-			if (isPlainObject(obj[key])) {
+			if (isSecretRef(obj[key])) {
+				// Shown as an empty secret field, the reference itself is kept on save
+				result[path] = "";
+			} else if (isPlainObject(obj[key])) {
 				Object.assign(result, flattenJSON(obj[key], path));
 			} else {
 				result[path] = obj[key];
@@ -669,6 +700,13 @@ export function ConfigEditorPage() {
 		// Merge with original storeData.settings to preserve unknown fields
 		const merged = { ...storeData.settings, ...converted["settings.json"] };
 
+		// Secret fields left empty keep the token that is in the vault
+		secretRefPaths(storeData.settings).forEach((path) => {
+			if (get(merged, path) === undefined) {
+				set(merged, path, get(storeData.settings, path));
+			}
+		});
+
 		return {
 			title: configName,
 			settings: merged,
@@ -968,6 +1006,19 @@ export function ConfigEditorPage() {
 													placeholder={field.placeholder}
 												/>
 											))
+											.with({ type: "secret" }, () => (
+												<Input
+													{...register(field.name)}
+													type="password"
+													autoComplete="off"
+													className={`text-sm w-1/2 h-7 ${highlightedField === field.name ? "animate-blink" : ""}`}
+													placeholder={
+														isSecretRef(get(storeData.settings, field.name))
+															? t("configEditor.secretStored")
+															: field.placeholder
+													}
+												/>
+											))
 											.with({ type: "number" }, () => (
 												<Input
 													{...register(field.name, {
@@ -1,11 +1,8 @@
-import { Kimi, Minimax, ZAI } from "@lobehub/icons";
-import { CopyIcon, EllipsisVerticalIcon, PencilLineIcon, PlusIcon } from "lucide-react";
+import { CopyIcon, EllipsisVerticalIcon, PencilLineIcon, PlugIcon, PlusIcon } from "lucide-react";
 import { useTranslation } from "react-i18next";
 import { useNavigate } from "react-router-dom";
 import { toast } from "sonner";
-import { GLMDialog } from "@/components/GLMBanner";
-import { KimiDialog } from "@/components/KimiDialog";
-import { MiniMaxDialog } from "@/components/MiniMaxDialog";
+import { ProviderPresetDialog } from "@/components/ProviderPresetDialog";
 import { Button } from "@/components/ui/button";
 import { ButtonGroup } from "@/components/ui/button-group";
 import {
@@ -95,39 +92,15 @@ function ConfigStores() {
 					</p>
 
 					<div className="mt-4 space-y-2">
-						<GLMDialog
+						<ProviderPresetDialog
 							trigger={
 								<Button
 									variant="ghost"
 									className="text-muted-foreground text-sm"
 									size="sm"
 								>
-									<ZAI />
-									{t("glm.useZhipuGlm")}
-								</Button>
-							}
-						/>
-						<MiniMaxDialog
-							trigger={
-								<Button
-									variant="ghost"
-									className="text-muted-foreground text-sm"
-									size="sm"
-								>
-									<Minimax />
-									{t("minimax.useMiniMax")}
-								</Button>
-							}
-						/>
-						<KimiDialog
-							trigger={
-								<Button
-									variant="ghost"
-									className="text-muted-foreground text-sm"
-									size="sm"
-								>
-									<Kimi />
-									{t("kimi.useKimi")}
+									<PlugIcon size={14} />
+									{t("providerPreset.use")}
 								</Button>
 							}
 						/>
@@ -173,27 +146,11 @@ function ConfigStores() {
 								</Button>
 							</DropdownMenuTrigger>
 							<DropdownMenuContent align="end">
-								<GLMDialog
-									trigger={
-										<DropdownMenuItem onSelect={(e) => e.preventDefault()}>
-											<ZAI />
-											{t("glm.useZhipuGlm")}
-										</DropdownMenuItem>
-									}
-								/>
-								<MiniMaxDialog
-									trigger={
-										<DropdownMenuItem onSelect={(e) => e.preventDefault()}>
-											<Minimax />
-											{t("minimax.useMiniMax")}
-										</DropdownMenuItem>
-									}
-								/>
-								<KimiDialog
+								<ProviderPresetDialog
 									trigger={
 										<DropdownMenuItem onSelect={(e) => e.preventDefault()}>
-											<Kimi />
-											{t("kimi.useKimi")}
+											<PlugIcon />
+											{t("providerPreset.use")}
 										</DropdownMenuItem>
 									}
 								/>
@@ -1,37 +1,57 @@
+import dayjs from "dayjs";
 import {
 	ArrowDownIcon,
 	ArrowUpIcon,
 	CircleDotDashedIcon,
+	DollarSignIcon,
 	RefreshCwIcon,
 } from "lucide-react";
-import { useEffect, useState } from "react";
+import { useState } from "react";
 import { useTranslation } from "react-i18next";
 import { ActivityGrid } from "@/components/ActivityGrid";
-import { TokenUsageChart } from "@/components/TokenUsageChart";
+import {
+	type TimeRange,
+	TokenUsageChart,
+	timeRangeQuery,
+} from "@/components/TokenUsageChart";
 import { Button } from "@/components/ui/button";
 import { TooltipProvider } from "@/components/ui/tooltip";
-import { type ProjectUsageRecord, useProjectUsageFiles } from "@/lib/query";
+import { useQueryUsage, useRefreshUsage, useUsageCosts } from "@/lib/query";
 import { cn, formatLargeNumber } from "@/lib/utils";
 
 export function UsagePage() {
 	const { t } = useTranslation();
+	// Time ranges are relative to when the page was opened or last refreshed
+	const [now, setNow] = useState(() => dayjs());
+	const [selectedModel, setSelectedModel] = useState("all");
+	const [timeRange, setTimeRange] = useState<TimeRange>("5h");
+
+	// Aggregated by the backend, the page never holds the individual records
 	const {
-		data: usageData,
+		data: byModel,
 		isLoading,
 		error,
-		refetch,
-		isRefetching,
-	} = useProjectUsageFiles();
-	const [filteredUsageData, setFilteredUsageData] = useState<
-		ProjectUsageRecord[]
-	>([]);
+	} = useQueryUsage({ group_by: ["model"] });
+	const { data: byDay } = useQueryUsage({
+		from: now.startOf("year").format("YYYY-MM-DD"),
+		group_by: ["day"],
+	});
+	const { data: selected } = useQueryUsage({
+		...timeRangeQuery(timeRange, now),
+		models: selectedModel === "all" ? [] : [selectedModel],
+	});
+	const { data: costs } = useUsageCosts();
+	const { refresh, isRefreshing } = useRefreshUsage();
+
+	const models = (byModel?.groups ?? [])
+		.map((group) => group.model)
+		.filter((model): model is string => !!model && model !== "unknown");
+	const selectedTotal = selected?.total;
 
-	// Initialize filtered data with full data
-	useEffect(() => {
-		if (usageData) {
-			setFilteredUsageData(usageData);
-		}
-	}, [usageData]);
+	// Only the Tauri backend knows model prices
+	const hasCost =
+		!!selectedTotal &&
+		selectedTotal.requests > selectedTotal.unpriced_requests;
 
 	return (
 		<TooltipProvider>
@@ -54,9 +74,10 @@ export function UsagePage() {
 
 					<div className="flex items-center gap-2">
 						<Button
-							disabled={isRefetching || isLoading}
+							disabled={isRefreshing || isLoading}
 							onClick={(_) => {
-								refetch();
+								setNow(dayjs());
+								refresh();
 							}}
 							variant="ghost"
 							size="sm"
@@ -64,10 +85,10 @@ export function UsagePage() {
 						>
 							<RefreshCwIcon
 								className={cn({
-									"animate-spin": isRefetching || isLoading,
+									"animate-spin": isRefreshing || isLoading,
 								})}
 							/>
-							{isRefetching || isLoading
+							{isRefreshing || isLoading
 								? t("usage.refreshing")
 								: t("usage.refresh")}
 						</Button>
@@ -107,24 +128,37 @@ export function UsagePage() {
 					</div>
 				) : error ? (
 					<p>{t("usage.error", { error: error.message })}</p>
-				) : usageData && usageData.length > 0 ? (
+				) : byModel && byModel.total.requests > 0 ? (
 					<>
+						{byModel.skipped_lines > 0 && (
+							<p
+								className="text-xs text-muted-foreground"
+								title={byModel.failures
+									.map(
+										(failure) =>
+											`${failure.path}:${failure.line}: ${failure.message}`,
+									)
+									.join("\n")}
+							>
+								{t("usage.skippedLines", { count: byModel.skipped_lines })}
+							</p>
+						)}
 						<div className=" rounded-lg pb-5">
-							<ActivityGrid data={usageData} />
+							<ActivityGrid data={byDay?.groups ?? []} />
 						</div>
-						<div className="grid grid-cols-1 md:grid-cols-3 gap-4">
+						<div
+							className={cn(
+								"grid grid-cols-1 gap-4",
+								hasCost ? "md:grid-cols-4" : "md:grid-cols-3",
+							)}
+						>
 							<div className="bg-blue-50 border-blue-100 text-blue-700 border-2 p-4 rounded-lg space-y-2 dark:bg-blue-950/20 dark:border-blue-900/30 dark:text-blue-300">
 								<div className="flex items-center gap-2">
 									<ArrowDownIcon size={12} />
 									<h3 className="font-medium">{t("usage.inputTokens")}</h3>
 								</div>
 								<p className="text-2xl font-bold">
-									{formatLargeNumber(
-										filteredUsageData.reduce(
-											(sum, record) => sum + (record.usage?.input_tokens || 0),
-											0,
-										),
-									)}
+									{formatLargeNumber(selectedTotal?.input_tokens ?? 0)}
 								</p>
 							</div>
 							<div className="bg-emerald-50 border-emerald-100 text-emerald-700 border-2 p-4 rounded-lg space-y-2 dark:bg-emerald-950/20 dark:border-emerald-900/30 dark:text-emerald-300">
@@ -133,12 +167,7 @@ export function UsagePage() {
 									<h3 className="font-medium">{t("usage.outputTokens")}</h3>
 								</div>
 								<p className="text-2xl font-bold">
-									{formatLargeNumber(
-										filteredUsageData.reduce(
-											(sum, record) => sum + (record.usage?.output_tokens || 0),
-											0,
-										),
-									)}
+									{formatLargeNumber(selectedTotal?.output_tokens ?? 0)}
 								</p>
 							</div>
 							<div className="bg-amber-50 border-amber-100 text-amber-700 border-2 p-4 rounded-lg space-y-2 dark:bg-amber-950/20 dark:border-amber-900/30 dark:text-amber-300">
@@ -148,31 +177,38 @@ export function UsagePage() {
 								</div>
 								<p className="text-2xl font-bold">
 									{formatLargeNumber(
-										filteredUsageData.reduce(
-											(sum, record) =>
-												sum + (record.usage?.cache_read_input_tokens || 0),
-											0,
-										),
+										selectedTotal?.cache_read_input_tokens ?? 0,
 									)}
 								</p>
 							</div>
-
-							{/* <div className="bg-zinc-50 p-4 rounded-lg space-y-2">
-                <div className="flex items-start justify-between">
-                  <h3 className="font-medium">Cost</h3>
-                  <Button variant="ghost" size="icon" className="-my-2 -mr-2">
-                    <SettingsIcon />
-                  </Button>
-                </div>
-                <p className="text-2xl font-bold">
-                  $0.00
-                </p>
-              </div> */}
+							{hasCost && (
+								<div className="bg-violet-50 border-violet-100 text-violet-700 border-2 p-4 rounded-lg space-y-2 dark:bg-violet-950/20 dark:border-violet-900/30 dark:text-violet-300">
+									<div className="flex items-center gap-2">
+										<DollarSignIcon size={12} />
+										<h3 className="font-medium">{t("usage.cost")}</h3>
+									</div>
+									<p className="text-2xl font-bold">
+										${selectedTotal?.cost_usd.toFixed(2)}
+									</p>
+								</div>
+							)}
 						</div>
+						{hasCost && costs && costs.unpriced_models.length > 0 && (
+							<p className="text-xs text-muted-foreground">
+								{t("usage.unpricedModels", {
+									models: costs.unpriced_models.join(", "),
+								})}
+							</p>
+						)}
 						<div className="mt-6 bg-card p-6 rounded-lg w-full min-w-0 border">
 							<TokenUsageChart
-								data={usageData}
-								onFilteredDataChange={setFilteredUsageData}
+								groups={selected?.groups ?? []}
+								models={models}
+								selectedModel={selectedModel}
+								onModelChange={setSelectedModel}
+								timeRange={timeRange}
+								onTimeRangeChange={setTimeRange}
+								now={now}
 							/>
 						</div>
 					</>
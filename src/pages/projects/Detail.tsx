@@ -33,7 +33,7 @@ import {
 	useWriteClaudeConfigFile,
 } from "../../lib/query";
 import { useCodeMirrorTheme } from "../../lib/use-codemirror-theme";
-import { cn } from "../../lib/utils";
+import { cn, getErrorMessage } from "../../lib/utils";
 
 export function Detail() {
 	const { t } = useTranslation();
@@ -86,7 +86,10 @@ export function Detail() {
 			}
 			updatedConfig.projects[decodedPath] = parsedContent;
 
-			writeClaudeConfig.mutate(updatedConfig);
+			writeClaudeConfig.mutate({
+				content: updatedConfig,
+				base: claudeConfig.content,
+			});
 			setHasChanges(false);
 		} catch (error) {
 			toast.error(t("projects.detail.invalidJson"));
@@ -155,9 +158,7 @@ export function Detail() {
 			<div className="w-full h-full flex items-center justify-center">
 				<div className="space-y-6 px-4">
 					{t("projects.detail.loadError")}{" "}
-					{(configError || projectsError) instanceof Error
-						? (configError || projectsError)?.message
-						: String(configError || projectsError)}
+					{getErrorMessage(configError || projectsError)}
 				</div>
 			</div>
 		);
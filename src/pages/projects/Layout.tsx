@@ -2,6 +2,7 @@ import { FolderOpenIcon, Loader2 } from "lucide-react";
 import { Outlet } from "react-router-dom";
 import { Alert, AlertDescription } from "../../components/ui/alert";
 import { useClaudeProjects } from "../../lib/query";
+import { getErrorMessage } from "../../lib/utils";
 
 export function ProjectsLayout() {
 	const { data: projects, isLoading, error } = useClaudeProjects();
@@ -25,7 +26,7 @@ export function ProjectsLayout() {
 				<Alert>
 					<AlertDescription>
 						Failed to load projects:{" "}
-						{error instanceof Error ? error.message : String(error)}
+						{getErrorMessage(error)}
 					</AlertDescription>
 				</Alert>
 			</div>
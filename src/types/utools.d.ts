@@ -19,6 +19,14 @@ interface UtoolsServices {
   getStore(storeId: string): ConfigStore;
   resetToOriginalConfig(): void;
 
+  // 服务商预设
+  listProviderPresets(): import("../lib/query").ProviderPreset[];
+  createConfigFromPreset(
+    presetId: string,
+    title: string | undefined,
+    secrets: Record<string, string>
+  ): ConfigStore;
+
   // MCP 服务器管理
   getGlobalMcpServers(): Record<string, McpServer>;
   updateGlobalMcpServer(serverName: string, serverConfig: any): void;
@@ -36,7 +44,11 @@ interface UtoolsServices {
   deleteClaudeAgent(agentName: string): void;
 
   // 使用量统计
-  readProjectUsageFiles(): ProjectUsageRecord[];
+  readProjectUsageFiles(): UsageScan;
+  queryUsage(
+    query: import("../lib/query").UsageQuery
+  ): import("../lib/query").UsageQueryResult;
+  getUsageCosts(): import("../lib/query").UsageCosts;
 
   // Memory 管理
   readClaudeMemory(): MemoryFile;
@@ -105,12 +117,40 @@ export interface ProjectUsageRecord {
   timestamp: string;
   model: string | null;
   usage: UsageData | null;
+  project_path?: string | null;
+  session_id?: string | null;
+  cost_usd?: number;
+}
+
+export interface SkippedLine {
+  path: string;
+  line: number;
+  message: string;
+}
+
+export interface UsageScan {
+  records: ProjectUsageRecord[];
+  skipped_lines: number;
+  failures: SkippedLine[];
 }
 
 export interface UsageData {
   input_tokens?: number;
+  cache_creation_input_tokens?: number;
   cache_read_input_tokens?: number;
   output_tokens?: number;
+  cache_creation?: {
+    ephemeral_5m_input_tokens?: number;
+    ephemeral_1h_input_tokens?: number;
+    [key: string]: unknown;
+  };
+  service_tier?: string;
+  server_tool_use?: {
+    web_search_requests?: number;
+    web_fetch_requests?: number;
+    [key: string]: unknown;
+  };
+  [key: string]: unknown;
 }
 
 export interface MemoryFile {